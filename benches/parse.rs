@@ -0,0 +1,91 @@
+//! Compares the response parser backends on large documents.
+
+#[macro_use]
+extern crate criterion;
+extern crate xmlrpc;
+
+use criterion::{Criterion, Throughput};
+use xmlrpc::{parse_response, Map, ParserBackend, ParserOptions, Value};
+
+/// Formats `value` as a `<methodResponse>` document.
+fn response(value: Value) -> Vec<u8> {
+    let mut xml = Vec::new();
+    xml.extend_from_slice(b"<?xml version=\"1.0\" encoding=\"utf-8\"?>");
+    xml.extend_from_slice(b"<methodResponse><params><param>");
+    value.write_as_xml(&mut xml).unwrap();
+    xml.extend_from_slice(b"</param></params></methodResponse>");
+    xml
+}
+
+/// An array of 20000 structs describing monitored hosts.
+fn large_array() -> Value {
+    Value::Array(
+        (0..20_000)
+            .map(|i| {
+                let mut host = Map::new();
+                host.insert("id".to_string(), Value::Int(i));
+                host.insert("name".to_string(), Value::from(format!("host-{}", i)));
+                host.insert("load".to_string(), Value::Double(f64::from(i) / 7.0));
+                host.insert("up".to_string(), Value::Bool(i % 3 != 0));
+                Value::Struct(host)
+            })
+            .collect(),
+    )
+}
+
+/// A tree of structs, 50 levels deep and with 200 members on each level.
+fn deep_structs() -> Value {
+    (0..50).fold(Value::Nil, |inner, level| {
+        let mut members: Map = (0..200)
+            .map(|i| {
+                (
+                    format!("member{}", i),
+                    Value::from(format!("{}/{}", level, i)),
+                )
+            })
+            .collect();
+        members.insert("inner".to_string(), inner);
+        Value::Struct(members)
+    })
+}
+
+/// An array of 4 base64 blobs of 1 MiB each.
+fn base64_blobs() -> Value {
+    Value::Array(
+        (0..4)
+            .map(|i| Value::Base64((0..1 << 20).map(|b| (b * i) as u8).collect()))
+            .collect(),
+    )
+}
+
+fn bench_backends(c: &mut Criterion) {
+    let documents = [
+        ("large_array", response(large_array())),
+        ("deep_structs", response(deep_structs())),
+        ("base64_blobs", response(base64_blobs())),
+    ];
+    let backends = [
+        ("xml-rs", ParserBackend::XmlRs),
+        ("quick-xml", ParserBackend::QuickXml),
+    ];
+
+    for &(name, ref document) in &documents {
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Bytes(document.len() as u64));
+        group.sample_size(10);
+        for &(backend_name, backend) in &backends {
+            let options = ParserOptions::new().backend(backend).max_depth(None);
+            group.bench_function(backend_name, |b| {
+                b.iter(|| {
+                    parse_response(&mut &document[..], &options)
+                        .unwrap()
+                        .unwrap()
+                })
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_backends);
+criterion_main!(benches);
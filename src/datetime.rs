@@ -0,0 +1,455 @@
+//! Conversions between `Value::DateTime` and the date/time types of other crates.
+
+use utils::calendar_date;
+use Value;
+
+#[cfg(feature = "chrono")]
+use chrono::{
+    DateTime as ChronoDateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset,
+    TimeZone, Timelike,
+};
+use iso8601::{Date, DateTime, Time};
+#[cfg(feature = "time")]
+use time::{
+    Date as TimeDate, Month, OffsetDateTime, PrimitiveDateTime, Time as TimeTime, UtcOffset,
+};
+
+#[cfg(feature = "time")]
+use std::convert::TryFrom;
+#[cfg(feature = "time")]
+use std::error;
+#[cfg(feature = "time")]
+use std::fmt;
+
+#[cfg(feature = "chrono")]
+/// Creates a `DateTime` from a date and time in a time zone that is `offset` seconds ahead of UTC.
+fn from_chrono(naive: &NaiveDateTime, offset: i32) -> DateTime {
+    // chrono represents leap seconds as a nanosecond value of 1 second or more
+    let nanosecond = naive.nanosecond();
+    DateTime {
+        date: Date::YMD {
+            year: naive.year(),
+            month: naive.month(),
+            day: naive.day(),
+        },
+        time: Time {
+            hour: naive.hour(),
+            minute: naive.minute(),
+            second: naive.second() + nanosecond / 1_000_000_000,
+            millisecond: nanosecond % 1_000_000_000 / 1_000_000,
+            tz_offset_hours: offset / 3600,
+            tz_offset_minutes: offset % 3600 / 60,
+        },
+    }
+}
+
+#[cfg(feature = "chrono")]
+/// Returns the date and time of `date_time` as written, ignoring its offset.
+fn to_naive(date_time: &DateTime) -> Option<NaiveDateTime> {
+    let (year, month, day) = calendar_date(&date_time.date)?;
+    let date = NaiveDate::from_ymd_opt(year, month, day)?;
+
+    let Time {
+        hour,
+        minute,
+        second,
+        millisecond,
+        ..
+    } = date_time.time;
+    let time = if second == 60 {
+        // A leap second
+        NaiveTime::from_hms_milli_opt(hour, minute, 59, 1000 + millisecond)?
+    } else {
+        NaiveTime::from_hms_milli_opt(hour, minute, second, millisecond)?
+    };
+
+    Some(NaiveDateTime::new(date, time))
+}
+
+/// Returns the offset of `date_time` from UTC in seconds.
+fn offset(date_time: &DateTime) -> i32 {
+    date_time.time.tz_offset_hours * 3600 + date_time.time.tz_offset_minutes * 60
+}
+
+#[cfg(feature = "chrono")]
+impl Value {
+    /// If the `Value` is a date and time, returns it as a chrono `NaiveDateTime`, ignoring its
+    /// offset from UTC. Returns `None` otherwise, or if it is not a valid date and time.
+    ///
+    /// This method is only available when the `chrono` feature is enabled.
+    pub fn as_naive_datetime(&self) -> Option<NaiveDateTime> {
+        match *self {
+            Value::DateTime(ref date_time) => to_naive(date_time),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is a date and time, returns it as a chrono `DateTime` with a fixed offset.
+    /// Returns `None` otherwise, or if it is not a valid date and time.
+    ///
+    /// XML-RPC date/time values usually don't specify an offset from UTC, and are interpreted in
+    /// the time zone of the server. Such values are assumed to be in `server_offset`. Note that
+    /// values with an explicit offset of zero (such as `Z` or `+00:00`) can not be distinguished
+    /// from values without offset, so they are also assumed to be in `server_offset`.
+    ///
+    /// This method is only available when the `chrono` feature is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate xmlrpc;
+    /// # use chrono::{FixedOffset, NaiveDate, Utc};
+    /// # use xmlrpc::Value;
+    /// let naive = NaiveDate::from_ymd_opt(2021, 1, 23)
+    ///     .unwrap()
+    ///     .and_hms_opt(12, 0, 0)
+    ///     .unwrap();
+    /// let value = Value::from(naive);
+    ///
+    /// // The server is known to use UTC+1
+    /// let server_offset = FixedOffset::east_opt(3600).unwrap();
+    /// let date_time = value.as_chrono_datetime(server_offset).unwrap();
+    /// assert_eq!(date_time.with_timezone(&Utc).to_rfc3339(), "2021-01-23T11:00:00+00:00");
+    /// ```
+    pub fn as_chrono_datetime(
+        &self,
+        server_offset: FixedOffset,
+    ) -> Option<ChronoDateTime<FixedOffset>> {
+        let date_time = match *self {
+            Value::DateTime(ref date_time) => date_time,
+            _ => return None,
+        };
+
+        let offset = match offset(date_time) {
+            0 => server_offset,
+            seconds => FixedOffset::east_opt(seconds)?,
+        };
+        offset.from_local_datetime(&to_naive(date_time)?).single()
+    }
+}
+
+#[cfg(feature = "chrono")]
+/// Converts a date and time without offset, which is written without time zone designator.
+impl From<NaiveDateTime> for Value {
+    fn from(other: NaiveDateTime) -> Self {
+        Value::DateTime(from_chrono(&other, 0))
+    }
+}
+
+#[cfg(feature = "chrono")]
+/// Converts a date and time in its local time and offset from UTC.
+///
+/// Since offsets of zero are not written, a `DateTime<Utc>` results in a value without time zone
+/// designator, which servers might interpret in their own time zone. Convert the value to the time
+/// zone of the server first if that is a problem.
+impl<Tz: TimeZone> From<ChronoDateTime<Tz>> for Value {
+    fn from(other: ChronoDateTime<Tz>) -> Self {
+        let offset = other.offset().fix().local_minus_utc();
+        Value::DateTime(from_chrono(&other.naive_local(), offset))
+    }
+}
+
+/// An error returned when a `Value` can not be converted to a date and time.
+///
+/// This error is only available when the `time` feature is enabled.
+#[cfg(feature = "time")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateTimeError {
+    _private: (),
+}
+
+#[cfg(feature = "time")]
+impl fmt::Display for DateTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("value is not a valid date and time")
+    }
+}
+
+#[cfg(feature = "time")]
+impl error::Error for DateTimeError {}
+
+/// Creates a `DateTime` from a date and time in a time zone with the given offset from UTC.
+///
+/// The seconds of the offset are dropped, since XML-RPC can't represent them.
+#[cfg(feature = "time")]
+fn from_time(date_time: PrimitiveDateTime, offset: UtcOffset) -> DateTime {
+    let (hours, minutes, _) = offset.as_hms();
+    DateTime {
+        date: Date::YMD {
+            year: date_time.year(),
+            month: u8::from(date_time.month()).into(),
+            day: date_time.day().into(),
+        },
+        time: Time {
+            hour: date_time.hour().into(),
+            minute: date_time.minute().into(),
+            second: date_time.second().into(),
+            millisecond: date_time.millisecond().into(),
+            tz_offset_hours: hours.into(),
+            tz_offset_minutes: minutes.into(),
+        },
+    }
+}
+
+/// Returns the date and time of `date_time` as written, ignoring its offset.
+#[cfg(feature = "time")]
+fn to_primitive(date_time: &DateTime) -> Option<PrimitiveDateTime> {
+    let (year, month, day) = calendar_date(&date_time.date)?;
+    let month = Month::try_from(month as u8).ok()?;
+    let date = TimeDate::from_calendar_date(year, month, day as u8).ok()?;
+
+    let Time {
+        hour,
+        minute,
+        second,
+        millisecond,
+        ..
+    } = date_time.time;
+    let time = TimeTime::from_hms_milli(
+        u8::try_from(hour).ok()?,
+        u8::try_from(minute).ok()?,
+        u8::try_from(second).ok()?,
+        u16::try_from(millisecond).ok()?,
+    )
+    .ok()?;
+
+    Some(PrimitiveDateTime::new(date, time))
+}
+
+/// Converts a date and time without offset, which is written without time zone designator.
+#[cfg(feature = "time")]
+impl From<PrimitiveDateTime> for Value {
+    fn from(other: PrimitiveDateTime) -> Self {
+        Value::DateTime(from_time(other, UtcOffset::UTC))
+    }
+}
+
+/// Converts a date and time in its local time and offset from UTC.
+///
+/// Since offsets of zero are not written, a UTC date and time results in a value without time zone
+/// designator, which servers might interpret in their own time zone. Convert the value to the
+/// offset of the server first if that is a problem.
+#[cfg(feature = "time")]
+impl From<OffsetDateTime> for Value {
+    fn from(other: OffsetDateTime) -> Self {
+        Value::DateTime(from_time(
+            PrimitiveDateTime::new(other.date(), other.time()),
+            other.offset(),
+        ))
+    }
+}
+
+/// Converts a date/time value to a `PrimitiveDateTime`, ignoring its offset from UTC.
+///
+/// Fails if the `Value` isn't a date and time, or if it is not a valid date and time (which
+/// includes leap seconds).
+#[cfg(feature = "time")]
+impl<'a> TryFrom<&'a Value> for PrimitiveDateTime {
+    type Error = DateTimeError;
+
+    fn try_from(value: &'a Value) -> Result<Self, DateTimeError> {
+        match *value {
+            Value::DateTime(ref date_time) => to_primitive(date_time),
+            _ => None,
+        }
+        .ok_or(DateTimeError { _private: () })
+    }
+}
+
+/// Converts a date/time value to an `OffsetDateTime`, using the offset specified by the value.
+///
+/// Values without offset are assumed to be in UTC. Since XML-RPC servers commonly send local
+/// times without offset, convert the value to a `PrimitiveDateTime` and use
+/// `PrimitiveDateTime::assume_offset` with the offset of the server instead if it isn't UTC.
+///
+/// Fails if the `Value` isn't a date and time, or if it is not a valid date and time (which
+/// includes leap seconds).
+#[cfg(feature = "time")]
+impl<'a> TryFrom<&'a Value> for OffsetDateTime {
+    type Error = DateTimeError;
+
+    fn try_from(value: &'a Value) -> Result<Self, DateTimeError> {
+        let date_time = PrimitiveDateTime::try_from(value)?;
+        let seconds = match *value {
+            Value::DateTime(ref date_time) => offset(date_time),
+            _ => unreachable!(),
+        };
+        let offset =
+            UtcOffset::from_whole_seconds(seconds).map_err(|_| DateTimeError { _private: () })?;
+        Ok(date_time.assume_offset(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "chrono")]
+    use chrono::Utc;
+    use utils::format_datetime;
+    use DateTimeFormat;
+
+    #[cfg(feature = "chrono")]
+    fn naive(date: (i32, u32, u32), time: (u32, u32, u32, u32)) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2)
+            .unwrap()
+            .and_hms_milli_opt(time.0, time.1, time.2, time.3)
+            .unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn converts_from_chrono() {
+        let value = Value::from(naive((2021, 1, 23), (9, 5, 7, 0)));
+        assert_eq!(
+            format_datetime(&value.as_datetime().unwrap(), DateTimeFormat::Basic).unwrap(),
+            "20210123T09:05:07"
+        );
+
+        let offset = FixedOffset::west_opt(5 * 3600 + 1800).unwrap();
+        let date_time = offset
+            .from_local_datetime(&naive((1999, 12, 31), (23, 59, 59, 0)))
+            .unwrap();
+        let value = Value::from(date_time);
+        assert_eq!(
+            format_datetime(&value.as_datetime().unwrap(), DateTimeFormat::Basic).unwrap(),
+            "19991231T23:59:59-05:30"
+        );
+        assert_eq!(
+            value.as_chrono_datetime(FixedOffset::east_opt(0).unwrap()),
+            Some(date_time)
+        );
+
+        let value = Value::from(date_time.with_timezone(&Utc));
+        assert_eq!(
+            value.as_naive_datetime(),
+            Some(naive((2000, 1, 1), (5, 29, 59, 0)))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn converts_to_chrono() {
+        let value = |date| {
+            Value::DateTime(DateTime {
+                date,
+                time: Time {
+                    hour: 8,
+                    minute: 30,
+                    second: 60,
+                    millisecond: 0,
+                    tz_offset_hours: 0,
+                    tz_offset_minutes: 0,
+                },
+            })
+        };
+
+        // 2020-12-31 is the Thursday of week 53 and the 366th day of 2020
+        let expected = Some(naive((2020, 12, 31), (8, 30, 59, 1000)));
+        for date in &[
+            Date::YMD {
+                year: 2020,
+                month: 12,
+                day: 31,
+            },
+            Date::Week {
+                year: 2020,
+                ww: 53,
+                d: 4,
+            },
+            Date::Ordinal {
+                year: 2020,
+                ddd: 366,
+            },
+        ] {
+            assert_eq!(value(*date).as_naive_datetime(), expected);
+        }
+
+        let invalid = value(Date::YMD {
+            year: 2021,
+            month: 2,
+            day: 29,
+        });
+        assert_eq!(invalid.as_naive_datetime(), None);
+        assert_eq!(Value::Int(1).as_naive_datetime(), None);
+    }
+
+    #[cfg(feature = "time")]
+    fn primitive(date: (i32, Month, u8), time: (u8, u8, u8, u16)) -> PrimitiveDateTime {
+        PrimitiveDateTime::new(
+            TimeDate::from_calendar_date(date.0, date.1, date.2).unwrap(),
+            TimeTime::from_hms_milli(time.0, time.1, time.2, time.3).unwrap(),
+        )
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn converts_from_time() {
+        let date_time = primitive((2021, Month::January, 23), (9, 5, 7, 8));
+        let value = Value::from(date_time);
+        assert_eq!(
+            format_datetime(&value.as_datetime().unwrap(), DateTimeFormat::Basic).unwrap(),
+            "20210123T09:05:07.008"
+        );
+        assert_eq!(PrimitiveDateTime::try_from(&value), Ok(date_time));
+
+        let offset = UtcOffset::from_hms(-5, -30, 0).unwrap();
+        let date_time =
+            primitive((1999, Month::December, 31), (23, 59, 59, 0)).assume_offset(offset);
+        let value = Value::from(date_time);
+        assert_eq!(
+            format_datetime(&value.as_datetime().unwrap(), DateTimeFormat::Basic).unwrap(),
+            "19991231T23:59:59-05:30"
+        );
+        assert_eq!(OffsetDateTime::try_from(&value), Ok(date_time));
+    }
+
+    #[test]
+    #[cfg(feature = "time")]
+    fn converts_to_time() {
+        let value = |date, second| {
+            Value::DateTime(DateTime {
+                date,
+                time: Time {
+                    hour: 8,
+                    minute: 30,
+                    second,
+                    millisecond: 250,
+                    tz_offset_hours: 0,
+                    tz_offset_minutes: 0,
+                },
+            })
+        };
+
+        // 2020-12-31 is the Thursday of week 53 and the 366th day of 2020
+        let expected = primitive((2020, Month::December, 31), (8, 30, 0, 250));
+        for date in &[
+            Date::YMD {
+                year: 2020,
+                month: 12,
+                day: 31,
+            },
+            Date::Week {
+                year: 2020,
+                ww: 53,
+                d: 4,
+            },
+            Date::Ordinal {
+                year: 2020,
+                ddd: 366,
+            },
+        ] {
+            let value = value(*date, 0);
+            assert_eq!(PrimitiveDateTime::try_from(&value), Ok(expected));
+            assert_eq!(OffsetDateTime::try_from(&value), Ok(expected.assume_utc()));
+        }
+
+        let date = Date::YMD {
+            year: 2020,
+            month: 12,
+            day: 31,
+        };
+        assert!(PrimitiveDateTime::try_from(&value(date, 60)).is_err());
+        assert!(OffsetDateTime::try_from(&Value::Nil).is_err());
+    }
+}
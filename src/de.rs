@@ -0,0 +1,725 @@
+//! Deserialization of responses with serde.
+//!
+//! The deserializer pulls values directly from the response parser, so no `Value` tree is built.
+
+use error::ParseError;
+use parser::{Parser, ParserBackend, ParserOptions};
+#[cfg(feature = "quick-xml")]
+use quick_source::{read_document, QuickXmlSource};
+use source::EventSource;
+use utils::format_datetime;
+use visitor::ValueVisitor;
+use {DateTimeFormat, Fault, Value};
+
+use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::error;
+use std::fmt::{self, Display};
+use std::io::Read;
+use xml::common::TextPosition;
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// The error type used by the deserializer.
+///
+/// Errors raised by `Deserialize` implementations don't know where in the document they occurred,
+/// so they are converted to `ParseError`s by the deserializer of the enclosing value.
+#[derive(Debug)]
+enum Error {
+    Parse(ParseError),
+    Custom(String),
+}
+
+impl Error {
+    /// Converts the error to a `ParseError`, locating it at `position` if necessary.
+    fn into_parse_error(self, position: TextPosition) -> ParseError {
+        match self {
+            Error::Parse(err) => err,
+            Error::Custom(message) => ParseError::Deserialize { message, position },
+        }
+    }
+
+    fn at(self, position: TextPosition) -> Self {
+        Error::Parse(self.into_parse_error(position))
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::Parse(ref err) => err.fmt(fmt),
+            Error::Custom(ref message) => fmt.write_str(message),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// A `ValueVisitor` that ignores everything, used to skip values.
+struct Ignore;
+
+impl ValueVisitor for Ignore {
+    type Error = ParseError;
+}
+
+/// Deserializes a string that is either borrowed from the document or owned.
+fn visit_str<'de, V: Visitor<'de>>(visitor: V, string: Cow<'de, str>) -> Result<V::Value, Error> {
+    match string {
+        Cow::Borrowed(string) => visitor.visit_borrowed_str(string),
+        Cow::Owned(string) => visitor.visit_string(string),
+    }
+}
+
+/// Deserializes a `<value>`.
+struct ValueDeserializer<'p, 'de, S> {
+    parser: &'p mut Parser<'de, S>,
+    /// Whether the opening `<value>` tag has already been consumed.
+    opened: bool,
+}
+
+impl<'p, 'de, S: EventSource<'de>> ValueDeserializer<'p, 'de, S> {
+    fn new(parser: &'p mut Parser<'de, S>) -> Self {
+        ValueDeserializer {
+            parser,
+            opened: false,
+        }
+    }
+
+    /// Consumes the opening `<value>` tag if necessary, and returns the position of its contents.
+    fn open(&mut self) -> ParseResult<TextPosition> {
+        if !self.opened {
+            self.parser.open_value()?;
+            self.opened = true;
+        }
+        Ok(self.parser.position())
+    }
+
+    /// Parses a string value (a raw string, a `<string>` or an empty value) without the closing
+    /// `</value>` tag. Returns `None` if the value has a different type.
+    fn parse_string(&mut self) -> ParseResult<Option<Cow<'de, str>>> {
+        let parser = &mut *self.parser;
+        if parser.is_characters() {
+            parser.expect_string().map(Some)
+        } else if parser.is_open("string") {
+            parser.expect_open("string")?;
+            if parser.try_close("string")? {
+                return Ok(Some(Cow::Borrowed("")));
+            }
+            let string = parser.expect_string()?;
+            parser.expect_close("string")?;
+            Ok(Some(string))
+        } else if parser.is_close("value") && !self.is_nil_empty_value() {
+            // empty value, parse as empty string
+            Ok(Some(Cow::Borrowed("")))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns whether the current token closes an empty value that is parsed as nil.
+    fn is_nil_empty_value(&self) -> bool {
+        self.parser.is_close("value") && self.parser.empty_value() == Value::Nil
+    }
+}
+
+impl<'p, 'de, S: EventSource<'de>> de::Deserializer<'de> for ValueDeserializer<'p, 'de, S> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        let position = self.open()?;
+        let value = if let Some(string) = self.parse_string()? {
+            visit_str(visitor, string)
+        } else if self.parser.is_open("struct") {
+            self.parser.expect_open("struct")?;
+            let mut map = MapAccess::new(&mut *self.parser);
+            let value = visitor
+                .visit_map(&mut map)
+                .map_err(|err| err.at(position))?;
+            map.end()?;
+            Ok(value)
+        } else if self.parser.is_open("array") {
+            self.parser.expect_open("array")?;
+            self.parser.expect_open("data")?;
+            let mut seq = SeqAccess::new(&mut *self.parser);
+            let value = visitor
+                .visit_seq(&mut seq)
+                .map_err(|err| err.at(position))?;
+            seq.end()?;
+            Ok(value)
+        } else if self.is_nil_empty_value() {
+            visitor.visit_unit()
+        } else {
+            match self.parser.parse_value_inner()? {
+                Value::Int(i) => visitor.visit_i32(i),
+                Value::Int64(i) => visitor.visit_i64(i),
+                Value::Bool(b) => visitor.visit_bool(b),
+                Value::String(s) | Value::BigInteger(s) | Value::BigDecimal(s) | Value::Dom(s) => {
+                    visitor.visit_string(s)
+                }
+                Value::Double(d) => visitor.visit_f64(d),
+                Value::DateTime(date_time) => {
+                    match format_datetime(&date_time, DateTimeFormat::Basic) {
+                        Some(formatted) => visitor.visit_string(formatted),
+                        None => Err(de::Error::custom("invalid date/time value")),
+                    }
+                }
+                Value::Base64(data) | Value::Serializable(data) => visitor.visit_byte_buf(data),
+                Value::Nil => visitor.visit_unit(),
+                Value::Struct(_) | Value::Array(_) => unreachable!(),
+            }
+        };
+        let value = value.map_err(|err| err.at(position))?;
+        self.parser.close_value()?;
+        Ok(value)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Error> {
+        let position = self.open()?;
+        if self.parser.is_open("nil") {
+            // `<nil/>` or `<ex:nil/>`
+            self.parser.parse_value_inner()?;
+        } else if !self.is_nil_empty_value() {
+            return visitor.visit_some(self);
+        }
+        self.parser.close_value()?;
+        visitor.visit_none().map_err(|err: Error| err.at(position))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        mut self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let position = self.open()?;
+        let value = if self.parser.is_open("struct") {
+            // A struct with a single member, named after the variant
+            self.parser.expect_open("struct")?;
+            let value = visitor
+                .visit_enum(EnumAccess {
+                    parser: &mut *self.parser,
+                })
+                .map_err(|err| err.at(position))?;
+            self.parser.expect_close("struct")?;
+            value
+        } else if let Some(string) = self.parse_string()? {
+            // The name of a unit variant
+            match string {
+                Cow::Borrowed(string) => visitor.visit_enum(string.into_deserializer()),
+                Cow::Owned(string) => visitor.visit_enum(string.into_deserializer()),
+            }
+            .map_err(|err: Error| err.at(position))?
+        } else {
+            return self.deserialize_any(visitor);
+        };
+        self.parser.close_value()?;
+        Ok(value)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.opened {
+            if !self.parser.is_close("value") {
+                self.parser.visit_value_inner(&mut Ignore)?;
+            }
+            self.parser.close_value()?;
+        } else {
+            self.parser.visit_value(&mut Ignore)?;
+        }
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Deserializes the members of a `<struct>`.
+struct MapAccess<'p, 'de, S> {
+    parser: &'p mut Parser<'de, S>,
+    /// Names of the members seen so far (only tracked if duplicates are rejected).
+    names: BTreeSet<String>,
+    /// Whether the value of the last member has not been deserialized yet.
+    pending_value: bool,
+    /// Whether the closing `</struct>` tag has been consumed.
+    done: bool,
+}
+
+impl<'p, 'de, S: EventSource<'de>> MapAccess<'p, 'de, S> {
+    fn new(parser: &'p mut Parser<'de, S>) -> Self {
+        MapAccess {
+            parser,
+            names: BTreeSet::new(),
+            pending_value: false,
+            done: false,
+        }
+    }
+
+    /// Parses the name of the next member, or the end of the struct.
+    fn next_name(&mut self) -> ParseResult<Option<Cow<'de, str>>> {
+        if self.pending_value {
+            self.pending_value = false;
+            self.parser.visit_value(&mut Ignore)?;
+            self.parser.expect_close("member")?;
+        }
+        if self.done || self.parser.try_close("struct")? {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let names = &self.names;
+        let name = self.parser.parse_member_name(|name| names.contains(name))?;
+        if self.parser.rejects_duplicate_members() {
+            self.names.insert(name.to_string());
+        }
+        self.pending_value = true;
+        Ok(Some(name))
+    }
+
+    /// Skips the members that were not deserialized.
+    fn end(mut self) -> ParseResult<()> {
+        while self.next_name()?.is_some() {}
+        Ok(())
+    }
+}
+
+impl<'a, 'p, 'de, S: EventSource<'de>> de::MapAccess<'de> for &'a mut MapAccess<'p, 'de, S> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let position = self.parser.position();
+        let key = match self.next_name()? {
+            None => return Ok(None),
+            Some(Cow::Borrowed(name)) => seed.deserialize(name.into_deserializer()),
+            Some(Cow::Owned(name)) => seed.deserialize(name.into_deserializer()),
+        };
+        key.map(Some).map_err(|err: Error| err.at(position))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        self.pending_value = false;
+        let value = seed.deserialize(ValueDeserializer::new(self.parser))?;
+        self.parser.expect_close("member")?;
+        Ok(value)
+    }
+}
+
+/// Deserializes the elements of an `<array>`.
+struct SeqAccess<'p, 'de, S> {
+    parser: &'p mut Parser<'de, S>,
+    /// Whether the closing `</data>` tag has been consumed.
+    done: bool,
+}
+
+impl<'p, 'de, S: EventSource<'de>> SeqAccess<'p, 'de, S> {
+    fn new(parser: &'p mut Parser<'de, S>) -> Self {
+        SeqAccess {
+            parser,
+            done: false,
+        }
+    }
+
+    /// Skips the elements that were not deserialized.
+    fn end(self) -> ParseResult<()> {
+        if !self.done {
+            while !self.parser.try_close("data")? {
+                self.parser.visit_value(&mut Ignore)?;
+            }
+        }
+        self.parser.expect_close("array")
+    }
+}
+
+impl<'a, 'p, 'de, S: EventSource<'de>> de::SeqAccess<'de> for &'a mut SeqAccess<'p, 'de, S> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.done || self.parser.try_close("data")? {
+            self.done = true;
+            return Ok(None);
+        }
+        seed.deserialize(ValueDeserializer::new(self.parser))
+            .map(Some)
+    }
+}
+
+/// Deserializes an enum variant represented as a struct with a single member.
+struct EnumAccess<'p, 'de, S> {
+    parser: &'p mut Parser<'de, S>,
+}
+
+impl<'p, 'de, S: EventSource<'de>> de::EnumAccess<'de> for EnumAccess<'p, 'de, S> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let position = self.parser.position();
+        let variant = match self.parser.parse_member_name(|_| false)? {
+            Cow::Borrowed(name) => seed.deserialize(name.into_deserializer()),
+            Cow::Owned(name) => seed.deserialize(name.into_deserializer()),
+        };
+        let variant = variant.map_err(|err: Error| err.at(position))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'p, 'de, S: EventSource<'de>> de::VariantAccess<'de> for EnumAccess<'p, 'de, S> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::IgnoredAny::deserialize(ValueDeserializer::new(&mut *self.parser))?;
+        self.parser.expect_close("member")?;
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        let value = seed.deserialize(ValueDeserializer::new(&mut *self.parser))?;
+        self.parser.expect_close("member")?;
+        Ok(value)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        let value =
+            de::Deserializer::deserialize_seq(ValueDeserializer::new(&mut *self.parser), visitor)?;
+        self.parser.expect_close("member")?;
+        Ok(value)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let value =
+            de::Deserializer::deserialize_map(ValueDeserializer::new(&mut *self.parser), visitor)?;
+        self.parser.expect_close("member")?;
+        Ok(value)
+    }
+}
+
+/// Deserializes the value of a response (or returns the `<fault>`).
+fn deserialize<'de, S: EventSource<'de>, T: Deserialize<'de>>(
+    parser: &mut Parser<'de, S>,
+) -> ParseResult<Result<T, Fault>> {
+    if let Some(fault) = parser.parse_response_start()? {
+        return Ok(Err(fault));
+    }
+
+    let position = parser.position();
+    let value = T::deserialize(ValueDeserializer::new(parser))
+        .map_err(|err| err.into_parse_error(position))?;
+
+    // </param>
+    parser.expect_close("param")?;
+
+    Ok(Ok(value))
+}
+
+/// Parses an XML-RPC response document, deserializing the returned value into a `T`.
+///
+/// This works like [`parse_response`], except that the value is deserialized directly from the
+/// document without building a [`Value`] first. This saves time and memory for large responses.
+///
+/// XML-RPC values map to the serde data model as follows:
+///
+/// * Structs are deserialized as maps (or structs), arrays as sequences (or tuples).
+/// * `<i4>`/`<int>`, `<i8>`, `<boolean>` and `<double>` are deserialized as the corresponding
+///   primitives; integers can also be deserialized into any other integer type they fit into.
+/// * `<base64>` data is deserialized as a byte buffer, and `<dateTime.iso8601>` values as strings.
+/// * `<nil/>` is deserialized as a unit or `None`. Any other value is deserialized as `Some`.
+/// * Enums are deserialized from strings (for unit variants) or from structs with a single member
+///   named after the variant.
+///
+/// # Errors
+///
+/// Returns a `ParseError` if the document is not a valid response, or if reading from `reader`
+/// fails. Values that can't be deserialized into the requested type are reported as
+/// [`ParseError::Deserialize`], along with their position in the document. A `<fault>` response
+/// is returned as `Ok(Err(fault))`.
+///
+/// [`parse_response`]: fn.parse_response.html
+/// [`Value`]: enum.Value.html
+/// [`ParseError::Deserialize`]: enum.ParseError.html#variant.Deserialize
+pub fn deserialize_response<R: Read, T: DeserializeOwned>(
+    reader: &mut R,
+    options: &ParserOptions,
+) -> ParseResult<Result<T, Fault>> {
+    deserialize_response_with_charset(reader, options, None)
+}
+
+/// Parses an XML-RPC response document held in memory, deserializing the returned value into a
+/// `T` that may borrow from the document.
+///
+/// Strings and struct member names are borrowed from `xml` where possible (that is, if they don't
+/// contain any escape sequences or CDATA sections), so a `T` containing `&str` fields can be
+/// deserialized without allocating. Otherwise, this works like [`deserialize_response`].
+///
+/// The response is always parsed with [`ParserBackend::QuickXml`], regardless of the backend
+/// selected in the `ParserOptions`. This function is only available when both the `serde` and
+/// `quick-xml` features are enabled.
+///
+/// [`deserialize_response`]: fn.deserialize_response.html
+/// [`ParserBackend::QuickXml`]: enum.ParserBackend.html#variant.QuickXml
+#[cfg(feature = "quick-xml")]
+pub fn deserialize_response_str<'de, T: Deserialize<'de>>(
+    xml: &'de str,
+    options: &ParserOptions,
+) -> ParseResult<Result<T, Fault>> {
+    if let Some(limit) = options.max_document_size {
+        if xml.len() as u64 > limit {
+            return Err(ParseError::DocumentTooLarge { limit });
+        }
+    }
+    deserialize(&mut Parser::new(QuickXmlSource::new(xml), options)?)
+}
+
+/// Parses a response from an XML reader, deserializing the returned value into a `T`.
+///
+/// `charset` is the character encoding of the document specified by the transport, if any.
+pub fn deserialize_response_with_charset<R: Read, T: DeserializeOwned>(
+    reader: &mut R,
+    options: &ParserOptions,
+    charset: Option<&str>,
+) -> ParseResult<Result<T, Fault>> {
+    match options.backend {
+        ParserBackend::XmlRs => deserialize(&mut Parser::from_reader(reader, options, charset)?),
+        #[cfg(feature = "quick-xml")]
+        ParserBackend::QuickXml => {
+            let document = read_document(reader, options.max_document_size, charset)?;
+            deserialize(&mut Parser::new(QuickXmlSource::new(&document), options)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn response(value: &str) -> String {
+        format!(
+            r#"<?xml version="1.0"?>
+<methodResponse><params><param>
+{}
+</param></params></methodResponse>"#,
+            value
+        )
+    }
+
+    fn read<T: DeserializeOwned>(xml: &str) -> ParseResult<T> {
+        Ok(deserialize_response(&mut xml.as_bytes(), &ParserOptions::new())?.unwrap())
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Item {
+        id: u32,
+        name: String,
+        tags: Vec<String>,
+        price: f64,
+        parent: Option<Box<Item>>,
+    }
+
+    #[test]
+    fn deserializes_structs() {
+        let xml = response(
+            r#"<value><struct>
+  <member><name>name</name><value>chair</value></member>
+  <member><name>id</name><value><i4>7</i4></value></member>
+  <member><name>tags</name><value><array><data>
+    <value><string>wood</string></value>
+    <value>brown</value>
+  </data></array></value></member>
+  <member><name>price</name><value><double>49.5</double></value></member>
+  <member><name>parent</name><value><nil/></value></member>
+  <member><name>unknown</name><value><struct></struct></value></member>
+</struct></value>"#,
+        );
+
+        assert_eq!(
+            read::<Item>(&xml),
+            Ok(Item {
+                id: 7,
+                name: "chair".to_string(),
+                tags: vec!["wood".to_string(), "brown".to_string()],
+                price: 49.5,
+                parent: None,
+            })
+        );
+
+        let map = read::<BTreeMap<String, de::IgnoredAny>>(&xml).unwrap();
+        assert_eq!(map.len(), 6);
+    }
+
+    #[test]
+    fn deserializes_enums() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Shape {
+            Empty,
+            Circle(f64),
+            Rect { w: i32, h: i32 },
+        }
+
+        let xml = response(
+            r#"<value><array><data>
+  <value>Empty</value>
+  <value><struct><member><name>Circle</name><value><double>1.5</double></value></member></struct></value>
+  <value><struct><member><name>Rect</name><value><struct>
+    <member><name>w</name><value><int>2</int></value></member>
+    <member><name>h</name><value><int>3</int></value></member>
+  </struct></value></member></struct></value>
+</data></array></value>"#,
+        );
+        assert_eq!(
+            read::<Vec<Shape>>(&xml),
+            Ok(vec![
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Rect { w: 2, h: 3 }
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_type_mismatches() {
+        let xml = response(
+            r#"<value><struct>
+  <member><name>id</name><value><i4>7</i4></value></member>
+  <member><name>name</name><value><boolean>1</boolean></value></member>
+</struct></value>"#,
+        );
+        match read::<Item>(&xml) {
+            Err(ParseError::Deserialize { message, position }) => {
+                assert!(message.contains("invalid type: boolean"), "{}", message);
+                assert_eq!(position.row, 4);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        let xml =
+            response(r#"<value><array><data><value><i4>-1</i4></value></data></array></value>"#);
+        match read::<Vec<u8>>(&xml) {
+            Err(ParseError::Deserialize { message, .. }) => {
+                assert!(message.contains("invalid value"), "{}", message)
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        match read::<Item>(&response("<value><struct></struct></value>")) {
+            Err(ParseError::Deserialize { message, .. }) => {
+                assert_eq!(message, "missing field `id`")
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn returns_faults() {
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse><fault><value><struct>
+  <member><name>faultCode</name><value><int>4</int></value></member>
+  <member><name>faultString</name><value>Too many parameters.</value></member>
+</struct></value></fault></methodResponse>"#;
+        let fault = deserialize_response::<_, Item>(&mut xml.as_bytes(), &ParserOptions::new())
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(fault.fault_code, 4);
+    }
+
+    #[test]
+    fn rejects_duplicate_members() {
+        let xml = response(
+            r#"<value><struct>
+  <member><name>a</name><value><i4>1</i4></value></member>
+  <member><name>a</name><value><i4>2</i4></value></member>
+</struct></value>"#,
+        );
+        let result = deserialize_response::<_, BTreeMap<String, i32>>(
+            &mut xml.as_bytes(),
+            &ParserOptions::strict(),
+        );
+        match result {
+            Err(ParseError::DuplicateMember { name, .. }) => assert_eq!(name, "a"),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn deserializes_empty_values_as_nil() {
+        let xml = response("<value><array><data><value/><value>a</value></data></array></value>");
+        let options = ParserOptions::new().empty_values_as_nil(true);
+        let result = deserialize_response::<_, Vec<Option<String>>>(&mut xml.as_bytes(), &options);
+        assert_eq!(result.unwrap(), Ok(vec![None, Some("a".to_string())]));
+        assert_eq!(
+            read::<Vec<Option<String>>>(&xml),
+            Ok(vec![Some(String::new()), Some("a".to_string())])
+        );
+    }
+
+    #[cfg(feature = "quick-xml")]
+    #[test]
+    fn borrows_strings() {
+        #[derive(Debug, Deserialize)]
+        struct Borrowed<'a> {
+            name: &'a str,
+            #[serde(borrow)]
+            note: Cow<'a, str>,
+        }
+
+        let xml = response(
+            r#"<value><struct>
+  <member><name>name</name><value><string>chair</string></value></member>
+  <member><name>note</name><value>&lt;new&gt;</value></member>
+</struct></value>"#,
+        );
+        let borrowed = deserialize_response_str::<Borrowed<'_>>(&xml, &ParserOptions::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(borrowed.name, "chair");
+        assert_eq!(borrowed.note, "<new>");
+        match borrowed.note {
+            Cow::Owned(_) => {}
+            Cow::Borrowed(_) => panic!("escaped string was borrowed"),
+        }
+
+        // Strings containing escape sequences can't be borrowed
+        let xml = xml.replace("chair", "&quot;chair&quot;");
+        let result = deserialize_response_str::<Borrowed<'_>>(&xml, &ParserOptions::new());
+        match result {
+            Err(ParseError::Deserialize { message, .. }) => {
+                assert!(
+                    message.contains("expected a borrowed string"),
+                    "{}",
+                    message
+                )
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Structural comparison of values.
+
+use path::Location;
+use Value;
+
+use std::fmt;
+
+/// Options controlling which values [`Value::diff_with`] considers equal.
+///
+/// By default, values are only equal if they have the same type and are equal according to
+/// `PartialEq`, except that NaN doubles are equal to each other.
+///
+/// [`Value::diff_with`]: enum.Value.html#method.diff_with
+#[derive(Clone, Debug, Default)]
+pub struct DiffOptions {
+    ignore_int_width: bool,
+    double_tolerance: f64,
+}
+
+impl DiffOptions {
+    /// Creates the default `DiffOptions`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether an `Int` and an `Int64` with the same value are considered equal.
+    ///
+    /// Servers differ in whether they send small 64-bit integers as `<i4>` or `<i8>`.
+    ///
+    /// Disabled by default.
+    pub fn ignore_int_width(mut self, ignore: bool) -> Self {
+        self.ignore_int_width = ignore;
+        self
+    }
+
+    /// Sets the maximum absolute difference between two doubles that are considered equal.
+    ///
+    /// Defaults to 0.
+    pub fn double_tolerance(mut self, tolerance: f64) -> Self {
+        self.double_tolerance = tolerance;
+        self
+    }
+}
+
+/// A difference between two values, found by [`Value::diff`].
+///
+/// The "left" value is the one `diff` was called on, the "right" value is its argument.
+///
+/// [`Value::diff`]: enum.Value.html#method.diff
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference<'a> {
+    location: Location,
+    kind: DifferenceKind<'a>,
+}
+
+impl<'a> Difference<'a> {
+    /// Returns the path of the differing value, in the syntax accepted by [`Value::get_path`].
+    ///
+    /// The path is empty if the compared values themselves differ.
+    ///
+    /// [`Value::get_path`]: enum.Value.html#method.get_path
+    pub fn path(&self) -> &str {
+        &self.location.path
+    }
+
+    /// Returns the location of the differing value as a JSON Pointer (see [`Value::pointer`]).
+    ///
+    /// Unlike the [`path`], the pointer is unambiguous even if member names contain `.` or `[`.
+    ///
+    /// [`Value::pointer`]: enum.Value.html#method.pointer
+    /// [`path`]: #method.path
+    pub fn pointer(&self) -> &str {
+        &self.location.pointer
+    }
+
+    /// Returns how the values differ.
+    pub fn kind(&self) -> &DifferenceKind<'a> {
+        &self.kind
+    }
+}
+
+impl<'a> fmt::Display for Difference<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = &self.location;
+        match self.kind {
+            DifferenceKind::Added(value) => write!(f, "{} was added: {}", location, value),
+            DifferenceKind::Removed(value) => write!(f, "{} was removed: {}", location, value),
+            DifferenceKind::TypeChanged { left, right } => write!(
+                f,
+                "{} changed type from `{}` to `{}`: {} != {}",
+                location,
+                left.type_name(),
+                right.type_name(),
+                left,
+                right
+            ),
+            DifferenceKind::ValueChanged { left, right } => {
+                write!(f, "{} changed from {} to {}", location, left, right)
+            }
+            DifferenceKind::LengthChanged { left, right } => {
+                write!(f, "{} changed length from {} to {}", location, left, right)
+            }
+        }
+    }
+}
+
+/// The ways in which two values can differ.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum DifferenceKind<'a> {
+    /// A struct member or array element only exists in the right value.
+    Added(&'a Value),
+    /// A struct member or array element only exists in the left value.
+    Removed(&'a Value),
+    /// The values have different types, such as `Int` and `Int64`.
+    TypeChanged {
+        /// The left value.
+        left: &'a Value,
+        /// The right value.
+        right: &'a Value,
+    },
+    /// The values have the same type, but are not equal.
+    ValueChanged {
+        /// The left value.
+        left: &'a Value,
+        /// The right value.
+        right: &'a Value,
+    },
+    /// The arrays have different lengths.
+    ///
+    /// The elements present in both arrays are compared as well, and each surplus element is
+    /// reported as added or removed.
+    LengthChanged {
+        /// The length of the left array.
+        left: usize,
+        /// The length of the right array.
+        right: usize,
+    },
+}
+
+struct Differ<'a, 'o> {
+    options: &'o DiffOptions,
+    differences: Vec<Difference<'a>>,
+}
+
+impl<'a, 'o> Differ<'a, 'o> {
+    fn report(&mut self, location: &Location, kind: DifferenceKind<'a>) {
+        self.differences.push(Difference {
+            location: location.clone(),
+            kind,
+        });
+    }
+
+    fn compare(&mut self, location: &Location, left: &'a Value, right: &'a Value) {
+        let equal = match (left, right) {
+            (Value::Struct(left), Value::Struct(right)) => {
+                for (name, value) in left {
+                    match right.get(name) {
+                        Some(other) => self.compare(&location.member(name), value, other),
+                        None => self.report(&location.member(name), DifferenceKind::Removed(value)),
+                    }
+                }
+                for (name, value) in right {
+                    if !left.contains_key(name) {
+                        self.report(&location.member(name), DifferenceKind::Added(value));
+                    }
+                }
+                return;
+            }
+            (Value::Array(left), Value::Array(right)) => {
+                if left.len() != right.len() {
+                    let kind = DifferenceKind::LengthChanged {
+                        left: left.len(),
+                        right: right.len(),
+                    };
+                    self.report(location, kind);
+                }
+                for (index, (value, other)) in left.iter().zip(right).enumerate() {
+                    self.compare(&location.element(index), value, other);
+                }
+                for (index, value) in left.iter().enumerate().skip(right.len()) {
+                    self.report(&location.element(index), DifferenceKind::Removed(value));
+                }
+                for (index, value) in right.iter().enumerate().skip(left.len()) {
+                    self.report(&location.element(index), DifferenceKind::Added(value));
+                }
+                return;
+            }
+            (&Value::Double(a), &Value::Double(b)) => {
+                a == b || a.is_nan() && b.is_nan() || (a - b).abs() <= self.options.double_tolerance
+            }
+            (&Value::Int(a), &Value::Int64(b)) | (&Value::Int64(b), &Value::Int(a))
+                if self.options.ignore_int_width =>
+            {
+                i64::from(a) == b
+            }
+            _ if left.type_name() != right.type_name() => {
+                self.report(location, DifferenceKind::TypeChanged { left, right });
+                return;
+            }
+            _ => left == right,
+        };
+
+        if !equal {
+            self.report(location, DifferenceKind::ValueChanged { left, right });
+        }
+    }
+}
+
+impl Value {
+    /// Compares this value with `other` and returns all differences between them.
+    ///
+    /// Structs and arrays are compared member by member and element by element, so each
+    /// difference refers to the innermost value that differs. The result is empty if the values
+    /// are equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use xmlrpc::Value;
+    /// let expected = Value::Struct(vec![
+    ///     ("id".to_string(), Value::Int(7)),
+    ///     ("tags".to_string(), Value::Array(vec![Value::from("a")])),
+    /// ].into_iter().collect());
+    /// let actual = Value::Struct(vec![
+    ///     ("id".to_string(), Value::Int64(7)),
+    ///     ("tags".to_string(), Value::Array(vec![Value::from("b")])),
+    /// ].into_iter().collect());
+    ///
+    /// let differences: Vec<_> = expected.diff(&actual).iter().map(|d| d.to_string()).collect();
+    /// assert_eq!(differences, [
+    ///     "`id` changed type from `i4` to `i8`: i4(7) != i8(7)",
+    ///     r#"`tags[0]` changed from "a" to "b""#,
+    /// ]);
+    /// ```
+    pub fn diff<'a>(&'a self, other: &'a Value) -> Vec<Difference<'a>> {
+        self.diff_with(other, &DiffOptions::default())
+    }
+
+    /// Compares this value with `other` according to `options` and returns all differences
+    /// between them.
+    ///
+    /// See [`diff`] for details.
+    ///
+    /// [`diff`]: #method.diff
+    pub fn diff_with<'a>(&'a self, other: &'a Value, options: &DiffOptions) -> Vec<Difference<'a>> {
+        let mut differ = Differ {
+            options,
+            differences: Vec::new(),
+        };
+        differ.compare(&Location::default(), self, other);
+        differ.differences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Map;
+
+    fn strings(differences: &[Difference<'_>]) -> Vec<String> {
+        differences.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn equal_values_have_no_differences() {
+        let value = Value::Array(vec![Value::Double(f64::NAN), Value::from("a")]);
+        assert_eq!(value.diff(&value.clone()), []);
+    }
+
+    #[test]
+    fn reports_differences_with_paths() {
+        let mut left = Map::new();
+        left.insert("kept".to_string(), Value::Int(1));
+        left.insert("removed".to_string(), Value::Bool(true));
+        left.insert(
+            "list".to_string(),
+            Value::Array(vec![Value::Int(1), Value::Int(2), Value::Int(3)]),
+        );
+        left.insert("a/b".to_string(), Value::Nil);
+        let left = Value::Struct(left);
+
+        let mut right = Map::new();
+        right.insert("kept".to_string(), Value::Int(1));
+        right.insert("added".to_string(), Value::from("new"));
+        right.insert(
+            "list".to_string(),
+            Value::Array(vec![Value::Int(1), Value::Int(5)]),
+        );
+        right.insert("a/b".to_string(), Value::Int(0));
+        let right = Value::Struct(right);
+
+        let differences = left.diff(&right);
+        let mut summary = differences
+            .iter()
+            .map(|d| (d.path(), d.pointer(), d.to_string()))
+            .collect::<Vec<_>>();
+        summary.sort();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "a/b",
+                    "/a~1b",
+                    "`a/b` changed type from `nil` to `i4`: nil != i4(0)".to_string()
+                ),
+                ("added", "/added", "`added` was added: \"new\"".to_string()),
+                (
+                    "list",
+                    "/list",
+                    "`list` changed length from 3 to 2".to_string()
+                ),
+                (
+                    "list[1]",
+                    "/list/1",
+                    "`list[1]` changed from i4(2) to i4(5)".to_string()
+                ),
+                (
+                    "list[2]",
+                    "/list/2",
+                    "`list[2]` was removed: i4(3)".to_string()
+                ),
+                (
+                    "removed",
+                    "/removed",
+                    "`removed` was removed: true".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            differences
+                .iter()
+                .find(|d| d.path() == "list[2]")
+                .unwrap()
+                .kind(),
+            &DifferenceKind::Removed(&Value::Int(3))
+        );
+    }
+
+    #[test]
+    fn reports_root_differences() {
+        let (left, right) = (Value::from("a"), Value::Array(Vec::new()));
+        assert_eq!(
+            strings(&left.diff(&right)),
+            ["the value changed type from `string` to `array`: \"a\" != []"]
+        );
+    }
+
+    #[test]
+    fn applies_options() {
+        let left = Value::Array(vec![Value::Int(7), Value::Double(0.1 + 0.2)]);
+        let right = Value::Array(vec![Value::Int64(7), Value::Double(0.3)]);
+        assert_eq!(
+            strings(&left.diff(&right)),
+            [
+                "`[0]` changed type from `i4` to `i8`: i4(7) != i8(7)",
+                "`[1]` changed from double(0.30000000000000004) to double(0.3)",
+            ]
+        );
+
+        let options = DiffOptions::new()
+            .ignore_int_width(true)
+            .double_tolerance(1e-9);
+        assert_eq!(left.diff_with(&right, &options), []);
+
+        let right = Value::Array(vec![Value::Int64(8), Value::Double(0.31)]);
+        assert_eq!(
+            strings(&left.diff_with(&right, &options)),
+            [
+                "`[0]` changed from i4(7) to i8(8)",
+                "`[1]` changed from double(0.30000000000000004) to double(0.31)",
+            ]
+        );
+    }
+}
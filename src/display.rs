@@ -0,0 +1,367 @@
+//! Human-readable formatting of values for logs and error messages.
+
+use utils::format_datetime;
+use visitor::ValueVisitor;
+use {DateTimeFormat, Value, ValueRef};
+
+use iso8601::DateTime;
+use std::fmt;
+use std::mem;
+
+/// The number of characters after which strings are truncated, unless a precision is specified.
+const DEFAULT_MAX_CHARS: usize = 80;
+
+/// A `ValueVisitor` that writes a human-readable representation of the visited value.
+struct DisplayWriter<'f, 'a> {
+    f: &'f mut fmt::Formatter<'a>,
+    pretty: bool,
+    max_chars: usize,
+    /// For each array or struct being written, whether no element has been written yet.
+    empty: Vec<bool>,
+    /// Whether the innermost container is an array, whose elements need a separator.
+    in_array: Vec<bool>,
+    /// The size of the `<base64>` value being written.
+    base64_len: usize,
+}
+
+impl<'f, 'a> DisplayWriter<'f, 'a> {
+    fn new(f: &'f mut fmt::Formatter<'a>) -> Self {
+        DisplayWriter {
+            pretty: f.alternate(),
+            max_chars: f.precision().unwrap_or(DEFAULT_MAX_CHARS),
+            f,
+            empty: Vec::new(),
+            in_array: Vec::new(),
+            base64_len: 0,
+        }
+    }
+
+    fn write(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        self.f.write_fmt(args)
+    }
+
+    /// Writes the separator and indentation preceding an element of the innermost container.
+    fn separate(&mut self) -> fmt::Result {
+        let first = match self.empty.last_mut() {
+            Some(empty) => mem::replace(empty, false),
+            None => return Ok(()),
+        };
+        let separator = if first { "" } else { "," };
+        if self.pretty {
+            let indent = self.empty.len() * 2;
+            self.write(format_args!(
+                "{}\n{:indent$}",
+                separator,
+                "",
+                indent = indent
+            ))
+        } else if !first {
+            self.write(format_args!("{} ", separator))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes the start of a scalar or container, preceded by a separator in arrays.
+    fn start_value(&mut self) -> fmt::Result {
+        if self.in_array.last() == Some(&true) {
+            self.separate()?;
+        }
+        Ok(())
+    }
+
+    fn scalar(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        self.start_value()?;
+        self.write(args)
+    }
+
+    fn open(&mut self, bracket: char, array: bool) -> fmt::Result {
+        self.start_value()?;
+        self.write(format_args!("{}", bracket))?;
+        self.empty.push(true);
+        self.in_array.push(array);
+        Ok(())
+    }
+
+    fn close(&mut self, bracket: char) -> fmt::Result {
+        self.in_array.pop();
+        let empty = self.empty.pop().unwrap_or(true);
+        if self.pretty && !empty {
+            let indent = self.empty.len() * 2;
+            self.write(format_args!("\n{:indent$}", "", indent = indent))?;
+        }
+        self.write(format_args!("{}", bracket))
+    }
+
+    /// Writes `text` quoted and escaped, truncated to `max_chars` characters.
+    fn quoted(&mut self, text: &str) -> fmt::Result {
+        match text.char_indices().nth(self.max_chars) {
+            Some((end, _)) => {
+                let len = text.chars().count();
+                self.write(format_args!("{:?}… ({} chars)", &text[..end], len))
+            }
+            None => self.write(format_args!("{:?}", text)),
+        }
+    }
+
+    fn typed_string(&mut self, type_name: &str, text: &str) -> fmt::Result {
+        self.start_value()?;
+        self.write(format_args!("{}(", type_name))?;
+        self.quoted(text)?;
+        self.write(format_args!(")"))
+    }
+}
+
+/// Formats a number of bytes with a binary unit, such as `1.2 KiB`.
+struct ByteSize(usize);
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64;
+        for unit in &["KiB", "MiB", "GiB"] {
+            size /= 1024.0;
+            if size < 1024.0 {
+                return write!(f, "{:.1} {}", size, unit);
+            }
+        }
+        write!(f, "{:.1} TiB", size / 1024.0)
+    }
+}
+
+impl<'f, 'a> ValueVisitor for DisplayWriter<'f, 'a> {
+    type Error = fmt::Error;
+
+    fn int(&mut self, value: i32) -> fmt::Result {
+        self.scalar(format_args!("i4({})", value))
+    }
+
+    fn int64(&mut self, value: i64) -> fmt::Result {
+        self.scalar(format_args!("i8({})", value))
+    }
+
+    fn bool(&mut self, value: bool) -> fmt::Result {
+        self.scalar(format_args!("{}", value))
+    }
+
+    fn string(&mut self, value: &str) -> fmt::Result {
+        self.start_value()?;
+        self.quoted(value)
+    }
+
+    fn double(&mut self, value: f64) -> fmt::Result {
+        self.scalar(format_args!("double({})", value))
+    }
+
+    fn datetime(&mut self, value: DateTime) -> fmt::Result {
+        match format_datetime(&value, DateTimeFormat::Basic) {
+            Some(formatted) => self.scalar(format_args!("dateTime.iso8601({})", formatted)),
+            None => self.scalar(format_args!("dateTime.iso8601({:?})", value)),
+        }
+    }
+
+    fn start_base64(&mut self) -> fmt::Result {
+        self.base64_len = 0;
+        Ok(())
+    }
+
+    fn base64_chunk(&mut self, data: &[u8]) -> fmt::Result {
+        self.base64_len += data.len();
+        Ok(())
+    }
+
+    fn end_base64(&mut self) -> fmt::Result {
+        let size = ByteSize(self.base64_len);
+        self.scalar(format_args!("base64({})", size))
+    }
+
+    fn start_struct(&mut self) -> fmt::Result {
+        self.open('{', false)
+    }
+
+    fn member(&mut self, name: &str) -> fmt::Result {
+        self.separate()?;
+        let plain = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.');
+        if plain {
+            self.write(format_args!("{}: ", name))
+        } else {
+            self.write(format_args!("{:?}: ", name))
+        }
+    }
+
+    fn end_struct(&mut self) -> fmt::Result {
+        self.close('}')
+    }
+
+    fn start_array(&mut self) -> fmt::Result {
+        self.open('[', true)
+    }
+
+    fn end_array(&mut self) -> fmt::Result {
+        self.close(']')
+    }
+
+    fn nil(&mut self) -> fmt::Result {
+        self.scalar(format_args!("nil"))
+    }
+
+    fn big_integer(&mut self, value: &str) -> fmt::Result {
+        self.typed_string("ex:biginteger", value)
+    }
+
+    fn big_decimal(&mut self, value: &str) -> fmt::Result {
+        self.typed_string("ex:bigdecimal", value)
+    }
+
+    fn serializable(&mut self, data: &[u8]) -> fmt::Result {
+        let size = ByteSize(data.len());
+        self.scalar(format_args!("ex:serializable({})", size))
+    }
+
+    fn dom(&mut self, xml: &str) -> fmt::Result {
+        self.typed_string("ex:dom", xml)
+    }
+}
+
+/// Formats the value in a compact, human-readable form for logs and error messages.
+///
+/// Structs are written as `{name: value, ...}` and arrays as `[value, ...]`. Integers, doubles and
+/// date/times are annotated with their XML-RPC type, as in `i4(1)`, `i8(1)` or `double(1.5)`.
+/// Binary data is only shown by its size, as in `base64(1.2 KiB)`, and strings are truncated to
+/// 80 characters, or the precision if one is given (`{:.20}`).
+///
+/// The alternate flag (`{:#}`) puts each struct member and array element on a line of its own,
+/// indented by two spaces per level.
+///
+/// This representation is not meant to be parsed; use [`write_as_xml`] to serialize values.
+///
+/// # Examples
+///
+/// ```
+/// # use xmlrpc::Value;
+/// let value = Value::Struct(vec![
+///     ("data".to_string(), Value::Base64(vec![0; 1500])),
+///     ("id".to_string(), Value::Int64(7)),
+///     ("tags".to_string(), Value::Array(vec![Value::from("a"), Value::Nil])),
+/// ].into_iter().collect());
+///
+/// assert_eq!(
+///     value.to_string(),
+///     r#"{data: base64(1.5 KiB), id: i8(7), tags: ["a", nil]}"#
+/// );
+/// ```
+///
+/// [`write_as_xml`]: #method.write_as_xml
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.visit(&mut DisplayWriter::new(f))
+    }
+}
+
+/// Formats the value in the same way as `Value`.
+impl<'a> fmt::Display for ValueRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.visit(&mut DisplayWriter::new(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iso8601;
+    use Map;
+
+    fn sample() -> Value {
+        // Inserted in sorted order, so that the output doesn't depend on the `indexmap` feature
+        let mut map = Map::new();
+        map.insert(
+            "date".to_string(),
+            Value::DateTime(iso8601::datetime("19980717T14:08:55").unwrap()),
+        );
+        map.insert("double".to_string(), Value::Double(1.5));
+        map.insert("empty".to_string(), Value::Array(Vec::new()));
+        map.insert("int".to_string(), Value::Int(-3));
+        map.insert("int64".to_string(), Value::Int64(1 << 40));
+        map.insert("odd name".to_string(), Value::Bool(false));
+        Value::Array(vec![
+            Value::Struct(map),
+            Value::from("quote \" and\nnewline"),
+            Value::Base64(vec![1, 2, 3]),
+            Value::Nil,
+        ])
+    }
+
+    #[test]
+    fn displays_compactly() {
+        assert_eq!(
+            sample().to_string(),
+            "[{date: dateTime.iso8601(19980717T14:08:55), double: double(1.5), empty: [], \
+             int: i4(-3), int64: i8(1099511627776), \"odd name\": false}, \
+             \"quote \\\" and\\nnewline\", base64(3 B), nil]"
+        );
+    }
+
+    #[test]
+    fn displays_pretty() {
+        assert_eq!(
+            format!("{:#}", sample()),
+            r#"[
+  {
+    date: dateTime.iso8601(19980717T14:08:55),
+    double: double(1.5),
+    empty: [],
+    int: i4(-3),
+    int64: i8(1099511627776),
+    "odd name": false
+  },
+  "quote \" and\nnewline",
+  base64(3 B),
+  nil
+]"#
+        );
+        assert_eq!(format!("{:#}", Value::Struct(Map::new())), "{}");
+        assert_eq!(format!("{:#}", Value::Int(1)), "i4(1)");
+    }
+
+    #[test]
+    fn truncates_long_strings() {
+        let long = "x".repeat(100);
+        assert_eq!(
+            Value::from(long.as_str()).to_string(),
+            format!("{:?}… (100 chars)", "x".repeat(80))
+        );
+        assert_eq!(format!("{:.3}", Value::from("äöüß")), "\"äöü\"… (4 chars)");
+        assert_eq!(format!("{:.4}", Value::from("äöüß")), "\"äöüß\"");
+        assert_eq!(
+            format!("{:.2}", Value::Dom("<a/>".to_string())),
+            "ex:dom(\"<a\"… (4 chars))"
+        );
+    }
+
+    #[test]
+    fn formats_byte_sizes() {
+        assert_eq!(ByteSize(0).to_string(), "0 B");
+        assert_eq!(ByteSize(1023).to_string(), "1023 B");
+        assert_eq!(ByteSize(1229).to_string(), "1.2 KiB");
+        assert_eq!(ByteSize(5 << 20).to_string(), "5.0 MiB");
+        assert_eq!(
+            Value::Serializable(vec![0; 2048]).to_string(),
+            "ex:serializable(2.0 KiB)"
+        );
+    }
+
+    #[test]
+    fn displays_value_refs_like_values() {
+        let value = sample();
+        assert_eq!(ValueRef::from(&value).to_string(), value.to_string());
+        assert_eq!(
+            format!("{:#}", ValueRef::from(&value)),
+            format!("{:#}", value)
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Transcoding of non-UTF-8 documents.
+
+use error::ParseError;
+
+use encoding_rs::{CoderResult, Decoder, Encoding, UTF_8};
+
+use std::io::{self, Read};
+use std::str;
+
+/// Maximum number of bytes inspected when looking for the XML declaration.
+const SNIFF_LEN: usize = 1024;
+
+/// A `Read` adapter that transcodes an XML document to UTF-8.
+///
+/// The encoding is determined from the byte order mark, the `charset` parameter of the HTTP
+/// `Content-Type` header, or the `encoding` declared in the XML declaration, in that order.
+/// Documents that are already UTF-8 encoded are passed through unchanged. Otherwise, the XML
+/// declaration is removed from the transcoded document, since it would be incorrect.
+pub struct Utf8Reader<R> {
+    inner: R,
+    /// The decoder used to transcode the document, or `None` if it is already UTF-8.
+    decoder: Option<Decoder>,
+    /// Data that was read or decoded, but not yet returned.
+    pending: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> Utf8Reader<R> {
+    /// Creates a new `Utf8Reader`, reading the start of the document to determine its encoding.
+    ///
+    /// `charset` is the encoding specified by the transport (eg. in the HTTP `Content-Type`
+    /// header), if any.
+    pub fn new(mut inner: R, charset: Option<&str>) -> Result<Self, ParseError> {
+        let mut prefix = Vec::new();
+        let mut eof = false;
+        while prefix.len() < SNIFF_LEN && !contains(&prefix, b"?>") {
+            let mut buf = [0; 256];
+            let len = inner.read(&mut buf)?;
+            if len == 0 {
+                eof = true;
+                break;
+            }
+            prefix.extend_from_slice(&buf[..len]);
+        }
+
+        let encoding = match Encoding::for_bom(&prefix) {
+            Some((encoding, _)) => encoding,
+            None => match charset.or_else(|| declared_encoding(&prefix)) {
+                Some(label) => Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| {
+                    ParseError::UnsupportedEncoding {
+                        encoding: label.to_string(),
+                    }
+                })?,
+                None => UTF_8,
+            },
+        };
+
+        let mut reader = Utf8Reader {
+            inner,
+            decoder: None,
+            pending: Vec::new(),
+            pos: 0,
+            eof,
+        };
+
+        if encoding == UTF_8 {
+            reader.pending = prefix;
+        } else {
+            reader.decoder = Some(encoding.new_decoder_with_bom_removal());
+            reader.decode(&prefix, eof);
+            if reader.pending.starts_with(b"<?xml") {
+                if let Some(end) = find(&reader.pending, b"?>") {
+                    reader.pending.drain(..end + 2);
+                }
+            }
+        }
+
+        Ok(reader)
+    }
+
+    /// Decodes `src` and appends the UTF-8 output to `self.pending`.
+    fn decode(&mut self, mut src: &[u8], last: bool) {
+        let decoder = self.decoder.as_mut().unwrap();
+        loop {
+            let start = self.pending.len();
+            let max_len = decoder
+                .max_utf8_buffer_length(src.len())
+                .unwrap_or(src.len() * 3 + 16);
+            self.pending.resize(start + max_len, 0);
+            let (result, read, written, _) =
+                decoder.decode_to_utf8(src, &mut self.pending[start..], last);
+            self.pending.truncate(start + written);
+            src = &src[read..];
+            if let CoderResult::InputEmpty = result {
+                return;
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for Utf8Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.pos < self.pending.len() {
+                let len = buf.len().min(self.pending.len() - self.pos);
+                buf[..len].copy_from_slice(&self.pending[self.pos..self.pos + len]);
+                self.pos += len;
+                return Ok(len);
+            }
+            if self.eof {
+                return Ok(0);
+            }
+            if self.decoder.is_none() {
+                return self.inner.read(buf);
+            }
+
+            self.pending.clear();
+            self.pos = 0;
+            let mut chunk = [0; 4096];
+            let len = self.inner.read(&mut chunk)?;
+            self.eof = len == 0;
+            let eof = self.eof;
+            self.decode(&chunk[..len], eof);
+        }
+    }
+}
+
+/// Extracts the `charset` parameter from the value of a `Content-Type` header.
+pub fn charset_from_content_type(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let mut parts = param.splitn(2, '=');
+        let name = parts.next()?.trim();
+        let value = parts.next()?.trim();
+        if name.eq_ignore_ascii_case("charset") {
+            Some(value.trim_matches('"'))
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the value of the `encoding` pseudo-attribute of the XML declaration at the start of
+/// `document`, if present.
+fn declared_encoding(document: &[u8]) -> Option<&str> {
+    if !document.starts_with(b"<?xml") {
+        return None;
+    }
+    let declaration = &document[..find(document, b"?>")?];
+    let declaration = str::from_utf8(declaration).ok()?;
+
+    let rest = &declaration[declaration.find("encoding")? + "encoding".len()..];
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'')?;
+    let rest = &rest[1..];
+    Some(&rest[..rest.find(quote)?])
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    find(haystack, needle).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcode(document: &[u8], charset: Option<&str>) -> Result<String, ParseError> {
+        let mut reader = Utf8Reader::new(document, charset)?;
+        let mut string = String::new();
+        reader.read_to_string(&mut string).unwrap();
+        Ok(string)
+    }
+
+    #[test]
+    fn passes_utf8_through() {
+        let document = "<?xml version=\"1.0\" encoding=\"UTF-8\"?><value>Grüße</value>";
+        assert_eq!(transcode(document.as_bytes(), None).unwrap(), document);
+        assert_eq!(
+            transcode(b"<value>plain</value>", None).unwrap(),
+            "<value>plain</value>"
+        );
+    }
+
+    #[test]
+    fn transcodes_declared_encoding() {
+        let document = b"<?xml version='1.0' encoding = 'ISO-8859-1'?>\n<value>Gr\xfc\xdfe</value>";
+        assert_eq!(transcode(document, None).unwrap(), "\n<value>Grüße</value>");
+
+        let document = b"<?xml version=\"1.0\" encoding=\"windows-1252\"?><value>\x80</value>";
+        assert_eq!(transcode(document, None).unwrap(), "<value>€</value>");
+    }
+
+    #[test]
+    fn charset_overrides_declaration() {
+        let document = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><value>\xe9</value>";
+        assert_eq!(
+            transcode(document, Some("latin1")).unwrap(),
+            "<value>é</value>"
+        );
+    }
+
+    #[test]
+    fn transcodes_utf16_with_bom() {
+        let document: Vec<u8> = "\u{feff}<?xml version=\"1.0\"?><value>é</value>"
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect();
+        assert_eq!(transcode(&document, None).unwrap(), "<value>é</value>");
+    }
+
+    #[test]
+    fn rejects_unknown_encoding() {
+        assert_eq!(
+            transcode(b"<?xml version=\"1.0\" encoding=\"EBCDIC-XYZ\"?>", None).unwrap_err(),
+            ParseError::UnsupportedEncoding {
+                encoding: "EBCDIC-XYZ".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn parses_content_type() {
+        assert_eq!(
+            charset_from_content_type("text/xml; charset=\"ISO-8859-1\""),
+            Some("ISO-8859-1")
+        );
+        assert_eq!(
+            charset_from_content_type("text/xml;Charset=utf-8"),
+            Some("utf-8")
+        );
+        assert_eq!(charset_from_content_type("text/xml"), None);
+    }
+}
@@ -1,13 +1,16 @@
 //! Defines error types used by this library.
 
-use Value;
+use writer::{write_document, WriterOptions, XmlDocument, XmlWriter};
+use {Map, Value};
 
 use xml::common::TextPosition;
 use xml::reader::Error as XmlError;
 
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::error;
 use std::fmt::{self, Display, Formatter};
-use std::{error, io};
+use std::io::{self, Write};
 
 /// Errors that can occur when trying to perform an XML-RPC request.
 ///
@@ -15,35 +18,151 @@ use std::{error, io};
 /// server (maybe it's not implementing XML-RPC correctly), or just a failure to execute the
 /// operation.
 #[derive(Debug)]
-pub struct Error(RequestErrorKind);
+pub struct Error {
+    kind: RequestErrorKind,
+    /// The raw response that could not be parsed, if any.
+    response: Option<Box<RawResponse>>,
+}
 
 impl Error {
     /// If this `Error` was caused by the server responding with a `<fault>` response,
     /// returns the `Fault` in question.
     pub fn fault(&self) -> Option<&Fault> {
-        match self.0 {
+        match self.kind {
             RequestErrorKind::Fault(ref fault) => Some(fault),
             _ => None,
         }
     }
+
+    /// If this `Error` was caused by a response that could not be parsed, returns the
+    /// `ParseError` in question.
+    pub fn parse_error(&self) -> Option<&ParseError> {
+        match self.kind {
+            RequestErrorKind::ParseError(ref err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Returns the raw server response that caused this error, if it is available.
+    ///
+    /// This is the case when the response could not be parsed, or when the transport rejected the
+    /// response and returned a [`ResponseError`] (the built-in HTTP transport does this for error
+    /// status codes and unexpected `Content-Type`s).
+    ///
+    /// [`ResponseError`]: struct.ResponseError.html
+    pub fn raw_response(&self) -> Option<&RawResponse> {
+        if let Some(ref response) = self.response {
+            return Some(response);
+        }
+
+        match self.kind {
+            RequestErrorKind::TransportError(ref err) => {
+                err.downcast_ref::<ResponseError>().map(|err| &err.response)
+            }
+            _ => None,
+        }
+    }
+
+    /// Attaches the raw response that caused this error.
+    pub(crate) fn with_response(mut self, response: RawResponse) -> Self {
+        self.response = Some(Box::new(response));
+        self
+    }
 }
 
 #[doc(hidden)] // hide internal impl
 impl From<RequestErrorKind> for Error {
     fn from(kind: RequestErrorKind) -> Self {
-        Error(kind)
+        Error {
+            kind,
+            response: None,
+        }
     }
 }
 
 impl Display for Error {
     fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
-        self.0.fmt(fmt)
+        self.kind.fmt(fmt)
     }
 }
 
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        self.0.source()
+        self.kind.source()
+    }
+}
+
+/// The raw data of a server response that could not be processed.
+///
+/// This is attached to an [`Error`] when the response could not be parsed or was rejected by the
+/// transport, which helps when debugging misbehaving servers (for example, a proxy that returns an
+/// HTML error page instead of an XML-RPC response).
+///
+/// [`Error`]: struct.Error.html
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RawResponse {
+    /// The HTTP status code, if the response was received via HTTP.
+    pub status: Option<u16>,
+    /// The response headers as `(name, value)` pairs, if known.
+    pub headers: Vec<(String, String)>,
+    /// The start of the response body, at most [`MAX_BODY_LEN`] bytes long.
+    ///
+    /// [`MAX_BODY_LEN`]: #associatedconstant.MAX_BODY_LEN
+    pub body: Vec<u8>,
+    /// Whether the response body was longer than `body`.
+    pub body_truncated: bool,
+}
+
+impl RawResponse {
+    /// The maximum number of body bytes that will be retained.
+    pub const MAX_BODY_LEN: usize = 4096;
+
+    /// Returns the retained part of the body as a string, replacing invalid UTF-8 sequences.
+    pub fn body_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+}
+
+/// A transport error caused by a response that was rejected by the transport.
+///
+/// Custom [`Transport`]s can return this error from `transmit` to make the offending response
+/// available via [`Error::raw_response`].
+///
+/// [`Transport`]: trait.Transport.html
+/// [`Error::raw_response`]: struct.Error.html#method.raw_response
+#[derive(Debug)]
+pub struct ResponseError {
+    error: Box<dyn error::Error + Send + Sync>,
+    response: RawResponse,
+}
+
+impl ResponseError {
+    /// Creates a new `ResponseError` from the reason why `response` was rejected.
+    pub fn new<E>(error: E, response: RawResponse) -> Self
+    where
+        E: Into<Box<dyn error::Error + Send + Sync>>,
+    {
+        ResponseError {
+            error: error.into(),
+            response,
+        }
+    }
+
+    /// Returns the rejected response.
+    pub fn response(&self) -> &RawResponse {
+        &self.response
+    }
+}
+
+impl Display for ResponseError {
+    fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
+        self.error.fmt(fmt)
+    }
+}
+
+impl error::Error for ResponseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&*self.error)
     }
 }
 
@@ -95,6 +214,7 @@ impl error::Error for RequestErrorKind {
 
 /// Describes possible error that can occur when parsing a `Response`.
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum ParseError {
     /// Error while parsing (malformed?) XML.
     XmlError(XmlError),
@@ -115,10 +235,88 @@ pub enum ParseError {
     UnexpectedXml {
         /// A short description of the kind of data that was expected.
         expected: String,
+        /// A short description of the data that was found instead, if available.
         found: Option<String>,
         /// The position of the unexpected data inside the XML document.
         position: TextPosition,
     },
+
+    /// A struct contained the same member name more than once.
+    ///
+    /// This is only reported when [`ParserOptions::reject_duplicate_members`] is enabled.
+    ///
+    /// [`ParserOptions::reject_duplicate_members`]: struct.ParserOptions.html#method.reject_duplicate_members
+    DuplicateMember {
+        /// The duplicated member name.
+        name: String,
+        /// The position of the duplicate member inside the XML document.
+        position: TextPosition,
+    },
+
+    /// The document uses a character encoding that isn't supported.
+    UnsupportedEncoding {
+        /// The name of the encoding, as specified by the document or the transport.
+        encoding: String,
+    },
+
+    /// Values were nested more deeply than allowed by [`ParserOptions::max_depth`].
+    ///
+    /// [`ParserOptions::max_depth`]: struct.ParserOptions.html#method.max_depth
+    DepthLimitExceeded {
+        /// The configured limit.
+        limit: usize,
+        /// The position of the value exceeding the limit.
+        position: TextPosition,
+    },
+
+    /// The document was larger than allowed by [`ParserOptions::max_document_size`].
+    ///
+    /// [`ParserOptions::max_document_size`]: struct.ParserOptions.html#method.max_document_size
+    DocumentTooLarge {
+        /// The configured limit in bytes.
+        limit: u64,
+    },
+
+    /// A string or member name was longer than allowed by [`ParserOptions::max_string_length`].
+    ///
+    /// [`ParserOptions::max_string_length`]: struct.ParserOptions.html#method.max_string_length
+    StringTooLong {
+        /// The configured limit in bytes.
+        limit: usize,
+        /// The position of the string exceeding the limit.
+        position: TextPosition,
+    },
+
+    /// Base64 data was larger than allowed by [`ParserOptions::max_base64_size`].
+    ///
+    /// [`ParserOptions::max_base64_size`]: struct.ParserOptions.html#method.max_base64_size
+    Base64TooLarge {
+        /// The configured limit in bytes.
+        limit: usize,
+        /// The position of the data exceeding the limit.
+        position: TextPosition,
+    },
+
+    /// The document contained more elements than allowed by [`ParserOptions::max_elements`].
+    ///
+    /// [`ParserOptions::max_elements`]: struct.ParserOptions.html#method.max_elements
+    TooManyElements {
+        /// The configured limit.
+        limit: usize,
+        /// The position of the element exceeding the limit.
+        position: TextPosition,
+    },
+
+    /// A value could not be deserialized into the requested type.
+    ///
+    /// This is only reported when deserializing a response with serde (for example, when a value
+    /// has the wrong type or a required struct member is missing).
+    Deserialize {
+        /// The error message reported by serde.
+        message: String,
+        /// The position of the value that could not be deserialized.
+        position: TextPosition,
+    },
 }
 
 impl From<XmlError> for ParseError {
@@ -164,6 +362,44 @@ impl Display for ParseError {
                 "unexpected XML at {} (expected {}, found {})",
                 position, expected, found
             ),
+            ParseError::DuplicateMember {
+                ref name,
+                ref position,
+            } => write!(fmt, "duplicate struct member '{}' at {}", name, position),
+            ParseError::UnsupportedEncoding { ref encoding } => {
+                write!(fmt, "unsupported encoding '{}'", encoding)
+            }
+            ParseError::DepthLimitExceeded {
+                limit,
+                ref position,
+            } => write!(
+                fmt,
+                "values nested more than {} levels deep at {}",
+                limit, position
+            ),
+            ParseError::DocumentTooLarge { limit } => {
+                write!(fmt, "document larger than {} bytes", limit)
+            }
+            ParseError::StringTooLong {
+                limit,
+                ref position,
+            } => write!(fmt, "string longer than {} bytes at {}", limit, position),
+            ParseError::Base64TooLarge {
+                limit,
+                ref position,
+            } => write!(
+                fmt,
+                "base64 data larger than {} bytes at {}",
+                limit, position
+            ),
+            ParseError::TooManyElements {
+                limit,
+                ref position,
+            } => write!(fmt, "more than {} elements at {}", limit, position),
+            ParseError::Deserialize {
+                ref message,
+                ref position,
+            } => write!(fmt, "{} at {}", message, position),
         }
     }
 }
@@ -174,15 +410,113 @@ impl error::Error for ParseError {}
 ///
 /// The XML-RPC specification requires that a `<faultCode>` and `<faultString>` is returned in the
 /// `<fault>` case, further describing the error.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq)]
 pub struct Fault {
     /// An application-specific error code.
     pub fault_code: i32,
     /// Human-readable error description.
     pub fault_string: String,
+    /// Additional, non-standard members of the fault struct.
+    extra: Map,
 }
 
 impl Fault {
+    /// Parse error: the XML is not well-formed.
+    pub const PARSE_ERROR: i32 = -32700;
+    /// Parse error: the document uses an unsupported encoding.
+    pub const UNSUPPORTED_ENCODING: i32 = -32701;
+    /// Parse error: the document contains a character that is invalid for its encoding.
+    pub const INVALID_CHARACTER: i32 = -32702;
+    /// Server error: the request is well-formed XML, but not a valid XML-RPC request.
+    pub const INVALID_REQUEST: i32 = -32600;
+    /// Server error: the requested method does not exist.
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    /// Server error: the parameters passed to the method are invalid.
+    pub const INVALID_PARAMS: i32 = -32602;
+    /// Server error: an internal XML-RPC error occurred.
+    pub const INTERNAL_ERROR: i32 = -32603;
+    /// An error raised by the called method itself.
+    pub const APPLICATION_ERROR: i32 = -32500;
+    /// An error raised by the system the server runs on.
+    pub const SYSTEM_ERROR: i32 = -32400;
+    /// An error in the transport layer.
+    pub const TRANSPORT_ERROR: i32 = -32300;
+
+    /// Creates a `Fault` from a fault code and a description.
+    pub fn new<S: Into<String>>(fault_code: i32, fault_string: S) -> Self {
+        Fault {
+            fault_code,
+            fault_string: fault_string.into(),
+            extra: Map::new(),
+        }
+    }
+
+    /// Creates a `Fault` from one of the standard interoperability fault kinds.
+    pub fn from_kind<S: Into<String>>(kind: FaultKind, fault_string: S) -> Self {
+        Fault::new(kind.code(), fault_string)
+    }
+
+    /// Creates a `Fault` indicating that a request was not well-formed XML.
+    pub fn parse_error<S: Into<String>>(fault_string: S) -> Self {
+        Fault::from_kind(FaultKind::ParseError, fault_string)
+    }
+
+    /// Creates a `Fault` indicating that a request was not a valid XML-RPC request.
+    pub fn invalid_request<S: Into<String>>(fault_string: S) -> Self {
+        Fault::from_kind(FaultKind::InvalidRequest, fault_string)
+    }
+
+    /// Creates a `Fault` indicating that the method `method_name` does not exist.
+    pub fn method_not_found(method_name: &str) -> Self {
+        Fault::from_kind(
+            FaultKind::MethodNotFound,
+            format!("method '{}' not found", method_name),
+        )
+    }
+
+    /// Creates a `Fault` indicating that invalid parameters were passed to a method.
+    pub fn invalid_params<S: Into<String>>(fault_string: S) -> Self {
+        Fault::from_kind(FaultKind::InvalidParams, fault_string)
+    }
+
+    /// Creates a `Fault` indicating an internal error in the XML-RPC implementation.
+    pub fn internal_error<S: Into<String>>(fault_string: S) -> Self {
+        Fault::from_kind(FaultKind::InternalError, fault_string)
+    }
+
+    /// Creates a `Fault` indicating an error raised by the called method.
+    pub fn application_error<S: Into<String>>(fault_string: S) -> Self {
+        Fault::from_kind(FaultKind::ApplicationError, fault_string)
+    }
+
+    /// Returns the additional, non-standard members of the fault struct.
+    ///
+    /// This is always empty unless the fault was parsed leniently (see
+    /// [`Fault::from_value_lenient`]) or members were added using [`extra_mut`].
+    ///
+    /// [`Fault::from_value_lenient`]: #method.from_value_lenient
+    /// [`extra_mut`]: #method.extra_mut
+    pub fn extra(&self) -> &Map {
+        &self.extra
+    }
+
+    /// Returns a mutable reference to the additional members of the fault struct.
+    ///
+    /// Members named `faultCode` or `faultString` are only written by [`to_value`] in place of a
+    /// fault code of 0 or an empty fault string.
+    ///
+    /// [`to_value`]: #method.to_value
+    pub fn extra_mut(&mut self) -> &mut Map {
+        &mut self.extra
+    }
+
+    /// Classifies this `Fault` according to the standard interoperability fault codes.
+    ///
+    /// Returns `None` if the fault code is application-specific.
+    pub fn kind(&self) -> Option<FaultKind> {
+        FaultKind::from_code(self.fault_code)
+    }
+
     /// Creates a `Fault` from a `Value`.
     ///
     /// The `Value` must be a `Value::Struct` with a `faultCode` and `faultString` field (and no
@@ -198,11 +532,8 @@ impl Fault {
                 }
 
                 match (map.get("faultCode"), map.get("faultString")) {
-                    (Some(&Value::Int(fault_code)), Some(&Value::String(ref fault_string))) => {
-                        Some(Fault {
-                            fault_code,
-                            fault_string: fault_string.to_string(),
-                        })
+                    (Some(Value::Int(fault_code)), Some(Value::String(fault_string))) => {
+                        Some(Fault::new(*fault_code, fault_string.as_str()))
                     }
                     _ => None,
                 }
@@ -211,20 +542,122 @@ impl Fault {
         }
     }
 
+    /// Creates a `Fault` from a `Value`, tolerating common deviations from the specification.
+    ///
+    /// The `Value` must be a `Value::Struct`, but unlike [`from_value`], this method accepts:
+    ///
+    /// * Additional members, which are preserved in [`extra`].
+    /// * A `faultCode` of type `i8` (if it fits in an `i32`) or a string containing an integer.
+    /// * A `faultCode` that can't be converted (such as `"Server.Error"`) or a `faultString` that
+    ///   isn't a string. These result in a code of 0 or an empty string, and the original member
+    ///   is kept in [`extra`] so that the fault can still be identified.
+    /// * A missing `faultCode` (which results in a code of 0) or `faultString` (which results in
+    ///   an empty string).
+    ///
+    /// Returns `None` if the value isn't a struct.
+    ///
+    /// [`from_value`]: #method.from_value
+    /// [`extra`]: #method.extra
+    pub fn from_value_lenient(value: &Value) -> Option<Self> {
+        let map = match *value {
+            Value::Struct(ref map) => map,
+            _ => return None,
+        };
+        let fault_code = match map.get("faultCode") {
+            Some(&Value::Int(code)) => Some(code),
+            Some(&Value::Int64(code)) => i32::try_from(code).ok(),
+            Some(Value::String(code)) => code.trim().parse().ok(),
+            _ => None,
+        };
+        let fault_string = match map.get("faultString") {
+            Some(Value::String(string)) => Some(string.clone()),
+            _ => None,
+        };
+        // Keep members that couldn't be converted, so they aren't lost
+        let extra = map
+            .iter()
+            .filter(|&(name, _)| match name.as_str() {
+                "faultCode" => fault_code.is_none(),
+                "faultString" => fault_string.is_none(),
+                _ => true,
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        let fault_code = fault_code.unwrap_or(0);
+        let fault_string = fault_string.unwrap_or_default();
+
+        Some(Fault {
+            fault_code,
+            fault_string,
+            extra,
+        })
+    }
+
     /// Turns this `Fault` into an equivalent `Value`.
     ///
     /// The returned value can be parsed back into a `Fault` using `Fault::from_value` or returned
     /// as a `<fault>` error response by serializing it into a `<fault></fault>` tag.
+    ///
+    /// Members in [`extra`] are included in the struct. A `faultCode` or `faultString` member in
+    /// [`extra`] (as kept by [`from_value_lenient`] when it can't convert them) is written instead
+    /// of a `fault_code` of 0 or an empty `fault_string`, and ignored otherwise.
+    ///
+    /// [`extra`]: #method.extra
+    /// [`from_value_lenient`]: #method.from_value_lenient
     pub fn to_value(&self) -> Value {
-        let mut map = BTreeMap::new();
-        map.insert("faultCode".to_string(), Value::from(self.fault_code));
-        map.insert(
-            "faultString".to_string(),
-            Value::from(self.fault_string.as_ref()),
-        );
+        let mut map = self.extra.clone();
+        if self.fault_code != 0 || !map.contains_key("faultCode") {
+            map.insert("faultCode".to_string(), Value::from(self.fault_code));
+        }
+        if !self.fault_string.is_empty() || !map.contains_key("faultString") {
+            map.insert(
+                "faultString".to_string(),
+                Value::from(self.fault_string.as_ref()),
+            );
+        }
 
         Value::Struct(map)
     }
+
+    /// Formats this `Fault` as a `<methodResponse>` document containing a `<fault>`.
+    ///
+    /// This is the response a server returns when a call fails. Combined with the conversion from
+    /// [`ParseError`] and the constructors for the standard fault codes, it allows servers to
+    /// report errors in an interoperable way.
+    ///
+    /// # Errors
+    ///
+    /// Any errors reported by the writer will be propagated to the caller.
+    ///
+    /// [`ParseError`]: enum.ParseError.html
+    pub fn write_response<W: Write>(&self, fmt: &mut W) -> io::Result<()> {
+        self.write_response_with(fmt, &WriterOptions::default())
+    }
+
+    /// Formats this `Fault` as a `<methodResponse>` document, using the given [`WriterOptions`].
+    ///
+    /// Unlike [`Value::write_as_xml_with`], this writes a complete document in the encoding
+    /// selected in `options`.
+    ///
+    /// [`WriterOptions`]: struct.WriterOptions.html
+    /// [`Value::write_as_xml_with`]: enum.Value.html#method.write_as_xml_with
+    pub fn write_response_with<W: Write>(
+        &self,
+        fmt: &mut W,
+        options: &WriterOptions,
+    ) -> io::Result<()> {
+        write_document(fmt, self, options)
+    }
+}
+
+impl XmlDocument for Fault {
+    fn write_root<W: Write>(&self, writer: &mut XmlWriter<'_, W>) -> io::Result<()> {
+        writer.open(&["methodResponse"])?;
+        writer.open(&["fault"])?;
+        self.to_value().visit(writer)?;
+        writer.close(&["fault"])?;
+        writer.close_root("methodResponse")
+    }
 }
 
 impl Display for Fault {
@@ -235,6 +668,118 @@ impl Display for Fault {
 
 impl error::Error for Fault {}
 
+/// Maps errors encountered while parsing an incoming request to the standard fault codes.
+///
+/// Malformed XML is reported as [`Fault::PARSE_ERROR`] (or [`Fault::UNSUPPORTED_ENCODING`]),
+/// while documents that aren't valid XML-RPC or exceed the parser's resource limits are reported
+/// as [`Fault::INVALID_REQUEST`]. Values that could not be deserialized into the expected type are
+/// reported as [`Fault::INVALID_PARAMS`].
+///
+/// [`Fault::PARSE_ERROR`]: struct.Fault.html#associatedconstant.PARSE_ERROR
+/// [`Fault::UNSUPPORTED_ENCODING`]: struct.Fault.html#associatedconstant.UNSUPPORTED_ENCODING
+/// [`Fault::INVALID_REQUEST`]: struct.Fault.html#associatedconstant.INVALID_REQUEST
+/// [`Fault::INVALID_PARAMS`]: struct.Fault.html#associatedconstant.INVALID_PARAMS
+impl<'a> From<&'a ParseError> for Fault {
+    fn from(err: &'a ParseError) -> Self {
+        match *err {
+            ParseError::XmlError(_) => Fault::parse_error(err.to_string()),
+            ParseError::UnsupportedEncoding { .. } => {
+                Fault::from_kind(FaultKind::UnsupportedEncoding, err.to_string())
+            }
+            ParseError::InvalidValue { .. }
+            | ParseError::UnexpectedXml { .. }
+            | ParseError::DuplicateMember { .. }
+            | ParseError::DepthLimitExceeded { .. }
+            | ParseError::DocumentTooLarge { .. }
+            | ParseError::StringTooLong { .. }
+            | ParseError::Base64TooLarge { .. }
+            | ParseError::TooManyElements { .. } => Fault::invalid_request(err.to_string()),
+            ParseError::Deserialize { .. } => Fault::invalid_params(err.to_string()),
+        }
+    }
+}
+
+/// The well-known fault codes from the [specification for fault code interoperability].
+///
+/// [specification for fault code interoperability]: http://xmlrpc-epi.sourceforge.net/specs/rfc.fault_codes.php
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FaultKind {
+    /// Parse error: the XML is not well-formed (`-32700`).
+    ParseError,
+    /// Parse error: unsupported encoding (`-32701`).
+    UnsupportedEncoding,
+    /// Parse error: invalid character for encoding (`-32702`).
+    InvalidCharacter,
+    /// Server error: invalid XML-RPC, not conforming to the spec (`-32600`).
+    InvalidRequest,
+    /// Server error: requested method not found (`-32601`).
+    MethodNotFound,
+    /// Server error: invalid method parameters (`-32602`).
+    InvalidParams,
+    /// Server error: internal XML-RPC error (`-32603`).
+    InternalError,
+    /// Application error (`-32500`).
+    ApplicationError,
+    /// System error (`-32400`).
+    SystemError,
+    /// Transport error (`-32300`).
+    TransportError,
+}
+
+impl FaultKind {
+    /// Returns the fault code corresponding to this kind.
+    pub fn code(self) -> i32 {
+        match self {
+            FaultKind::ParseError => Fault::PARSE_ERROR,
+            FaultKind::UnsupportedEncoding => Fault::UNSUPPORTED_ENCODING,
+            FaultKind::InvalidCharacter => Fault::INVALID_CHARACTER,
+            FaultKind::InvalidRequest => Fault::INVALID_REQUEST,
+            FaultKind::MethodNotFound => Fault::METHOD_NOT_FOUND,
+            FaultKind::InvalidParams => Fault::INVALID_PARAMS,
+            FaultKind::InternalError => Fault::INTERNAL_ERROR,
+            FaultKind::ApplicationError => Fault::APPLICATION_ERROR,
+            FaultKind::SystemError => Fault::SYSTEM_ERROR,
+            FaultKind::TransportError => Fault::TRANSPORT_ERROR,
+        }
+    }
+
+    /// Returns the `FaultKind` with the given fault code, or `None` if the code is not one of the
+    /// standard codes.
+    pub fn from_code(code: i32) -> Option<Self> {
+        Some(match code {
+            Fault::PARSE_ERROR => FaultKind::ParseError,
+            Fault::UNSUPPORTED_ENCODING => FaultKind::UnsupportedEncoding,
+            Fault::INVALID_CHARACTER => FaultKind::InvalidCharacter,
+            Fault::INVALID_REQUEST => FaultKind::InvalidRequest,
+            Fault::METHOD_NOT_FOUND => FaultKind::MethodNotFound,
+            Fault::INVALID_PARAMS => FaultKind::InvalidParams,
+            Fault::INTERNAL_ERROR => FaultKind::InternalError,
+            Fault::APPLICATION_ERROR => FaultKind::ApplicationError,
+            Fault::SYSTEM_ERROR => FaultKind::SystemError,
+            Fault::TRANSPORT_ERROR => FaultKind::TransportError,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for FaultKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            FaultKind::ParseError => "parse error, not well formed",
+            FaultKind::UnsupportedEncoding => "parse error, unsupported encoding",
+            FaultKind::InvalidCharacter => "parse error, invalid character for encoding",
+            FaultKind::InvalidRequest => "server error, invalid XML-RPC",
+            FaultKind::MethodNotFound => "server error, requested method not found",
+            FaultKind::InvalidParams => "server error, invalid method parameters",
+            FaultKind::InternalError => "server error, internal XML-RPC error",
+            FaultKind::ApplicationError => "application error",
+            FaultKind::SystemError => "system error",
+            FaultKind::TransportError => "transport error",
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -243,14 +788,176 @@ mod tests {
 
     #[test]
     fn fault_roundtrip() {
-        let input = Fault {
-            fault_code: -123456,
-            fault_string: "The Bald Lazy House Jumps Over The Hyperactive Kitten".to_string(),
-        };
+        let input = Fault::new(
+            -123456,
+            "The Bald Lazy House Jumps Over The Hyperactive Kitten",
+        );
 
         assert_eq!(Fault::from_value(&input.to_value()), Some(input));
     }
 
+    #[test]
+    fn writes_fault_responses() {
+        let fault = Fault::method_not_found("x<y");
+        let mut output = Vec::new();
+        fault.write_response(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<methodResponse>\n<fault>\n\
+             <value><struct>\n<member>\n<name>faultCode</name>\n<value><i4>-32601</i4></value>\n\
+             </member>\n<member>\n<name>faultString</name>\n\
+             <value><string>method 'x&lt;y' not found</string></value>\n</member>\n\
+             </struct></value>\n</fault>\n</methodResponse>"
+        );
+        assert_eq!(
+            ::parse_response(&mut &output[..], &::ParserOptions::new()),
+            Ok(Err(fault))
+        );
+
+        // A server reporting a malformed request
+        let err =
+            ::parse_response(&mut &b"<methodResponse>"[..], &::ParserOptions::new()).unwrap_err();
+        let fault = Fault::from(&err);
+        let mut output = Vec::new();
+        let options = WriterOptions::new().encoding(::encoding_rs::WINDOWS_1252);
+        fault.write_response_with(&mut output, &options).unwrap();
+        assert!(output.starts_with(b"<?xml version=\"1.0\" encoding=\"windows-1252\"?>"));
+        let response = ::parse_response(&mut &output[..], &::ParserOptions::new()).unwrap();
+        assert_eq!(response.unwrap_err().kind(), Some(FaultKind::ParseError));
+    }
+
+    #[test]
+    fn lenient_fault() {
+        let mut map = Map::new();
+        map.insert("faultCode".to_string(), Value::from("  42 "));
+        map.insert("faultString".to_string(), Value::from("oops"));
+        map.insert("traceback".to_string(), Value::from("line 1"));
+        let value = Value::Struct(map);
+
+        assert_eq!(Fault::from_value(&value), None);
+        let fault = Fault::from_value_lenient(&value).unwrap();
+        assert_eq!(fault.fault_code, 42);
+        assert_eq!(fault.fault_string, "oops");
+        assert_eq!(fault.extra().len(), 1);
+        assert_eq!(fault.extra()["traceback"], Value::from("line 1"));
+        assert_eq!(Fault::from_value_lenient(&fault.to_value()), Some(fault));
+
+        let mut map = Map::new();
+        map.insert("faultCode".to_string(), Value::Int64(-32601));
+        let fault = Fault::from_value_lenient(&Value::Struct(map)).unwrap();
+        assert_eq!(fault.kind(), Some(FaultKind::MethodNotFound));
+        assert_eq!(fault.fault_string, "");
+
+        let mut map = Map::new();
+        map.insert("faultCode".to_string(), Value::Int64(1 << 40));
+        map.insert("faultString".to_string(), Value::Int(3));
+        let value = Value::Struct(map);
+        let fault = Fault::from_value_lenient(&value).unwrap();
+        assert_eq!(fault.fault_code, 0);
+        assert_eq!(fault.fault_string, "");
+        assert_eq!(fault.extra()["faultCode"], Value::Int64(1 << 40));
+        assert_eq!(fault.extra()["faultString"], Value::Int(3));
+        assert_eq!(fault.to_value(), value);
+
+        assert_eq!(Fault::from_value_lenient(&Value::from("oops")), None);
+    }
+
+    #[test]
+    fn lenient_fault_with_string_code() {
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse>
+  <fault>
+    <value><struct>
+      <member><name>faultCode</name><value><string>Server.Error</string></value></member>
+      <member><name>faultString</name><value><string>no such object</string></value></member>
+    </struct></value>
+  </fault>
+</methodResponse>"#;
+        let options = ::ParserOptions::new().lenient_faults(true);
+        let fault = ::parse_response(&mut xml.as_bytes(), &options)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(fault.fault_code, 0);
+        assert_eq!(fault.fault_string, "no such object");
+        assert_eq!(fault.extra()["faultCode"], Value::from("Server.Error"));
+
+        let mut output = Vec::new();
+        fault.write_response(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("<string>Server.Error</string>"));
+        assert!(!output.contains("<i4>0</i4>"));
+    }
+
+    #[test]
+    fn fault_kinds() {
+        let fault = Fault::method_not_found("system.frobnicate");
+        assert_eq!(fault.fault_code, -32601);
+        assert_eq!(fault.kind(), Some(FaultKind::MethodNotFound));
+        assert_eq!(
+            fault.to_string(),
+            "method 'system.frobnicate' not found (-32601)"
+        );
+
+        assert_eq!(Fault::new(4, "Too many parameters.").kind(), None);
+
+        for code in -32800..-32200 {
+            if let Some(kind) = FaultKind::from_code(code) {
+                assert_eq!(kind.code(), code);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_error_to_fault() {
+        let err = ParseError::UnexpectedXml {
+            expected: "<methodCall>".to_string(),
+            found: None,
+            position: TextPosition::new(),
+        };
+        assert_eq!(Fault::from(&err).kind(), Some(FaultKind::InvalidRequest));
+
+        let err = ParseError::from(io::Error::new(
+            io::ErrorKind::ConnectionReset,
+            "connection reset",
+        ));
+        assert_eq!(Fault::from(&err).kind(), Some(FaultKind::ParseError));
+
+        let err = ParseError::Deserialize {
+            message: "invalid type: string \"a\", expected i32".to_string(),
+            position: TextPosition::new(),
+        };
+        assert_eq!(Fault::from(&err).kind(), Some(FaultKind::InvalidParams));
+    }
+
+    #[test]
+    fn raw_response_of_rejected_response() {
+        let response = RawResponse {
+            status: Some(502),
+            headers: vec![("Content-Type".to_string(), "text/html".to_string())],
+            body: b"<html>Bad Gateway</html>".to_vec(),
+            body_truncated: false,
+        };
+        let err = Error::from(RequestErrorKind::TransportError(Box::new(
+            ResponseError::new(
+                "server response indicates error: 502 Bad Gateway",
+                response.clone(),
+            ),
+        )));
+
+        assert_eq!(err.raw_response(), Some(&response));
+        assert_eq!(
+            err.raw_response().unwrap().body_lossy(),
+            "<html>Bad Gateway</html>"
+        );
+        assert_eq!(
+            err.to_string(),
+            "transport error: server response indicates error: 502 Bad Gateway"
+        );
+
+        let err = Error::from(RequestErrorKind::TransportError("oh no".into()));
+        assert_eq!(err.raw_response(), None);
+    }
+
     #[test]
     fn error_impls_error() {
         fn assert_error<T: error::Error>() {}
@@ -0,0 +1,404 @@
+//! Conversions between `Value` and `serde_json::Value`.
+
+use utils::format_datetime;
+use {DateTimeFormat, Map, Value};
+
+use base64::{decode, encode};
+use iso8601::datetime;
+use serde_json::{Map as JsonMap, Number, Value as JsonValue};
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+
+const TAG_I8: &str = "$i8";
+const TAG_DOUBLE: &str = "$double";
+const TAG_BASE64: &str = "$base64";
+const TAG_DATETIME: &str = "$dateTime.iso8601";
+const TAG_BIG_INTEGER: &str = "$biginteger";
+const TAG_BIG_DECIMAL: &str = "$bigdecimal";
+const TAG_SERIALIZABLE: &str = "$serializable";
+const TAG_DOM: &str = "$dom";
+
+/// How values that JSON has no type for are represented when converting between `Value` and
+/// `serde_json::Value`.
+///
+/// Arrays, structs, strings, booleans, 32-bit integers, finite doubles and `Nil` are always
+/// converted to the equivalent JSON value. The other types are converted as follows:
+///
+/// | `Value`              | `Plain`                      | `Tagged`                                    |
+/// |----------------------|------------------------------|---------------------------------------------|
+/// | `Int64(n)`           | `n`                          | `{"$i8": n}`                                |
+/// | NaN or infinity      | `null`                       | `{"$double": "NaN"}` (or `"Infinity"`, `"-Infinity"`) |
+/// | `DateTime(_)`        | `"1998-07-17T14:08:55"`      | `{"$dateTime.iso8601": "1998-07-17T14:08:55"}` |
+/// | `Base64(data)`       | `data` as a base64 string    | `{"$base64": "..."}`                        |
+/// | `BigInteger(s)`      | `s`                          | `{"$biginteger": s}`                        |
+/// | `BigDecimal(s)`      | `s`                          | `{"$bigdecimal": s}`                        |
+/// | `Serializable(data)` | `data` as a base64 string    | `{"$serializable": "..."}`                  |
+/// | `Dom(xml)`           | `xml`                        | `{"$dom": xml}`                             |
+///
+/// Date/times are written like [`DateTimeFormat::Extended`], with the offset from UTC unless it
+/// is zero.
+///
+/// The plain encoding is meant for consumers that don't know about XML-RPC, and converting it back
+/// loses the type of the values above. With the tagged encoding, converting a `Value` to JSON and
+/// back results in the same `Value`, except that week and ordinal dates become calendar dates. To
+/// tell structs and tagged values apart, struct member names starting with `$` are escaped by
+/// prepending another `$` (`$ref` becomes `$$ref`).
+///
+/// When converting JSON to a `Value`, integers are converted to `Int` if they fit, and to `Int64`
+/// or `Double` otherwise.
+///
+/// [`DateTimeFormat::Extended`]: enum.DateTimeFormat.html#variant.Extended
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JsonEncoding {
+    /// Uses the closest JSON type, losing the distinction between some XML-RPC types.
+    #[default]
+    Plain,
+    /// Represents the types JSON lacks as objects with a single, `$`-prefixed member.
+    Tagged,
+}
+
+/// An error converting between `Value` and `serde_json::Value`.
+///
+/// This is returned for date/times that can not be formatted (see [`Value::DateTime`]), and for
+/// malformed tagged values.
+///
+/// [`Value::DateTime`]: enum.Value.html#variant.DateTime
+#[derive(Debug)]
+pub struct JsonError {
+    message: String,
+}
+
+impl JsonError {
+    fn invalid_tag(tag: &str, expected: &str) -> Self {
+        JsonError {
+            message: format!("the `{}` tag must contain {}", tag, expected),
+        }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for JsonError {}
+
+impl Value {
+    /// Converts this `Value` to JSON using the given encoding.
+    ///
+    /// This method is only available when the `json` feature is enabled.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value contains a date/time that can't be formatted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate serde_json;
+    /// # extern crate xmlrpc;
+    /// # use xmlrpc::{JsonEncoding, Value};
+    /// # fn main() {
+    /// let value = Value::Array(vec![Value::Int(1), Value::Int64(2)]);
+    /// let json = value.to_json(JsonEncoding::Tagged).unwrap();
+    /// assert_eq!(json.to_string(), r#"[1,{"$i8":2}]"#);
+    /// assert_eq!(Value::from_json(&json, JsonEncoding::Tagged).unwrap(), value);
+    /// # }
+    /// ```
+    pub fn to_json(&self, encoding: JsonEncoding) -> Result<JsonValue, JsonError> {
+        let tagged = encoding == JsonEncoding::Tagged;
+        let tag = |tag: &str, json: JsonValue| {
+            if tagged {
+                let mut object = JsonMap::new();
+                object.insert(tag.to_string(), json);
+                JsonValue::Object(object)
+            } else {
+                json
+            }
+        };
+
+        Ok(match *self {
+            Value::Int(i) => JsonValue::from(i),
+            Value::Int64(i) => tag(TAG_I8, JsonValue::from(i)),
+            Value::Bool(b) => JsonValue::Bool(b),
+            Value::String(ref s) => JsonValue::String(s.clone()),
+            Value::Double(d) => match Number::from_f64(d) {
+                Some(number) => JsonValue::Number(number),
+                None if tagged => {
+                    let name = if d.is_nan() {
+                        "NaN"
+                    } else if d > 0.0 {
+                        "Infinity"
+                    } else {
+                        "-Infinity"
+                    };
+                    tag(TAG_DOUBLE, JsonValue::from(name))
+                }
+                None => JsonValue::Null,
+            },
+            Value::DateTime(ref date_time) => {
+                match format_datetime(date_time, DateTimeFormat::Extended) {
+                    Some(formatted) => tag(TAG_DATETIME, JsonValue::String(formatted)),
+                    None => {
+                        return Err(JsonError {
+                            message: format!(
+                                "{:?} can not be converted to a JSON date/time",
+                                date_time
+                            ),
+                        })
+                    }
+                }
+            }
+            Value::Base64(ref data) => tag(TAG_BASE64, JsonValue::String(encode(data))),
+            Value::Struct(ref map) => {
+                let mut object = JsonMap::new();
+                for (name, value) in map {
+                    let name = if tagged && name.starts_with('$') {
+                        format!("${}", name)
+                    } else {
+                        name.clone()
+                    };
+                    object.insert(name, value.to_json(encoding)?);
+                }
+                JsonValue::Object(object)
+            }
+            Value::Array(ref array) => JsonValue::Array(
+                array
+                    .iter()
+                    .map(|value| value.to_json(encoding))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Nil => JsonValue::Null,
+            Value::BigInteger(ref s) => tag(TAG_BIG_INTEGER, JsonValue::String(s.clone())),
+            Value::BigDecimal(ref s) => tag(TAG_BIG_DECIMAL, JsonValue::String(s.clone())),
+            Value::Serializable(ref data) => tag(TAG_SERIALIZABLE, JsonValue::String(encode(data))),
+            Value::Dom(ref xml) => tag(TAG_DOM, JsonValue::String(xml.clone())),
+        })
+    }
+
+    /// Converts JSON to a `Value`, decoding tagged values if `encoding` is
+    /// [`JsonEncoding::Tagged`].
+    ///
+    /// This method is only available when the `json` feature is enabled.
+    ///
+    /// # Errors
+    ///
+    /// With the plain encoding, this never fails. With the tagged encoding, it fails if a tagged
+    /// value has the wrong type or can't be decoded, such as `{"$base64": 1}`.
+    ///
+    /// [`JsonEncoding::Tagged`]: enum.JsonEncoding.html#variant.Tagged
+    pub fn from_json(json: &JsonValue, encoding: JsonEncoding) -> Result<Value, JsonError> {
+        let tagged = encoding == JsonEncoding::Tagged;
+        Ok(match *json {
+            JsonValue::Null => Value::Nil,
+            JsonValue::Bool(b) => Value::Bool(b),
+            JsonValue::Number(ref number) => from_number(number),
+            JsonValue::String(ref s) => Value::String(s.clone()),
+            JsonValue::Array(ref array) => Value::Array(
+                array
+                    .iter()
+                    .map(|json| Value::from_json(json, encoding))
+                    .collect::<Result<_, _>>()?,
+            ),
+            JsonValue::Object(ref object) => {
+                if tagged && object.len() == 1 {
+                    let (tag, json) = object.iter().next().unwrap();
+                    if let Some(value) = from_tagged(tag, json)? {
+                        return Ok(value);
+                    }
+                }
+
+                let mut map = Map::new();
+                for (name, json) in object {
+                    let name = match name.strip_prefix('$') {
+                        Some(unescaped) if tagged && unescaped.starts_with('$') => unescaped,
+                        _ => name,
+                    };
+                    map.insert(name.to_string(), Value::from_json(json, encoding)?);
+                }
+                Value::Struct(map)
+            }
+        })
+    }
+}
+
+fn from_number(number: &Number) -> Value {
+    match number.as_i64() {
+        Some(i) => match i32::try_from(i) {
+            Ok(i) => Value::Int(i),
+            Err(_) => Value::Int64(i),
+        },
+        // Either a float or an integer above `i64::MAX`
+        None => Value::Double(number.as_f64().unwrap_or(f64::NAN)),
+    }
+}
+
+/// Decodes the tagged value `{tag: json}`, returning `None` if `tag` isn't one of the tags.
+fn from_tagged(tag: &str, json: &JsonValue) -> Result<Option<Value>, JsonError> {
+    if tag == TAG_I8 {
+        return match json.as_i64() {
+            Some(i) => Ok(Some(Value::Int64(i))),
+            None => Err(JsonError::invalid_tag(tag, "a 64-bit integer")),
+        };
+    }
+
+    let string = match json.as_str() {
+        Some(string) => string,
+        None => match tag {
+            TAG_DOUBLE | TAG_BASE64 | TAG_DATETIME | TAG_BIG_INTEGER | TAG_BIG_DECIMAL
+            | TAG_SERIALIZABLE | TAG_DOM => {
+                return Err(JsonError::invalid_tag(tag, "a string"));
+            }
+            _ => return Ok(None),
+        },
+    };
+
+    let value = match tag {
+        TAG_DOUBLE => match string {
+            "NaN" => Value::Double(f64::NAN),
+            "Infinity" => Value::Double(f64::INFINITY),
+            "-Infinity" => Value::Double(f64::NEG_INFINITY),
+            _ => {
+                return Err(JsonError::invalid_tag(
+                    tag,
+                    "`NaN`, `Infinity` or `-Infinity`",
+                ))
+            }
+        },
+        TAG_BASE64 => Value::Base64(
+            decode(string).map_err(|_| JsonError::invalid_tag(tag, "a base64 string"))?,
+        ),
+        TAG_DATETIME => Value::DateTime(
+            datetime(string).map_err(|_| JsonError::invalid_tag(tag, "an ISO 8601 date/time"))?,
+        ),
+        TAG_BIG_INTEGER => Value::BigInteger(string.to_string()),
+        TAG_BIG_DECIMAL => Value::BigDecimal(string.to_string()),
+        TAG_SERIALIZABLE => Value::Serializable(
+            decode(string).map_err(|_| JsonError::invalid_tag(tag, "a base64 string"))?,
+        ),
+        TAG_DOM => Value::Dom(string.to_string()),
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn sample() -> Value {
+        let mut map = Map::new();
+        map.insert("int".to_string(), Value::Int(-3));
+        map.insert("int64".to_string(), Value::Int64(1 << 40));
+        map.insert("small int64".to_string(), Value::Int64(7));
+        map.insert("double".to_string(), Value::Double(1.0));
+        map.insert("infinity".to_string(), Value::Double(f64::INFINITY));
+        map.insert("$ref".to_string(), Value::from("not a tag"));
+        map.insert("$$ref".to_string(), Value::Nil);
+        map.insert(
+            "date".to_string(),
+            Value::DateTime(datetime("19980717T14:08:55.050-05:00").unwrap()),
+        );
+        map.insert("data".to_string(), Value::Base64(vec![0, 1, 255]));
+        map.insert("big".to_string(), Value::BigInteger("1".repeat(30)));
+        map.insert("decimal".to_string(), Value::BigDecimal("0.1".to_string()));
+        map.insert("object".to_string(), Value::Serializable(vec![0xac, 0xed]));
+        map.insert("dom".to_string(), Value::Dom("<a/>".to_string()));
+        Value::Array(vec![
+            Value::Struct(map),
+            Value::Bool(true),
+            Value::from("x"),
+            Value::Nil,
+        ])
+    }
+
+    #[test]
+    fn tagged_round_trip_is_lossless() {
+        let value = sample();
+        let json = value.to_json(JsonEncoding::Tagged).unwrap();
+        assert_eq!(
+            Value::from_json(&json, JsonEncoding::Tagged).unwrap(),
+            value
+        );
+
+        // Also through the textual representation
+        let json: JsonValue = serde_json::from_str(&json.to_string()).unwrap();
+        assert_eq!(
+            Value::from_json(&json, JsonEncoding::Tagged).unwrap(),
+            value
+        );
+
+        let tagged = &json[0];
+        assert_eq!(tagged["int64"]["$i8"], 1i64 << 40);
+        assert_eq!(tagged["infinity"]["$double"], "Infinity");
+        assert_eq!(tagged["$$ref"], "not a tag");
+        assert_eq!(tagged["$$$ref"], JsonValue::Null);
+        assert_eq!(
+            tagged["date"]["$dateTime.iso8601"],
+            "1998-07-17T14:08:55.050-05:00"
+        );
+        assert_eq!(tagged["data"]["$base64"], "AAH/");
+    }
+
+    #[test]
+    fn converts_to_plain_json() {
+        let json = sample().to_json(JsonEncoding::Plain).unwrap();
+        let plain = &json[0];
+        assert_eq!(plain["int"], -3);
+        assert_eq!(plain["int64"], 1i64 << 40);
+        assert_eq!(plain["infinity"], JsonValue::Null);
+        assert_eq!(plain["$ref"], "not a tag");
+        assert_eq!(plain["date"], "1998-07-17T14:08:55.050-05:00");
+        assert_eq!(plain["data"], "AAH/");
+        assert_eq!(plain["dom"], "<a/>");
+        assert_eq!(json[1], true);
+        assert_eq!(json[3], JsonValue::Null);
+    }
+
+    #[test]
+    fn converts_from_plain_json() {
+        let json: JsonValue = serde_json::from_str(
+            r#"{"a": [1, 5000000000, 1.5, 18446744073709551615], "$i8": 1, "b": {"$base64": "AA=="}}"#,
+        )
+        .unwrap();
+        let value = Value::from_json(&json, JsonEncoding::Plain).unwrap();
+        assert_eq!(
+            value["a"],
+            Value::Array(vec![
+                Value::Int(1),
+                Value::Int64(5_000_000_000),
+                Value::Double(1.5),
+                Value::Double(u64::MAX as f64),
+            ])
+        );
+        assert_eq!(value["$i8"], Value::Int(1));
+        assert_eq!(value["b"]["$base64"], Value::from("AA=="));
+    }
+
+    #[test]
+    fn rejects_malformed_tags() {
+        for json in &[
+            r#"{"$i8": "1"}"#,
+            r#"{"$base64": 1}"#,
+            r#"{"$base64": "not base64!"}"#,
+            r#"{"$dateTime.iso8601": "yesterday"}"#,
+            r#"{"$double": "1.5"}"#,
+        ] {
+            let json: JsonValue = serde_json::from_str(json).unwrap();
+            assert!(Value::from_json(&json, JsonEncoding::Tagged).is_err());
+            assert!(Value::from_json(&json, JsonEncoding::Plain).is_ok());
+        }
+
+        // Unknown tags and objects with more members are structs
+        let json: JsonValue = serde_json::from_str(r#"{"$other": 1}"#).unwrap();
+        assert_eq!(
+            Value::from_json(&json, JsonEncoding::Tagged).unwrap()["$other"],
+            Value::Int(1)
+        );
+    }
+}
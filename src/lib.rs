@@ -4,26 +4,87 @@
 //!
 //! [XML-RPC specification]: http://xmlrpc.scripting.com/spec.html
 
-#![doc(html_root_url = "https://docs.rs/xmlrpc/0.15.1")]
+#![doc(html_root_url = "https://docs.rs/xmlrpc/0.16.0")]
 #![warn(missing_debug_implementations)]
 #![warn(rust_2018_idioms)]
 #![warn(missing_docs)]
 
 extern crate base64;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+extern crate encoding_rs;
+#[cfg(feature = "indexmap")]
+extern crate indexmap;
 extern crate iso8601;
+#[cfg(feature = "quick-xml")]
+extern crate quick_xml;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "time")]
+extern crate time;
 extern crate xml;
 
+#[cfg(any(feature = "chrono", feature = "time"))]
+mod datetime;
+#[cfg(feature = "serde")]
+mod de;
+mod diff;
+mod display;
+mod encoding;
 mod error;
+#[cfg(feature = "json")]
+mod json;
+pub mod map;
 mod parser;
+mod path;
+#[cfg(feature = "quick-xml")]
+mod quick_source;
 mod request;
+mod schema;
+#[cfg(feature = "serde")]
+mod ser;
+mod source;
+mod stream;
 mod transport;
 mod utils;
 mod value;
+mod value_ref;
+mod visitor;
+mod writer;
 
-pub use error::{Error, Fault};
+#[cfg(feature = "time")]
+pub use datetime::DateTimeError;
+#[cfg(feature = "serde")]
+pub use de::deserialize_response;
+#[cfg(all(feature = "serde", feature = "quick-xml"))]
+pub use de::deserialize_response_str;
+pub use diff::{DiffOptions, Difference, DifferenceKind};
+pub use error::{Error, Fault, FaultKind, ParseError, RawResponse, ResponseError};
+#[cfg(feature = "json")]
+pub use json::{JsonEncoding, JsonError};
+pub use map::Map;
+#[cfg(feature = "quick-xml")]
+pub use parser::parse_response_ref;
+pub use parser::{parse_response, visit_response, ParserBackend, ParserOptions, Response};
+pub use path::{PathError, PathErrorKind};
 pub use request::Request;
+pub use schema::{
+    ScalarType, Schema, Signature, StructSchema, ValidationError, Violation, ViolationKind,
+};
+#[cfg(feature = "serde")]
+pub use ser::{serialize_value, serialize_value_with, SerializeError};
+pub use stream::{ResponseItem, ResponseStream};
 pub use transport::Transport;
 pub use value::{Index, Value};
+pub use value_ref::ValueRef;
+pub use visitor::ValueVisitor;
+pub use writer::{DateTimeFormat, NonFiniteDoubles, WriterLayout, WriterOptions};
 
 #[cfg(feature = "http")]
 pub use transport::http;
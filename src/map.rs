@@ -0,0 +1,320 @@
+//! The map type used for the members of XML-RPC structs.
+
+use Value;
+
+#[cfg(feature = "indexmap")]
+use indexmap::map::{self as imp, IndexMap as MapImpl};
+
+use std::borrow::Borrow;
+#[cfg(not(feature = "indexmap"))]
+use std::collections::btree_map::{self as imp, BTreeMap as MapImpl};
+use std::fmt;
+use std::hash::Hash;
+use std::iter::{FromIterator, FusedIterator};
+use std::ops;
+
+/// The members of a [`Value::Struct`], mapping member names to values.
+///
+/// By default, members are kept sorted by name. When the `indexmap` feature is enabled, they are
+/// kept in the order in which they appear in a response or were inserted instead, so that parsing
+/// and writing a value again yields the members in their original order. The API is the same in
+/// both cases, which is why keys must implement both `Ord` and `Hash`.
+///
+/// [`Value::Struct`]: ../enum.Value.html#variant.Struct
+#[derive(Clone)]
+pub struct Map<K = String, V = Value> {
+    map: MapImpl<K, V>,
+}
+
+impl<K, V> Map<K, V> {
+    /// Creates an empty `Map`.
+    pub fn new() -> Self {
+        Map {
+            map: MapImpl::new(),
+        }
+    }
+
+    /// Returns the number of members in the map.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no members.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Removes all members from the map.
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+
+    /// Returns an iterator over the members of the map.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.map.iter())
+    }
+
+    /// Returns an iterator over the members of the map, with mutable references to the values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut(self.map.iter_mut())
+    }
+
+    /// Returns an iterator over the member names of the map.
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.map.keys())
+    }
+
+    /// Returns an iterator over the values of the map.
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values(self.map.values())
+    }
+
+    /// Returns an iterator over mutable references to the values of the map.
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut(self.map.values_mut())
+    }
+}
+
+impl<K: Ord + Hash, V> Map<K, V> {
+    /// Returns a reference to the value of the member named `key`, if there is one.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        self.map.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the member named `key`, if there is one.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        self.map.get_mut(key)
+    }
+
+    /// Returns `true` if the map contains a member named `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Inserts a member into the map, returning the previous value of a member with the same name.
+    ///
+    /// A member that replaces an existing one keeps its position.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.map.insert(key, value)
+    }
+
+    /// Removes the member named `key` from the map, returning its value if there was one.
+    ///
+    /// The remaining members keep their order.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + Hash + ?Sized,
+    {
+        #[cfg(feature = "indexmap")]
+        let value = self.map.shift_remove(key);
+        #[cfg(not(feature = "indexmap"))]
+        let value = self.map.remove(key);
+        value
+    }
+
+    /// Keeps only the members for which `keep` returns `true`.
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.map.retain(keep)
+    }
+}
+
+impl<K, V> Default for Map<K, V> {
+    fn default() -> Self {
+        Map::new()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Debug for Map<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.map.fmt(f)
+    }
+}
+
+/// Maps are equal if they contain the same members, regardless of their order.
+impl<K: Ord + Hash, V: PartialEq> PartialEq for Map<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K: Ord + Hash, V: Eq> Eq for Map<K, V> {}
+
+impl<K, V, Q> ops::Index<&Q> for Map<K, V>
+where
+    K: Ord + Hash + Borrow<Q>,
+    Q: Ord + Hash + ?Sized,
+{
+    type Output = V;
+
+    /// Returns the value of the member named `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map doesn't contain a member named `key`.
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no member found for key")
+    }
+}
+
+impl<K: Ord + Hash, V> FromIterator<(K, V)> for Map<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Map {
+            map: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<K: Ord + Hash, V> Extend<(K, V)> for Map<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        self.map.extend(iter)
+    }
+}
+
+impl<K, V> IntoIterator for Map<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> IntoIter<K, V> {
+        IntoIter(self.map.into_iter())
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a Map<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut Map<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+/// Defines an iterator wrapping the corresponding iterator of the backing map.
+macro_rules! map_iter {
+    ($(#[$attr:meta])* $name:ident<$($lt:lifetime),*>, $item:ty) => {
+        $(#[$attr])*
+        #[derive(Debug)]
+        pub struct $name<$($lt,)* K, V>(imp::$name<$($lt,)* K, V>);
+
+        impl<$($lt,)* K, V> Iterator for $name<$($lt,)* K, V> {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.0.next()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.size_hint()
+            }
+        }
+
+        impl<$($lt,)* K, V> DoubleEndedIterator for $name<$($lt,)* K, V> {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.0.next_back()
+            }
+        }
+
+        impl<$($lt,)* K, V> ExactSizeIterator for $name<$($lt,)* K, V> {
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+        }
+
+        impl<$($lt,)* K, V> FusedIterator for $name<$($lt,)* K, V> {}
+    };
+}
+
+map_iter!(
+    /// An iterator over the members of a [`Map`](struct.Map.html).
+    Iter<'a>, (&'a K, &'a V)
+);
+map_iter!(
+    /// A mutable iterator over the members of a [`Map`](struct.Map.html).
+    IterMut<'a>, (&'a K, &'a mut V)
+);
+map_iter!(
+    /// An owning iterator over the members of a [`Map`](struct.Map.html).
+    IntoIter<>, (K, V)
+);
+map_iter!(
+    /// An iterator over the member names of a [`Map`](struct.Map.html).
+    Keys<'a>, &'a K
+);
+map_iter!(
+    /// An iterator over the values of a [`Map`](struct.Map.html).
+    Values<'a>, &'a V
+);
+map_iter!(
+    /// A mutable iterator over the values of a [`Map`](struct.Map.html).
+    ValuesMut<'a>, &'a mut V
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(map: &Map) -> Vec<&str> {
+        map.keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn removing_keeps_member_order() {
+        let mut map: Map = vec![("b", 1), ("c", 2), ("a", 3)]
+            .into_iter()
+            .map(|(name, i)| (name.to_string(), Value::Int(i)))
+            .collect();
+        #[cfg(feature = "indexmap")]
+        assert_eq!(names(&map), ["b", "c", "a"]);
+        #[cfg(not(feature = "indexmap"))]
+        assert_eq!(names(&map), ["a", "b", "c"]);
+
+        assert_eq!(map.remove("c"), Some(Value::Int(2)));
+        assert_eq!(map.remove("c"), None);
+        #[cfg(feature = "indexmap")]
+        assert_eq!(names(&map), ["b", "a"]);
+        #[cfg(not(feature = "indexmap"))]
+        assert_eq!(names(&map), ["a", "b"]);
+
+        assert_eq!(map.insert("b".to_string(), Value::Nil), Some(Value::Int(1)));
+        assert_eq!(map["b"], Value::Nil);
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn equality_ignores_member_order() {
+        let mut left = Map::new();
+        left.insert("a".to_string(), Value::Int(1));
+        left.insert("b".to_string(), Value::Int(2));
+        let mut right = Map::new();
+        right.insert("b".to_string(), Value::Int(2));
+        right.insert("a".to_string(), Value::Int(1));
+        assert_eq!(left, right);
+
+        right.insert("c".to_string(), Value::Nil);
+        assert_ne!(left, right);
+    }
+}
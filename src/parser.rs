@@ -1,15 +1,25 @@
 //! XML-RPC response parser.
 
 use error::ParseError;
+#[cfg(feature = "quick-xml")]
+use quick_source::{read_document, QuickXmlSource};
+use source::{Event, EventSource, Name, XmlRsSource};
+use utils::APACHE_EXTENSIONS_NS;
+#[cfg(feature = "quick-xml")]
+use value_ref::ValueRef;
+use visitor::{ValueBuilder, ValueVisitor};
+#[cfg(feature = "quick-xml")]
+use Map;
 use {Fault, Value};
 
 use base64;
-use iso8601::datetime;
-use std::collections::BTreeMap;
-use std::io::{self, ErrorKind, Read};
-use xml::common::Position;
-use xml::reader::{EventReader, XmlEvent};
-use xml::ParserConfig;
+use iso8601::{datetime, DateTime};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::io::{self, Read};
+use std::mem;
+#[cfg(feature = "serde")]
+use xml::common::TextPosition;
 
 /// A response from the server.
 ///
@@ -18,26 +28,359 @@ pub type Response = Result<Value, Fault>;
 
 type ParseResult<T> = Result<T, ParseError>;
 
-pub struct Parser<'a, R: Read> {
-    reader: EventReader<&'a mut R>,
-    /// Current "token". The parser makes decisions based on this token, then pulls the next one
-    /// from `reader`.
-    cur: XmlEvent,
+/// Options controlling how server responses are parsed.
+///
+/// By default, responses are parsed according to the XML-RPC specification, except that duplicate
+/// struct members are accepted (the last one wins). Use [`strict`] or [`lenient`] to select a
+/// dialect, or the builder methods to pick individual behaviors, and pass the options to
+/// [`Request::parser_options`].
+///
+/// [`strict`]: #method.strict
+/// [`lenient`]: #method.lenient
+/// [`Request::parser_options`]: struct.Request.html#method.parser_options
+///
+/// # Resource limits
+///
+/// Since responses might come from untrusted servers, the parser can enforce limits on the
+/// resources a response may consume. Only the nesting depth is limited by default (to
+/// [`DEFAULT_MAX_DEPTH`]), which prevents deeply nested responses from overflowing the stack.
+///
+/// Only [`max_document_size`] bounds the memory used while parsing. The other limits are checked
+/// once the XML parser has read the respective element or text, which is buffered completely, so
+/// they restrict the shape of accepted responses rather than the memory needed to parse them.
+///
+/// [`DEFAULT_MAX_DEPTH`]: #associatedconstant.DEFAULT_MAX_DEPTH
+/// [`max_document_size`]: #method.max_document_size
+#[derive(Clone, Debug)]
+pub struct ParserOptions {
+    lenient_faults: bool,
+    allow_attributes: bool,
+    textual_booleans: bool,
+    reject_duplicate_members: bool,
+    trim_values: bool,
+    strict_doubles: bool,
+    tolerant_datetimes: bool,
+    ignore_namespaces: bool,
+    empty_values_as_nil: bool,
+    max_depth: Option<usize>,
+    pub(crate) max_document_size: Option<u64>,
+    max_string_length: Option<usize>,
+    max_base64_size: Option<usize>,
+    max_elements: Option<usize>,
+    apache_extensions: bool,
+    pub(crate) backend: ParserBackend,
 }
 
-impl<'a, R: Read> Parser<'a, R> {
-    pub fn new(reader: &'a mut R) -> ParseResult<Self> {
-        let reader = EventReader::new_with_config(
-            reader,
-            ParserConfig {
-                cdata_to_characters: true,
-                ..Default::default()
-            },
-        );
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            lenient_faults: false,
+            allow_attributes: false,
+            textual_booleans: false,
+            reject_duplicate_members: false,
+            trim_values: false,
+            strict_doubles: false,
+            tolerant_datetimes: false,
+            ignore_namespaces: false,
+            empty_values_as_nil: false,
+            max_depth: Some(Self::DEFAULT_MAX_DEPTH),
+            max_document_size: None,
+            max_string_length: None,
+            max_base64_size: None,
+            max_elements: None,
+            apache_extensions: false,
+            backend: ParserBackend::default(),
+        }
+    }
+}
+
+impl ParserOptions {
+    /// The default limit for the nesting depth of values.
+    pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+    /// Creates the default `ParserOptions`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates `ParserOptions` that only accept responses strictly conforming to the
+    /// specification.
+    ///
+    /// In addition to the defaults, this rejects structs with duplicate member names and doubles
+    /// that aren't plain decimal literals.
+    pub fn strict() -> Self {
+        Self::default()
+            .reject_duplicate_members(true)
+            .strict_doubles(true)
+    }
+
+    /// Creates `ParserOptions` that accept common deviations from the specification.
+    ///
+    /// This enables lenient fault parsing, tolerates attributes and namespaces, accepts `true` and
+    /// `false` as booleans and common variants of date/times, and ignores whitespace around scalar
+    /// values. Duplicate struct members are accepted, with the last one winning.
+    pub fn lenient() -> Self {
+        Self::default()
+            .lenient_faults(true)
+            .allow_attributes(true)
+            .ignore_namespaces(true)
+            .textual_booleans(true)
+            .trim_values(true)
+            .tolerant_datetimes(true)
+    }
+
+    /// Sets whether `<fault>` responses that don't conform to the specification are accepted.
+    ///
+    /// When enabled, faults are parsed using [`Fault::from_value_lenient`], which accepts extra
+    /// members, `i8` or string fault codes, and missing fields. Otherwise, such a fault makes
+    /// the whole call fail with a parse error.
+    ///
+    /// Disabled by default.
+    ///
+    /// [`Fault::from_value_lenient`]: struct.Fault.html#method.from_value_lenient
+    pub fn lenient_faults(mut self, lenient: bool) -> Self {
+        self.lenient_faults = lenient;
+        self
+    }
+
+    /// Sets whether attributes on XML-RPC tags are ignored instead of causing an error.
+    ///
+    /// Disabled by default.
+    pub fn allow_attributes(mut self, allow: bool) -> Self {
+        self.allow_attributes = allow;
+        self
+    }
+
+    /// Sets whether XML-RPC tags in a namespace (such as `<x:value>`, or `<value>` in a default
+    /// namespace declared with `xmlns`) are accepted as if they had no namespace.
+    ///
+    /// Otherwise, namespaced tags are rejected, except for the Apache extension types if
+    /// [`apache_extensions`] is enabled.
+    ///
+    /// Disabled by default.
+    ///
+    /// [`apache_extensions`]: #method.apache_extensions
+    pub fn ignore_namespaces(mut self, ignore: bool) -> Self {
+        self.ignore_namespaces = ignore;
+        self
+    }
+
+    /// Sets whether a `<value>` without content (`<value></value>` or `<value/>`) is parsed as
+    /// `Value::Nil` instead of an empty string.
+    ///
+    /// The specification treats values without type tag as strings, but some servers send empty
+    /// values for missing or null data.
+    ///
+    /// Disabled by default.
+    pub fn empty_values_as_nil(mut self, nil: bool) -> Self {
+        self.empty_values_as_nil = nil;
+        self
+    }
+
+    /// Sets whether `<boolean>` values may be written as `true` and `false` (in any case), in
+    /// addition to `1` and `0`.
+    ///
+    /// Disabled by default.
+    pub fn textual_booleans(mut self, accept: bool) -> Self {
+        self.textual_booleans = accept;
+        self
+    }
+
+    /// Sets whether a struct containing the same member name more than once is rejected with
+    /// [`ParseError::DuplicateMember`]. Otherwise, the last member with a given name wins.
+    ///
+    /// Disabled by default.
+    ///
+    /// [`ParseError::DuplicateMember`]: enum.ParseError.html#variant.DuplicateMember
+    pub fn reject_duplicate_members(mut self, reject: bool) -> Self {
+        self.reject_duplicate_members = reject;
+        self
+    }
 
+    /// Sets whether leading and trailing whitespace is ignored in integer, double, boolean and
+    /// date/time values.
+    ///
+    /// Disabled by default.
+    pub fn trim_values(mut self, trim: bool) -> Self {
+        self.trim_values = trim;
+        self
+    }
+
+    /// Sets whether `<double>` values must be plain decimal literals, as required by the
+    /// specification: an optional sign, followed by digits with an optional decimal point.
+    ///
+    /// Otherwise, any number accepted by Rust's `f64` parser is allowed, including exponent
+    /// notation (`1e10`) as well as `NaN` and infinities (`inf` or `Infinity`), which are sent by
+    /// some servers.
+    ///
+    /// Disabled by default.
+    pub fn strict_doubles(mut self, strict: bool) -> Self {
+        self.strict_doubles = strict;
+        self
+    }
+
+    /// Sets whether date/time values may use common variants of the ISO 8601 format that servers
+    /// send, in addition to the formats accepted by default (such as `19980717T14:08:55` and
+    /// `1998-07-17T14:08:55Z`).
+    ///
+    /// When enabled, the date and time may be separated by a space instead of `T`, `T` and `Z`
+    /// may be lowercase, whitespace before the time zone is ignored, and a date without time is
+    /// accepted as midnight.
+    ///
+    /// Disabled by default.
+    pub fn tolerant_datetimes(mut self, tolerant: bool) -> Self {
+        self.tolerant_datetimes = tolerant;
+        self
+    }
+
+    /// Sets the maximum nesting depth of values, or `None` for no limit.
+    ///
+    /// A scalar value has a depth of 1, an array of scalars has a depth of 2, and so on. Exceeding
+    /// the limit results in [`ParseError::DepthLimitExceeded`].
+    ///
+    /// Defaults to [`DEFAULT_MAX_DEPTH`].
+    ///
+    /// [`ParseError::DepthLimitExceeded`]: enum.ParseError.html#variant.DepthLimitExceeded
+    /// [`DEFAULT_MAX_DEPTH`]: #associatedconstant.DEFAULT_MAX_DEPTH
+    pub fn max_depth(mut self, limit: Option<usize>) -> Self {
+        self.max_depth = limit;
+        self
+    }
+
+    /// Sets the maximum size of the response document in bytes, or `None` for no limit.
+    ///
+    /// Exceeding the limit results in [`ParseError::DocumentTooLarge`].
+    ///
+    /// No limit is set by default.
+    ///
+    /// [`ParseError::DocumentTooLarge`]: enum.ParseError.html#variant.DocumentTooLarge
+    pub fn max_document_size(mut self, limit: Option<u64>) -> Self {
+        self.max_document_size = limit;
+        self
+    }
+
+    /// Sets the maximum length in bytes of strings and struct member names, or `None` for no
+    /// limit.
+    ///
+    /// Exceeding the limit results in [`ParseError::StringTooLong`]. The limit is checked after a
+    /// string has been read completely, so it does not prevent long strings from being buffered.
+    /// Use [`max_document_size`] to bound the memory used by a response.
+    ///
+    /// No limit is set by default.
+    ///
+    /// [`ParseError::StringTooLong`]: enum.ParseError.html#variant.StringTooLong
+    /// [`max_document_size`]: #method.max_document_size
+    pub fn max_string_length(mut self, limit: Option<usize>) -> Self {
+        self.max_string_length = limit;
+        self
+    }
+
+    /// Sets the maximum size in bytes of decoded `<base64>` data, or `None` for no limit.
+    ///
+    /// Exceeding the limit results in [`ParseError::Base64TooLarge`]. Like [`max_string_length`],
+    /// this is checked after the encoded data has been read completely, so it does not bound the
+    /// memory used by a response.
+    ///
+    /// No limit is set by default.
+    ///
+    /// [`ParseError::Base64TooLarge`]: enum.ParseError.html#variant.Base64TooLarge
+    /// [`max_string_length`]: #method.max_string_length
+    pub fn max_base64_size(mut self, limit: Option<usize>) -> Self {
+        self.max_base64_size = limit;
+        self
+    }
+
+    /// Sets the maximum number of XML elements in the response, or `None` for no limit.
+    ///
+    /// Exceeding the limit results in [`ParseError::TooManyElements`].
+    ///
+    /// No limit is set by default.
+    ///
+    /// [`ParseError::TooManyElements`]: enum.ParseError.html#variant.TooManyElements
+    pub fn max_elements(mut self, limit: Option<usize>) -> Self {
+        self.max_elements = limit;
+        self
+    }
+
+    /// Sets whether the [Apache XML-RPC extension types] are accepted.
+    ///
+    /// These are the types in the `http://ws.apache.org/xmlrpc/namespaces/extensions` namespace
+    /// (usually bound to the `ex` prefix), which Apache XML-RPC servers send when
+    /// `enabledForExtensions` is set. They are mapped onto `Value`s as follows:
+    ///
+    /// * `<ex:nil/>` becomes `Value::Nil`.
+    /// * `<ex:i1>` and `<ex:i2>` become `Value::Int`, `<ex:i8>` becomes `Value::Int64`.
+    /// * `<ex:float>` becomes `Value::Double`.
+    /// * `<ex:dateTime>` becomes `Value::DateTime`.
+    /// * `<ex:biginteger>`, `<ex:bigdecimal>`, `<ex:serializable>` and `<ex:dom>` become
+    ///   `Value::BigInteger`, `Value::BigDecimal`, `Value::Serializable` and `Value::Dom`,
+    ///   respectively.
+    ///
+    /// Disabled by default, in which case namespaced tags are rejected unless
+    /// [`ignore_namespaces`] is enabled.
+    ///
+    /// [`ignore_namespaces`]: #method.ignore_namespaces
+    ///
+    /// [Apache XML-RPC extension types]: https://ws.apache.org/xmlrpc/types.html
+    pub fn apache_extensions(mut self, accept: bool) -> Self {
+        self.apache_extensions = accept;
+        self
+    }
+
+    /// Sets the XML parser used to parse responses.
+    ///
+    /// Defaults to [`ParserBackend::XmlRs`].
+    ///
+    /// [`ParserBackend::XmlRs`]: enum.ParserBackend.html#variant.XmlRs
+    pub fn backend(mut self, backend: ParserBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+}
+
+/// The XML parser used to parse responses.
+///
+/// Both backends accept the same documents and produce the same `Value`s and `ParseError`s,
+/// except for the messages and positions of malformed XML errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParserBackend {
+    /// The [xml-rs] parser, which reads the response incrementally.
+    ///
+    /// [xml-rs]: https://crates.io/crates/xml-rs
+    #[default]
+    XmlRs,
+    /// The [quick-xml] parser, which reads the whole response into memory before parsing it.
+    ///
+    /// This is considerably faster, especially for large responses, but requires enabling the
+    /// `quick-xml` feature.
+    ///
+    /// [quick-xml]: https://crates.io/crates/quick-xml
+    #[cfg(feature = "quick-xml")]
+    QuickXml,
+}
+
+pub struct Parser<'a, S> {
+    source: S,
+    /// Current "token". The parser makes decisions based on this token, then pulls the next one
+    /// from `source`.
+    cur: Event<'a>,
+    options: ParserOptions,
+    /// Nesting depth of the value currently being parsed.
+    depth: usize,
+    /// Number of elements encountered so far.
+    elements: usize,
+}
+
+impl<'a, S: EventSource<'a>> Parser<'a, S> {
+    /// Creates a new parser pulling events from `source`.
+    pub fn new(source: S, options: &ParserOptions) -> ParseResult<Self> {
         let mut parser = Parser {
-            cur: XmlEvent::EndDocument, // dummy value
-            reader,
+            source,
+            cur: Event::EndDocument, // dummy value
+            options: options.clone(),
+            depth: 0,
+            elements: 0,
         };
         parser.next()?;
         Ok(parser)
@@ -45,43 +388,77 @@ impl<'a, R: Read> Parser<'a, R> {
 
     /// Disposes `self.cur` and pulls the next event from the XML parser to replace it.
     fn next(&mut self) -> ParseResult<()> {
-        loop {
-            let event = self.reader.next()?;
-            match event {
-                XmlEvent::StartDocument { .. }
-                | XmlEvent::Comment(_)
-                | XmlEvent::Whitespace(_)
-                | XmlEvent::ProcessingInstruction { .. } => continue, // skip these
-                XmlEvent::StartElement {
-                    ref attributes,
-                    ref name,
-                    ..
-                } => {
-                    if name.namespace.is_some() || name.prefix.is_some() {
-                        return self.expected("tag without namespace or prefix");
-                    }
-                    if !attributes.is_empty() {
-                        return self.expected(format!("tag <{}> without attributes", name));
-                    }
+        let event = self.source.next_event()?;
+        match event {
+            Event::StartElement {
+                ref name,
+                has_attributes,
+            } => {
+                if !self.is_allowed_name(name) {
+                    return self.expected("tag without namespace or prefix");
                 }
-                XmlEvent::EndElement { ref name } => {
-                    if name.namespace.is_some() || name.prefix.is_some() {
-                        return self.expected("tag without namespace or prefix");
+                if has_attributes && !self.options.allow_attributes {
+                    return self.expected(format!("tag <{}> without attributes", name));
+                }
+
+                self.elements += 1;
+                if let Some(limit) = self.options.max_elements {
+                    if self.elements > limit {
+                        return Err(ParseError::TooManyElements {
+                            limit,
+                            position: self.source.position(),
+                        });
                     }
                 }
-                XmlEvent::EndDocument | XmlEvent::CData(_) | XmlEvent::Characters(_) => {}
             }
+            Event::EndElement { ref name } => {
+                if !self.is_allowed_name(name) {
+                    return self.expected("tag without namespace or prefix");
+                }
+            }
+            Event::EndDocument | Event::Characters(_) => {}
+        }
 
-            self.cur = event;
-            return Ok(());
+        self.cur = event;
+        Ok(())
+    }
+
+    /// Returns whether a tag with the given name may appear in the document.
+    fn is_allowed_name(&self, name: &Name<'_>) -> bool {
+        self.is_standard_name(name) || self.is_extension_name(name)
+    }
+
+    /// Returns whether `name` is the name of a tag defined by the specification, which has no
+    /// namespace unless namespaces are ignored.
+    fn is_standard_name(&self, name: &Name<'_>) -> bool {
+        match name.namespace {
+            None => name.prefix.is_none(),
+            Some(_) => self.options.ignore_namespaces && !self.is_extension_name(name),
+        }
+    }
+
+    /// Returns whether `name` is in the namespace of the Apache extension types, and these are
+    /// enabled.
+    fn is_extension_name(&self, name: &Name<'_>) -> bool {
+        self.options.apache_extensions && name.namespace.as_deref() == Some(APACHE_EXTENSIONS_NS)
+    }
+
+    /// If the current token is an opening tag whose local name is one of `tags`, returns that
+    /// name.
+    fn current_tag(&self, tags: &[&'static str]) -> Option<&'static str> {
+        match self.cur {
+            Event::StartElement { ref name, .. } => {
+                tags.iter().find(|tag| **tag == name.local_name).cloned()
+            }
+            _ => None,
         }
     }
 
     /// Expects that the current token is an opening tag like `<tag>` without attributes (and a
     /// local name without namespaces). If not, returns an error.
-    fn expect_open(&mut self, tag: &str) -> ParseResult<()> {
+    pub fn expect_open(&mut self, tag: &str) -> ParseResult<()> {
         match self.cur {
-            XmlEvent::StartElement { ref name, .. } if name.local_name == tag => {}
+            Event::StartElement { ref name, .. } if name.local_name == tag => {}
             _ => return self.expected(format!("<{}>", tag)),
         }
         self.next()?;
@@ -90,24 +467,39 @@ impl<'a, R: Read> Parser<'a, R> {
 
     /// Expects that the current token is a closing tag like `</tag>` with a local name without
     /// namespaces. If not, returns an error.
-    fn expect_close(&mut self, tag: &str) -> ParseResult<()> {
+    pub fn expect_close(&mut self, tag: &str) -> ParseResult<()> {
         match self.cur {
-            XmlEvent::EndElement { ref name } if name.local_name == tag => {}
+            Event::EndElement { ref name } if name.local_name == tag => {}
             _ => return self.expected(format!("</{}>", tag)),
         }
         self.next()?;
         Ok(())
     }
 
+    /// If the current token is a closing tag like `</tag>`, consumes it and returns `true`.
+    pub fn try_close(&mut self, tag: &str) -> ParseResult<bool> {
+        match self.cur {
+            Event::EndElement { ref name } if name.local_name == tag => {}
+            _ => return Ok(false),
+        }
+        self.next()?;
+        Ok(true)
+    }
+
     /// Expects that the current token is a characters sequence. Parses and returns a value.
     fn expect_value<T, E>(
         &mut self,
         for_type: &'static str,
-        parse: impl Fn(&str) -> Result<T, E>,
+        parse: impl FnOnce(&str) -> Result<T, E>,
     ) -> ParseResult<T> {
         let value = match self.cur {
-            XmlEvent::Characters(ref string) => {
-                parse(string).map_err(|_| self.invalid_value(for_type, string.to_owned()))?
+            Event::Characters(ref string) => {
+                let data = if self.options.trim_values {
+                    string.trim()
+                } else {
+                    string
+                };
+                parse(data).map_err(|_| self.invalid_value(for_type, string.to_string()))?
             }
             _ => return self.expected("characters"),
         };
@@ -115,24 +507,99 @@ impl<'a, R: Read> Parser<'a, R> {
         Ok(value)
     }
 
+    /// Expects that the current token is a characters sequence not exceeding the string length
+    /// limit, and returns it.
+    pub fn expect_string(&mut self) -> ParseResult<Cow<'a, str>> {
+        match self.cur {
+            Event::Characters(ref string) => self.check_string(string)?,
+            _ => return self.expected("characters"),
+        }
+        self.take_characters()
+    }
+
+    /// Takes the characters sequence that is the current token (without copying it) and pulls the
+    /// next token.
+    fn take_characters(&mut self) -> ParseResult<Cow<'a, str>> {
+        match mem::replace(&mut self.cur, Event::EndDocument) {
+            Event::Characters(string) => {
+                self.next()?;
+                Ok(string)
+            }
+            _ => unreachable!("current token is not a characters sequence"),
+        }
+    }
+
     /// Builds and returns an `Err(UnexpectedXml)`.
-    fn expected<T, E: ToString>(&self, expected: E) -> ParseResult<T> {
+    fn expected<T, E: From<ParseError>>(&self, expected: impl ToString) -> Result<T, E> {
         let expected = expected.to_string();
-        let position = self.reader.position();
+        let position = self.source.position();
 
-        Err(ParseError::UnexpectedXml {
+        Err(E::from(ParseError::UnexpectedXml {
             expected,
             position,
             found: match self.cur {
-                XmlEvent::StartElement { ref name, .. } => Some(format!("<{}>", name)),
-                XmlEvent::EndElement { ref name, .. } => Some(format!("</{}>", name)),
-                XmlEvent::EndDocument => Some("end of data".to_string()),
-                XmlEvent::Characters(ref data) | XmlEvent::CData(ref data) => {
-                    Some(format!("\"{}\"", data))
-                }
-                _ => None,
+                Event::StartElement { ref name, .. } => Some(format!("<{}>", name)),
+                Event::EndElement { ref name, .. } => Some(format!("</{}>", name)),
+                Event::EndDocument => Some("end of data".to_string()),
+                Event::Characters(ref data) => Some(format!("\"{}\"", data)),
             },
-        })
+        }))
+    }
+
+    /// Checks that a string or member name doesn't exceed the configured length limit.
+    fn check_string(&self, string: &str) -> ParseResult<()> {
+        match self.options.max_string_length {
+            Some(limit) if string.len() > limit => Err(ParseError::StringTooLong {
+                limit,
+                position: self.source.position(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that `len` bytes of base64 data don't exceed the configured size limit.
+    fn check_base64(&self, len: usize) -> ParseResult<()> {
+        match self.options.max_base64_size {
+            Some(limit) if len > limit => Err(ParseError::Base64TooLarge {
+                limit,
+                position: self.source.position(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Decodes the base64 data that is the current token, checking it against the size limit.
+    ///
+    /// The decoded data is passed to `chunk` in pieces of limited size.
+    fn expect_base64<E: From<ParseError>>(
+        &mut self,
+        for_type: &'static str,
+        mut chunk: impl FnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let stripped: Vec<_> = match self.cur {
+            Event::Characters(ref string) => string
+                .bytes()
+                .filter(|b| !b" \n\t\r\x0b\x0c".contains(b))
+                .collect(),
+            _ => return self.expected("characters"),
+        };
+        // Reject oversized data before decoding it (ignoring padding)
+        self.check_base64((stripped.len() / 4 * 3).saturating_sub(2))?;
+
+        let mut buf = Vec::new();
+        let mut len = 0;
+        for encoded in stripped.chunks(BASE64_CHUNK_LEN) {
+            buf.clear();
+            if base64::decode_config_buf(encoded, base64::STANDARD, &mut buf).is_err() {
+                let string = self.take_characters()?;
+                return Err(self.invalid_value(for_type, string.into_owned()).into());
+            }
+            len += buf.len();
+            self.check_base64(len)?;
+            chunk(&buf)?;
+        }
+        self.next()?;
+        Ok(())
     }
 
     fn invalid_value(&self, for_type: &'static str, value: String) -> ParseError {
@@ -140,213 +607,671 @@ impl<'a, R: Read> Parser<'a, R> {
         ParseError::InvalidValue {
             for_type,
             found: value,
-            position: self.reader.position(),
+            position: self.source.position(),
         }
     }
 
     fn parse_response(&mut self) -> ParseResult<Response> {
-        let response: Response;
+        let mut builder = ValueBuilder::new();
+        Ok(self
+            .visit_response(&mut builder)?
+            .map(|()| builder.finish()))
+    }
+
+    fn visit_response<V: ValueVisitor>(
+        &mut self,
+        visitor: &mut V,
+    ) -> Result<Result<(), Fault>, V::Error>
+    where
+        V::Error: From<ParseError>,
+    {
+        if let Some(fault) = self.parse_response_start()? {
+            return Ok(Err(fault));
+        }
+
+        self.visit_value(visitor)?;
 
+        // </param>
+        self.expect_close("param")?;
+
+        Ok(Ok(()))
+    }
+
+    /// Parses the start of a response, up to the `<value>` of a successful response.
+    ///
+    /// If the response is a `<fault>`, it is parsed completely and returned instead.
+    pub fn parse_response_start(&mut self) -> ParseResult<Option<Fault>> {
         // <methodResponse>
         self.expect_open("methodResponse")?;
 
         // <fault> / <params>
-        match self.cur.clone() {
-            XmlEvent::StartElement { ref name, .. } => {
+        match self.cur {
+            Event::StartElement { ref name, .. } => {
                 if name.local_name == "fault" {
                     self.next()?;
                     let value = self.parse_value()?;
-                    let fault = Fault::from_value(&value)
-                        .ok_or_else(|| io::Error::new(ErrorKind::Other, "malformed <fault>"))?;
-                    response = Err(fault);
+                    let fault = if self.options.lenient_faults {
+                        Fault::from_value_lenient(&value)
+                    } else {
+                        Fault::from_value(&value)
+                    };
+                    let fault = fault.ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "malformed <fault>")
+                    })?;
+                    Ok(Some(fault))
                 } else if name.local_name == "params" {
                     self.next()?;
                     // <param>
                     self.expect_open("param")?;
-
-                    let value = self.parse_value()?;
-                    response = Ok(value);
-
-                    // </param>
-                    self.expect_close("param")?;
+                    Ok(None)
                 } else {
-                    return self.expected(format!("<fault> or <params>, got {}", name));
+                    self.expected(format!("<fault> or <params>, got {}", name))
                 }
             }
-            _ => return self.expected("<fault> or <params>"),
+            _ => self.expected("<fault> or <params>"),
         }
+    }
 
-        Ok(response)
+    pub fn parse_value(&mut self) -> ParseResult<Value> {
+        let mut builder = ValueBuilder::new();
+        self.visit_value(&mut builder)?;
+        Ok(builder.finish())
     }
 
-    fn parse_value(&mut self) -> ParseResult<Value> {
+    /// Expects an opening `<value>` tag, entering a new nesting level.
+    pub fn open_value(&mut self) -> ParseResult<()> {
+        self.depth += 1;
+        if let Some(limit) = self.options.max_depth {
+            if self.depth > limit {
+                return Err(ParseError::DepthLimitExceeded {
+                    limit,
+                    position: self.source.position(),
+                });
+            }
+        }
+
         // <value>
-        self.expect_open("value")?;
+        self.expect_open("value")
+    }
+
+    /// Expects a closing `</value>` tag, leaving the current nesting level.
+    pub fn close_value(&mut self) -> ParseResult<()> {
+        self.expect_close("value")?;
+        self.depth -= 1;
+        Ok(())
+    }
 
-        if let Ok(()) = self.expect_close("value") {
-            // empty value, parse as empty string
-            return Ok(Value::String(String::new()));
+    /// Returns whether the current token is an opening tag like `<tag>`.
+    pub fn is_open(&self, tag: &str) -> bool {
+        match self.cur {
+            Event::StartElement { ref name, .. } => name.local_name == tag,
+            _ => false,
         }
+    }
 
-        let value = self.parse_value_inner()?;
+    /// Returns whether the current token is a closing tag like `</tag>`.
+    #[cfg(feature = "serde")]
+    pub fn is_close(&self, tag: &str) -> bool {
+        match self.cur {
+            Event::EndElement { ref name } => name.local_name == tag,
+            _ => false,
+        }
+    }
 
-        // </value>
-        self.expect_close("value")?;
+    /// Returns whether the current token is a characters sequence.
+    #[cfg(feature = "serde")]
+    pub fn is_characters(&self) -> bool {
+        matches!(self.cur, Event::Characters(_))
+    }
 
-        Ok(value)
+    /// Returns the position of the current token.
+    #[cfg(feature = "serde")]
+    pub fn position(&self) -> TextPosition {
+        self.source.position()
     }
 
-    fn parse_value_inner(&mut self) -> ParseResult<Value> {
-        let value = match self.cur.clone() {
-            // Raw string or specific type tag
-            XmlEvent::StartElement { ref name, .. } => {
-                let name = &*name.local_name;
-                match name {
-                    "struct" => {
-                        self.next()?;
-                        let mut members = BTreeMap::new();
-                        loop {
-                            if let Ok(_) = self.expect_close("struct") {
-                                break;
-                            }
-
-                            self.expect_open("member")?;
-                            // <member>
-
-                            // <name>NAME</name>
-                            self.expect_open("name")?;
-                            let name = match self.cur {
-                                XmlEvent::Characters(ref string) => string.clone(),
-                                _ => return self.expected("characters"),
-                            };
-                            self.next()?;
-                            self.expect_close("name")?;
-
-                            // Value
-                            let value = self.parse_value()?;
-
-                            // </member>
-                            self.expect_close("member")?;
-
-                            members.insert(name, value);
-                        }
-
-                        Value::Struct(members)
-                    }
-                    "array" => {
-                        self.next()?;
-                        let mut elements: Vec<Value> = Vec::new();
-                        self.expect_open("data")?;
-                        loop {
-                            if let Ok(_) = self.expect_close("data") {
-                                break;
-                            }
-
-                            elements.push(self.parse_value()?);
-                        }
-                        self.expect_close("array")?;
-                        Value::Array(elements)
-                    }
-                    "nil" => {
-                        self.next()?;
-                        self.expect_close("nil")?;
-                        Value::Nil
-                    }
-                    "string" => {
-                        self.next()?;
-                        let string = match self.cur.clone() {
-                            XmlEvent::Characters(string) => {
-                                self.next()?;
-                                self.expect_close("string")?;
-                                string
-                            }
-                            XmlEvent::EndElement { ref name } if name.local_name == "string" => {
-                                self.next()?;
-                                String::new()
-                            }
-                            _ => return self.expected("characters or </string>"),
-                        };
-                        Value::String(string)
-                    }
-                    "base64" => {
-                        self.next()?;
-                        let data = match self.cur.clone() {
-                            XmlEvent::Characters(ref string) => {
-                                self.next()?;
-                                self.expect_close("base64")?;
-
-                                let stripped: Vec<_> = string
-                                    .bytes()
-                                    .filter(|b| !b" \n\t\r\x0b\x0c".contains(b))
-                                    .collect();
-                                base64::decode(&stripped)
-                                    .map_err(|_| self.invalid_value("base64", string.to_string()))?
-                            }
-                            XmlEvent::EndElement { ref name } if name.local_name == "base64" => {
-                                self.next()?;
-                                Vec::new()
-                            }
-                            _ => return self.expected("characters or </base64>"),
-                        };
-                        Value::Base64(data)
-                    }
-                    "i4" | "int" => {
-                        self.next()?;
-                        let value = self
-                            .expect_value("integer", |data| data.parse::<i32>().map(Value::Int))?;
-                        self.expect_close(name)?;
-                        value
+    /// Parses a struct `<member>`.
+    ///
+    /// `is_duplicate` is called with the name of the member and returns whether a member with that
+    /// name has already been parsed.
+    pub fn parse_member(
+        &mut self,
+        is_duplicate: impl Fn(&str) -> bool,
+    ) -> ParseResult<(String, Value)> {
+        let mut builder = ValueBuilder::new();
+        let name = self.visit_member(&mut builder, is_duplicate)?;
+        Ok((name, builder.finish()))
+    }
+
+    /// Parses a struct `<member>`, passing its name and value to `visitor`. Returns the name of
+    /// the member.
+    ///
+    /// `is_duplicate` is called with the name of the member and returns whether a member with that
+    /// name has already been parsed.
+    fn visit_member<V: ValueVisitor>(
+        &mut self,
+        visitor: &mut V,
+        is_duplicate: impl Fn(&str) -> bool,
+    ) -> Result<String, V::Error>
+    where
+        V::Error: From<ParseError>,
+    {
+        let name = self.parse_member_name(is_duplicate)?;
+        visitor.member(&name)?;
+
+        // Value
+        self.visit_value(visitor)?;
+
+        // </member>
+        self.expect_close("member")?;
+
+        Ok(name.into_owned())
+    }
+
+    /// Parses the start of a struct `<member>` up to its `<value>`, and returns the member name.
+    ///
+    /// `is_duplicate` is called with the name of the member and returns whether a member with that
+    /// name has already been parsed.
+    pub fn parse_member_name(
+        &mut self,
+        is_duplicate: impl Fn(&str) -> bool,
+    ) -> ParseResult<Cow<'a, str>> {
+        // <member>
+        self.expect_open("member")?;
+
+        // <name>NAME</name>
+        self.expect_open("name")?;
+        let name = self.expect_string()?;
+        self.expect_close("name")?;
+
+        if self.options.reject_duplicate_members && is_duplicate(&name) {
+            return Err(ParseError::DuplicateMember {
+                name: name.into_owned(),
+                position: self.source.position(),
+            });
+        }
+
+        Ok(name)
+    }
+
+    /// Returns whether struct members with duplicate names are rejected.
+    pub fn rejects_duplicate_members(&self) -> bool {
+        self.options.reject_duplicate_members
+    }
+
+    /// Returns the value of a `<value>` without content: an empty string, or nil if
+    /// `empty_values_as_nil` is enabled.
+    pub fn empty_value(&self) -> Value {
+        if self.options.empty_values_as_nil {
+            Value::Nil
+        } else {
+            Value::String(String::new())
+        }
+    }
+
+    pub fn parse_value_inner(&mut self) -> ParseResult<Value> {
+        let mut builder = ValueBuilder::new();
+        self.visit_value_inner(&mut builder)?;
+        Ok(builder.finish())
+    }
+
+    /// Parses a `<value>`, passing its contents to `visitor`.
+    pub fn visit_value<V: ValueVisitor>(&mut self, visitor: &mut V) -> Result<(), V::Error>
+    where
+        V::Error: From<ParseError>,
+    {
+        self.open_value()?;
+
+        if self.try_close("value")? {
+            if self.options.empty_values_as_nil {
+                visitor.nil()?;
+            } else {
+                visitor.string("")?;
+            }
+        } else {
+            self.visit_value_inner(visitor)?;
+
+            // </value>
+            self.expect_close("value")?;
+        }
+
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Parses the contents of a `<value>`, passing them to `visitor`.
+    pub fn visit_value_inner<V: ValueVisitor>(&mut self, visitor: &mut V) -> Result<(), V::Error>
+    where
+        V::Error: From<ParseError>,
+    {
+        let name = match self.cur {
+            // Apache extension type tag
+            Event::StartElement { ref name, .. } if self.is_extension_name(name) => {
+                return match self.current_tag(EXTENSION_TYPE_TAGS) {
+                    Some(name) => self.visit_extension_value(name, visitor),
+                    None => self.expected("valid extension type tag"),
+                };
+            }
+            // Specific type tag
+            Event::StartElement { .. } => match self.current_tag(TYPE_TAGS) {
+                Some(name) => name,
+                None => return self.expected("valid type tag"),
+            },
+            // Raw string
+            Event::Characters(_) => {
+                visitor.string(&self.expect_string()?)?;
+                return Ok(());
+            }
+            _ => return self.expected("type tag or characters"),
+        };
+        self.next()?;
+
+        match name {
+            "struct" => {
+                visitor.start_struct()?;
+                if self.options.reject_duplicate_members {
+                    let mut names = BTreeSet::new();
+                    while !self.try_close("struct")? {
+                        let name = self.visit_member(visitor, |name| names.contains(name))?;
+                        names.insert(name);
                     }
-                    "i8" => {
-                        self.next()?;
-                        let value =
-                            self.expect_value("i8", |data| data.parse::<i64>().map(Value::Int64))?;
-                        self.expect_close(name)?;
-                        value
+                } else {
+                    while !self.try_close("struct")? {
+                        self.visit_member(visitor, |_| false)?;
                     }
-                    "boolean" => {
-                        self.next()?;
-                        let value = self.expect_value("boolean", |data| match data {
-                            "0" => Ok(Value::Bool(false)),
-                            "1" => Ok(Value::Bool(true)),
-                            _ => Err(()),
-                        })?;
-                        self.expect_close(name)?;
-                        value
+                }
+                visitor.end_struct()?;
+            }
+            "array" => {
+                visitor.start_array()?;
+                self.expect_open("data")?;
+                while !self.try_close("data")? {
+                    self.visit_value(visitor)?;
+                }
+                self.expect_close("array")?;
+                visitor.end_array()?;
+            }
+            "nil" => {
+                self.expect_close("nil")?;
+                visitor.nil()?;
+            }
+            "string" => match self.cur {
+                Event::Characters(_) => {
+                    visitor.string(&self.expect_string()?)?;
+                    self.expect_close("string")?;
+                }
+                Event::EndElement { ref name } if name.local_name == "string" => {
+                    self.next()?;
+                    visitor.string("")?;
+                }
+                _ => return self.expected("characters or </string>"),
+            },
+            "base64" => {
+                visitor.start_base64()?;
+                match self.cur {
+                    Event::Characters(_) => {
+                        self.expect_base64("base64", |chunk| visitor.base64_chunk(chunk))?;
+                        self.expect_close("base64")?;
                     }
-                    "double" => {
+                    Event::EndElement { ref name } if name.local_name == "base64" => {
                         self.next()?;
-                        let value = self.expect_value("double", |data| {
-                            data.parse::<f64>().map(Value::Double)
-                        })?;
-                        self.expect_close(name)?;
-                        value
                     }
-                    "dateTime.iso8601" => {
-                        self.next()?;
-                        let value = self.expect_value("dateTime.iso8601", |data| {
-                            datetime(data).map(Value::DateTime)
-                        })?;
-                        self.expect_close(name)?;
-                        value
+                    _ => return self.expected("characters or </base64>"),
+                }
+                visitor.end_base64()?;
+            }
+            "i4" | "int" => {
+                visitor.int(self.expect_value("integer", str::parse::<i32>)?)?;
+                self.expect_close(name)?;
+            }
+            "i8" => {
+                visitor.int64(self.expect_value("i8", str::parse::<i64>)?)?;
+                self.expect_close(name)?;
+            }
+            "boolean" => {
+                let textual = self.options.textual_booleans;
+                let value = self.expect_value("boolean", |data| match data {
+                    "0" => Ok(false),
+                    "1" => Ok(true),
+                    _ if textual && data.eq_ignore_ascii_case("false") => Ok(false),
+                    _ if textual && data.eq_ignore_ascii_case("true") => Ok(true),
+                    _ => Err(()),
+                })?;
+                visitor.bool(value)?;
+                self.expect_close(name)?;
+            }
+            "double" => {
+                let strict = self.options.strict_doubles;
+                let value = self.expect_value("double", |data| {
+                    if strict && !is_decimal_literal(data) {
+                        return Err(());
                     }
-                    _ => return self.expected("valid type tag"),
+                    data.parse::<f64>().map_err(drop)
+                })?;
+                visitor.double(value)?;
+                self.expect_close(name)?;
+            }
+            "dateTime.iso8601" => {
+                let tolerant = self.options.tolerant_datetimes;
+                let value =
+                    self.expect_value("dateTime.iso8601", |data| parse_datetime(data, tolerant))?;
+                visitor.datetime(value)?;
+                self.expect_close(name)?;
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    /// Parses a `<value>` into a `ValueRef`, taking strings and member names from the document
+    /// without copying them where possible.
+    #[cfg(feature = "quick-xml")]
+    fn parse_value_ref(&mut self) -> ParseResult<ValueRef<'a>> {
+        self.open_value()?;
+
+        let value = match self.cur {
+            // empty value
+            Event::EndElement { ref name } if name.local_name == "value" => {
+                if self.options.empty_values_as_nil {
+                    ValueRef::Nil
+                } else {
+                    ValueRef::String(Cow::Borrowed(""))
                 }
             }
-            XmlEvent::Characters(string) => {
+            // Raw string
+            Event::Characters(_) => ValueRef::String(self.expect_string()?),
+            _ if self.is_open("string") => {
                 self.next()?;
-                Value::String(string)
+                if self.try_close("string")? {
+                    ValueRef::String(Cow::Borrowed(""))
+                } else {
+                    let string = self.expect_string()?;
+                    self.expect_close("string")?;
+                    ValueRef::String(string)
+                }
             }
-            _ => return self.expected("type tag or characters"),
+            _ if self.is_open("struct") => {
+                self.next()?;
+                let mut members = Map::new();
+                while !self.try_close("struct")? {
+                    let name = self.parse_member_name(|name| members.contains_key(name))?;
+                    let value = self.parse_value_ref()?;
+                    self.expect_close("member")?;
+                    members.insert(name, value);
+                }
+                ValueRef::Struct(members)
+            }
+            _ if self.is_open("array") => {
+                self.next()?;
+                self.expect_open("data")?;
+                let mut elements = Vec::new();
+                while !self.try_close("data")? {
+                    elements.push(self.parse_value_ref()?);
+                }
+                self.expect_close("array")?;
+                ValueRef::Array(elements)
+            }
+            // Other values don't borrow from the document
+            _ => ValueRef::from(self.parse_value_inner()?),
         };
 
+        self.close_value()?;
         Ok(value)
     }
+
+    /// Parses the value of an Apache extension type tag named `name` (without prefix), passing it
+    /// to `visitor`.
+    fn visit_extension_value<V: ValueVisitor>(
+        &mut self,
+        name: &'static str,
+        visitor: &mut V,
+    ) -> Result<(), V::Error>
+    where
+        V::Error: From<ParseError>,
+    {
+        if name == "dom" {
+            let xml = self.source.read_raw_element()?;
+            self.next()?;
+            // `read_raw_element` has consumed the closing tag
+            visitor.dom(&xml)?;
+            return Ok(());
+        }
+        self.next()?;
+
+        match name {
+            "nil" => visitor.nil()?,
+            "i1" => visitor.int(self.expect_value("ex:i1", str::parse::<i8>)?.into())?,
+            "i2" => visitor.int(self.expect_value("ex:i2", str::parse::<i16>)?.into())?,
+            "i8" => visitor.int64(self.expect_value("ex:i8", str::parse::<i64>)?)?,
+            "float" => visitor.double(self.expect_value("ex:float", str::parse::<f32>)?.into())?,
+            "dateTime" => {
+                let tolerant = self.options.tolerant_datetimes;
+                let value =
+                    self.expect_value("ex:dateTime", |data| parse_datetime(data, tolerant))?;
+                visitor.datetime(value)?;
+            }
+            "biginteger" => self.expect_value("ex:biginteger", |data| {
+                let digits = data.strip_prefix(['+', '-']).unwrap_or(data);
+                if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+                    Ok(visitor.big_integer(data))
+                } else {
+                    Err(())
+                }
+            })??,
+            "bigdecimal" => self.expect_value("ex:bigdecimal", |data| {
+                let valid_chars = data
+                    .bytes()
+                    .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b));
+                if valid_chars && data.parse::<f64>().is_ok() {
+                    Ok(visitor.big_decimal(data))
+                } else {
+                    Err(())
+                }
+            })??,
+            "serializable" => {
+                let mut data = Vec::new();
+                self.expect_base64("ex:serializable", |chunk| {
+                    data.extend_from_slice(chunk);
+                    Ok(())
+                })?;
+                visitor.serializable(&data)?;
+            }
+            _ => unreachable!(),
+        }
+        self.expect_close(name)?;
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Parser<'static, XmlRsSource<R>> {
+    /// Creates a parser incrementally reading a document from `reader` using xml-rs.
+    ///
+    /// `charset` is the character encoding of the document specified by the transport, if any.
+    pub fn from_reader(
+        reader: R,
+        options: &ParserOptions,
+        charset: Option<&str>,
+    ) -> ParseResult<Self> {
+        let source = XmlRsSource::new(reader, options.max_document_size, charset)?;
+        Parser::new(source, options)
+    }
+}
+
+/// Number of base64 characters decoded at once (a multiple of 4).
+const BASE64_CHUNK_LEN: usize = 16 * 1024;
+
+/// Local names of the type tags defined by the specification.
+const TYPE_TAGS: &[&str] = &[
+    "struct",
+    "array",
+    "nil",
+    "string",
+    "base64",
+    "i4",
+    "int",
+    "i8",
+    "boolean",
+    "double",
+    "dateTime.iso8601",
+];
+
+/// Local names of the Apache extension type tags.
+const EXTENSION_TYPE_TAGS: &[&str] = &[
+    "nil",
+    "i1",
+    "i2",
+    "i8",
+    "float",
+    "dateTime",
+    "biginteger",
+    "bigdecimal",
+    "serializable",
+    "dom",
+];
+
+/// Parses an XML-RPC response document.
+///
+/// This is the parser used by [`Request::call`], and can be used to parse responses received
+/// through other means. The document is transcoded to UTF-8 first if it declares a different
+/// encoding.
+///
+/// # Errors
+///
+/// Returns a `ParseError` if the document is not a valid response, or if reading from `reader`
+/// fails. A `<fault>` response is returned as `Ok(Err(fault))`.
+///
+/// [`Request::call`]: struct.Request.html#method.call
+pub fn parse_response<R: Read>(reader: &mut R, options: &ParserOptions) -> ParseResult<Response> {
+    parse_response_with_charset(reader, options, None)
+}
+
+/// Parses an XML-RPC response document, passing the returned value to `visitor`.
+///
+/// This works like [`parse_response`], except that the returned value is passed to the
+/// [`ValueVisitor`] while it is being parsed instead of building a [`Value`]. A `<fault>` response
+/// is returned as `Ok(Err(fault))` without calling the visitor.
+///
+/// # Errors
+///
+/// Returns the first error returned by the visitor, or a `ParseError` (converted into the
+/// visitor's error type) if the document is not a valid response, or if reading from `reader`
+/// fails. The visitor may already have been called for a part of the value in that case.
+///
+/// [`parse_response`]: fn.parse_response.html
+/// [`ValueVisitor`]: trait.ValueVisitor.html
+/// [`Value`]: enum.Value.html
+pub fn visit_response<R: Read, V: ValueVisitor>(
+    reader: &mut R,
+    options: &ParserOptions,
+    visitor: &mut V,
+) -> Result<Result<(), Fault>, V::Error>
+where
+    V::Error: From<ParseError>,
+{
+    match options.backend {
+        ParserBackend::XmlRs => Parser::from_reader(reader, options, None)?.visit_response(visitor),
+        #[cfg(feature = "quick-xml")]
+        ParserBackend::QuickXml => {
+            let document = read_document(reader, options.max_document_size, None)?;
+            Parser::new(QuickXmlSource::new(&document), options)?.visit_response(visitor)
+        }
+    }
+}
+
+/// Returns whether `data` is a number in the decimal notation allowed for `<double>` values by the
+/// specification.
+fn is_decimal_literal(data: &str) -> bool {
+    let number = data.strip_prefix(['+', '-']).unwrap_or(data);
+    let mut parts = number.splitn(2, '.');
+    let integer = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("");
+    !(integer.is_empty() && fraction.is_empty())
+        && integer
+            .bytes()
+            .chain(fraction.bytes())
+            .all(|b| b.is_ascii_digit())
+}
+
+/// Parses a date/time value. If `tolerant` is set, the variants described at
+/// `ParserOptions::tolerant_datetimes` are first rewritten into a form understood by `iso8601`.
+fn parse_datetime(data: &str, tolerant: bool) -> Result<DateTime, String> {
+    if !tolerant {
+        return datetime(data);
+    }
+
+    let mut normalized = String::with_capacity(data.len() + 9);
+    let mut has_time = false;
+    for c in data.chars() {
+        match c {
+            'T' | 't' | ' ' if !has_time => {
+                has_time = true;
+                normalized.push('T');
+            }
+            ' ' => {}
+            'z' => normalized.push('Z'),
+            _ => normalized.push(c),
+        }
+    }
+    if !has_time {
+        normalized.push_str("T00:00:00");
+    }
+    datetime(&normalized)
+}
+
+/// Parses an XML-RPC response document held in memory into a `ValueRef` borrowing from it.
+///
+/// Strings and struct member names that don't contain any escape sequences or CDATA sections are
+/// borrowed from `xml` instead of being copied. Otherwise, this works like [`parse_response`].
+///
+/// The response is always parsed with [`ParserBackend::QuickXml`], regardless of the backend
+/// selected in the `ParserOptions`. This function is only available when the `quick-xml` feature
+/// is enabled.
+///
+/// # Errors
+///
+/// Returns a `ParseError` if the document is not a valid response. A `<fault>` response is
+/// returned as `Ok(Err(fault))`.
+///
+/// [`parse_response`]: fn.parse_response.html
+/// [`ParserBackend::QuickXml`]: enum.ParserBackend.html#variant.QuickXml
+#[cfg(feature = "quick-xml")]
+pub fn parse_response_ref<'a>(
+    xml: &'a str,
+    options: &ParserOptions,
+) -> ParseResult<Result<ValueRef<'a>, Fault>> {
+    if let Some(limit) = options.max_document_size {
+        if xml.len() as u64 > limit {
+            return Err(ParseError::DocumentTooLarge { limit });
+        }
+    }
+
+    let mut parser = Parser::new(QuickXmlSource::new(xml), options)?;
+    if let Some(fault) = parser.parse_response_start()? {
+        return Ok(Err(fault));
+    }
+    let value = parser.parse_value_ref()?;
+    parser.expect_close("param")?;
+    Ok(Ok(value))
 }
 
 /// Parses a response from an XML reader.
-pub fn parse_response<R: Read>(reader: &mut R) -> ParseResult<Response> {
-    Parser::new(reader)?.parse_response()
+///
+/// `charset` is the character encoding of the document specified by the transport, if any.
+pub fn parse_response_with_charset<R: Read>(
+    reader: &mut R,
+    options: &ParserOptions,
+    charset: Option<&str>,
+) -> ParseResult<Response> {
+    match options.backend {
+        ParserBackend::XmlRs => Parser::from_reader(reader, options, charset)?.parse_response(),
+        #[cfg(feature = "quick-xml")]
+        ParserBackend::QuickXml => {
+            let document = read_document(reader, options.max_document_size, charset)?;
+            Parser::new(QuickXmlSource::new(&document), options)?.parse_response()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -358,13 +1283,65 @@ mod tests {
 
     use std::fmt::Debug;
     use std::iter;
+    #[cfg(feature = "quick-xml")]
+    use std::str;
 
     fn read_response(xml: &str) -> ParseResult<Response> {
-        parse_response(&mut xml.as_bytes())
+        read_response_with(xml, &ParserOptions::default())
+    }
+
+    fn read_response_with(xml: &str, options: &ParserOptions) -> ParseResult<Response> {
+        read_bytes_with(xml.as_bytes(), options)
+    }
+
+    /// Parses a response, checking that all backends agree on the result.
+    fn read_bytes_with(xml: &[u8], options: &ParserOptions) -> ParseResult<Response> {
+        let result = parse_response(&mut &xml[..], options);
+        #[cfg(feature = "quick-xml")]
+        {
+            let options = options.clone().backend(ParserBackend::QuickXml);
+            assert_same_result(&result, &parse_response(&mut &xml[..], &options));
+            if let Ok(xml) = str::from_utf8(xml) {
+                let borrowed = parse_response_ref(xml, &options)
+                    .map(|response| response.map(ValueRef::into_owned));
+                assert_same_result(&result, &borrowed);
+            }
+        }
+        result
     }
 
     fn read_value(xml: &str) -> ParseResult<Value> {
-        Parser::new(&mut xml.as_bytes())?.parse_value()
+        read_value_with(xml, &ParserOptions::default())
+    }
+
+    /// Parses a value, checking that all backends agree on the result.
+    fn read_value_with(xml: &str, options: &ParserOptions) -> ParseResult<Value> {
+        let mut bytes = xml.as_bytes();
+        let source = XmlRsSource::new(&mut bytes, options.max_document_size, None)?;
+        let result = Parser::new(source, options).and_then(|mut parser| parser.parse_value());
+        #[cfg(feature = "quick-xml")]
+        {
+            let source = QuickXmlSource::new(xml);
+            let quick = Parser::new(source, options).and_then(|mut parser| parser.parse_value());
+            assert_same_result(&result, &quick);
+        }
+        result
+    }
+
+    /// Asserts that two parse results are equal, or both errors of the same kind.
+    #[cfg(feature = "quick-xml")]
+    fn assert_same_result<T: Debug + PartialEq>(left: &ParseResult<T>, right: &ParseResult<T>) {
+        match (left, right) {
+            (Ok(left), Ok(right)) => assert_eq!(left, right),
+            (Err(left), Err(right)) => assert_eq!(
+                mem::discriminant(left),
+                mem::discriminant(right),
+                "backends disagree: {:?} / {:?}",
+                left,
+                right
+            ),
+            _ => panic!("backends disagree: {:?} / {:?}", left, right),
+        }
     }
 
     /// Test helper function that will panic with the `Err` if a `Result` is not an `Ok`.
@@ -377,17 +1354,15 @@ mod tests {
 
     /// Test helper function that will panic with the `Ok` if a `Result` is not an `Err`.
     fn assert_err<T: Debug, E: Debug>(result: Result<T, E>) {
-        match result {
-            Ok(t) => panic!("assert_err called on Ok value: {:?}", t),
-            Err(_) => {}
+        if let Ok(t) = result {
+            panic!("assert_err called on Ok value: {:?}", t);
         }
     }
 
     #[test]
     fn parses_base64_response() {
         assert_ok(read_response(
-            r##"
-<?xml version="1.0" encoding="UTF-8"?>
+            r##"<?xml version="1.0" encoding="UTF-8"?>
 <methodResponse>
     <params>
         <param>
@@ -402,8 +1377,7 @@ mod tests {
     #[test]
     fn parses_response() {
         assert_ok(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
     <params>
         <param>
@@ -419,8 +1393,7 @@ mod tests {
     fn parses_fault() {
         assert_eq!(
             read_response(
-                r##"
-<?xml version="1.0"?>
+                r##"<?xml version="1.0"?>
 <methodResponse>
    <fault>
       <value>
@@ -438,10 +1411,7 @@ mod tests {
       </fault>
    </methodResponse>"##
             ),
-            Ok(Err(Fault {
-                fault_code: 4,
-                fault_string: "Too many parameters.".into(),
-            }))
+            Ok(Err(Fault::new(4, "Too many parameters.")))
         );
     }
 
@@ -450,8 +1420,7 @@ mod tests {
         // "A <fault> struct may not contain members other than those specified."
 
         assert_err(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
    <fault>
       <value>
@@ -480,8 +1449,7 @@ mod tests {
         // Make sure to reject type errors in <fault>s - They're specified to contain specifically
         // typed fields.
         assert_err(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
    <fault>
       <value>
@@ -501,8 +1469,7 @@ mod tests {
         ));
 
         assert_err(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
    <fault>
       <value>
@@ -522,6 +1489,54 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parses_nonconforming_faults_leniently() {
+        let options = ParserOptions::new().lenient_faults(true);
+        let response = read_response_with(
+            r##"<?xml version="1.0"?>
+<methodResponse>
+   <fault>
+      <value>
+         <struct>
+            <member>
+               <name>faultCode</name>
+               <value><string>4</string></value>
+               </member>
+            <member>
+               <name>faultString</name>
+               <value><string>Too many parameters.</string></value>
+               </member>
+            <member>
+               <name>traceback</name>
+               <value><string>File "server.py", line 1</string></value>
+               </member>
+            </struct>
+         </value>
+      </fault>
+   </methodResponse>"##,
+            &options,
+        );
+
+        let fault = response.unwrap().unwrap_err();
+        assert_eq!(fault.fault_code, 4);
+        assert_eq!(fault.fault_string, "Too many parameters.");
+        assert_eq!(
+            fault.extra().get("traceback"),
+            Some(&Value::from("File \"server.py\", line 1"))
+        );
+
+        // A fault that isn't a struct is still rejected
+        assert_err(read_response_with(
+            r##"<?xml version="1.0"?>
+<methodResponse>
+   <fault>
+      <value><string>Too many parameters.</string></value>
+   </fault>
+</methodResponse>"##,
+            &options,
+        ));
+    }
+
     #[test]
     fn parses_string_value_with_whitespace() {
         assert_eq!(
@@ -551,6 +1566,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_doubles() {
+        assert_eq!(
+            read_value("<value><double>-12.214</double></value>"),
+            Ok(Value::Double(-12.214))
+        );
+        assert_eq!(
+            read_value("<value><double>1e10</double></value>"),
+            Ok(Value::Double(1e10))
+        );
+        assert_eq!(
+            read_value("<value><double>-2.5E-3</double></value>"),
+            Ok(Value::Double(-2.5e-3))
+        );
+        assert_eq!(
+            read_value("<value><double>-Infinity</double></value>"),
+            Ok(Value::Double(f64::NEG_INFINITY))
+        );
+        // NaN isn't equal to itself, so the backends can't be compared
+        let xml = "<methodResponse><params><param><value><double>NaN</double></value></param>\
+                   </params></methodResponse>";
+        match parse_response(&mut xml.as_bytes(), &ParserOptions::new()) {
+            Ok(Ok(Value::Double(value))) => assert!(value.is_nan()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let strict = ParserOptions::strict();
+        for valid in &["1", "+1.5", "-0.25", ".5", "3."] {
+            let xml = format!("<value><double>{}</double></value>", valid);
+            assert_ok(read_value_with(&xml, &strict));
+        }
+        for invalid in &["1e10", "NaN", "inf", "-Infinity", ".", "+-1", "1.2.3"] {
+            let xml = format!("<value><double>{}</double></value>", invalid);
+            match read_value_with(&xml, &strict) {
+                Err(ParseError::InvalidValue { .. }) => {}
+                result => panic!("{:?} parsed as {:?}", invalid, result),
+            }
+        }
+    }
+
     #[test]
     fn parses_date_values() {
         assert_ok(read_value(
@@ -567,6 +1622,47 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parses_tolerant_datetimes() {
+        let tolerant = ParserOptions::new().tolerant_datetimes(true);
+        let expected = Value::DateTime(datetime("1998-07-17T14:08:55").unwrap());
+        for variant in &[
+            "19980717T14:08:55",
+            "1998-07-17T14:08:55",
+            "1998-07-17T14:08:55Z",
+            "1998-07-17 14:08:55",
+            "1998-07-17t14:08:55z",
+            "19980717T14:08:55 Z",
+        ] {
+            let xml = format!(
+                "<value><dateTime.iso8601>{}</dateTime.iso8601></value>",
+                variant
+            );
+            assert_eq!(read_value_with(&xml, &tolerant), Ok(expected.clone()));
+        }
+
+        let xml = "<value><dateTime.iso8601>1998-07-17 14:08:55 -05:00</dateTime.iso8601></value>";
+        assert_err(read_value(xml));
+        assert_eq!(
+            read_value_with(xml, &tolerant),
+            Ok(Value::DateTime(
+                datetime("1998-07-17T14:08:55-05:00").unwrap()
+            ))
+        );
+
+        let xml = "<value><dateTime.iso8601>1998-07-17</dateTime.iso8601></value>";
+        assert_err(read_value(xml));
+        assert_eq!(
+            read_value_with(xml, &tolerant),
+            Ok(Value::DateTime(datetime("1998-07-17T00:00:00").unwrap()))
+        );
+
+        assert_err(read_value_with(
+            "<value><dateTime.iso8601>ILLEGAL VALUE :(</dateTime.iso8601></value>",
+            &tolerant,
+        ));
+    }
+
     #[test]
     fn parses_base64() {
         assert_eq!(
@@ -654,6 +1750,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parses_empty_value_as_nil() {
+        let options = ParserOptions::new().empty_values_as_nil(true);
+        assert_eq!(read_value_with("<value/>", &options), Ok(Value::Nil));
+        assert_eq!(
+            read_value_with("<value><string/></value>", &options),
+            Ok(Value::String(String::new()))
+        );
+        assert_eq!(
+            read_response_with(
+                r##"<?xml version="1.0"?>
+<methodResponse><params><param><value><array><data>
+    <value></value>
+</data></array></value></param></params></methodResponse>"##,
+                &options,
+            ),
+            Ok(Ok(Value::Array(vec![Value::Nil])))
+        );
+    }
+
+    #[test]
+    fn ignores_namespaces() {
+        let xml = r##"<?xml version="1.0"?>
+<x:methodResponse xmlns:x="urn:x"><x:params><x:param>
+    <value xmlns="urn:y"><array><data><value><x:i4>1</x:i4></value></data></array></value>
+</x:param></x:params></x:methodResponse>"##;
+        assert_err(read_response(xml));
+        assert_eq!(
+            read_response_with(xml, &ParserOptions::new().ignore_namespaces(true)),
+            Ok(Ok(Value::Array(vec![Value::Int(1)])))
+        );
+
+        // Apache extension types take precedence over ignored namespaces
+        let options = ParserOptions::new()
+            .ignore_namespaces(true)
+            .apache_extensions(true);
+        let ex = r#"xmlns:ex="http://ws.apache.org/xmlrpc/namespaces/extensions""#;
+        assert_eq!(
+            read_value_with(&format!("<value {}><ex:i8>1</ex:i8></value>", ex), &options),
+            Ok(Value::Int64(1))
+        );
+        assert_err(read_value_with(
+            &format!("<value {}><ex:string>a</ex:string></value>", ex),
+            &options,
+        ));
+    }
+
     #[test]
     fn rejects_attributes() {
         assert_err(read_value(
@@ -661,8 +1804,7 @@ mod tests {
         ));
 
         assert_err(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse invalid="1">
     <params>
         <param>
@@ -673,8 +1815,7 @@ mod tests {
 "##,
         ));
         assert_err(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
     <params invalid="1">
         <param>
@@ -685,8 +1826,7 @@ mod tests {
 "##,
         ));
         assert_err(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
     <params>
         <param invalid="1">
@@ -697,8 +1837,7 @@ mod tests {
 "##,
         ));
         assert_err(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
     <params>
         <param>
@@ -709,8 +1848,7 @@ mod tests {
 "##,
         ));
         assert_err(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
     <params>
         <param>
@@ -730,7 +1868,7 @@ mod tests {
 
         assert_eq!(
             errstr(r#"<value name="ble">\t  I'm a string!  </value>"#),
-            "unexpected XML at 1:1 (expected tag <value> without attributes, found end of data)"
+            "unexpected XML at 1:18 (expected tag <value> without attributes, found end of data)"
         );
 
         assert_eq!(
@@ -747,8 +1885,7 @@ mod tests {
     #[test]
     fn parses_empty_value_response() {
         assert_ok(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
     <params>
         <param>
@@ -763,8 +1900,7 @@ mod tests {
     #[test]
     fn parses_empty_value_in_struct_response() {
         assert_ok(read_response(
-            r##"
-<?xml version="1.0"?>
+            r##"<?xml version="1.0"?>
 <methodResponse>
     <params>
         <param><value>
@@ -804,4 +1940,342 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    #[cfg(feature = "indexmap")]
+    fn preserves_struct_member_order() {
+        let xml = "<value><struct>\n\
+                   <member>\n<name>zeta</name>\n<value><i4>1</i4></value>\n</member>\n\
+                   <member>\n<name>alpha</name>\n<value><string>2</string></value>\n</member>\n\
+                   </struct></value>\n";
+        let value = read_value(xml).unwrap();
+        let names: Vec<_> = value.as_struct().unwrap().keys().collect();
+        assert_eq!(names, ["zeta", "alpha"]);
+
+        let mut output = Vec::new();
+        value.write_as_xml(&mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), xml);
+    }
+
+    #[test]
+    fn rejects_duplicate_struct_member_in_strict_mode() {
+        let err = read_value_with(
+            r#"<value><struct>
+                <member><name>A</name><value>first</value></member>
+                <member><name>A</name><value>second</value></member>
+            </struct></value>"#,
+            &ParserOptions::strict(),
+        )
+        .unwrap_err();
+        match err {
+            ParseError::DuplicateMember { ref name, .. } => assert_eq!(name, "A"),
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn lenient_dialect() {
+        let lenient = ParserOptions::lenient();
+
+        assert_err(read_value("<value><boolean>true</boolean></value>"));
+        assert_eq!(
+            read_value_with("<value><boolean>true</boolean></value>", &lenient),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            read_value_with("<value><boolean>FALSE</boolean></value>", &lenient),
+            Ok(Value::Bool(false))
+        );
+        assert_err(read_value_with(
+            "<value><boolean>yes</boolean></value>",
+            &lenient,
+        ));
+
+        assert_err(read_value("<value><int> 42\n</int></value>"));
+        assert_eq!(
+            read_value_with("<value><int> 42\n</int></value>", &lenient),
+            Ok(Value::Int(42))
+        );
+        assert_eq!(
+            read_value_with("<value><double>\t1.5 </double></value>", &lenient),
+            Ok(Value::Double(1.5))
+        );
+
+        assert_eq!(
+            read_value_with(
+                r#"<value type="int"><int xmlns="">4</int></value>"#,
+                &lenient
+            ),
+            Ok(Value::Int(4))
+        );
+        assert_eq!(
+            read_value_with(r#"<x:value xmlns:x="urn:x">a</x:value>"#, &lenient),
+            Ok(Value::from("a"))
+        );
+        assert_ok(read_response_with(
+            r##"<?xml version="1.0"?>
+<methodResponse version="2">
+    <params>
+        <param name="result">
+            <value>teststring</value>
+        </param>
+    </params>
+</methodResponse>
+"##,
+            &lenient,
+        ));
+
+        // Strings are never trimmed
+        assert_eq!(
+            read_value_with("<value><string> a </string></value>", &lenient),
+            Ok(Value::String(" a ".into()))
+        );
+    }
+
+    #[test]
+    fn limits_depth() {
+        let nested = |depth| {
+            let mut xml = String::new();
+            for _ in 1..depth {
+                xml.push_str("<value><array><data>");
+            }
+            xml.push_str("<value><int>1</int></value>");
+            for _ in 1..depth {
+                xml.push_str("</data></array></value>");
+            }
+            xml
+        };
+
+        let options = ParserOptions::new().max_depth(Some(3));
+        assert_ok(read_value_with(&nested(3), &options));
+        match read_value_with(&nested(4), &options) {
+            Err(ParseError::DepthLimitExceeded { limit: 3, .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // The default limit protects against stack overflows
+        match read_value(&nested(100_000)) {
+            Err(ParseError::DepthLimitExceeded { limit, .. }) => {
+                assert_eq!(limit, ParserOptions::DEFAULT_MAX_DEPTH)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn limits_document_size() {
+        let xml = r##"<?xml version="1.0"?>
+<methodResponse>
+    <params>
+        <param>
+            <value>teststring</value>
+        </param>
+    </params>
+</methodResponse>"##;
+
+        let options = ParserOptions::new().max_document_size(Some(xml.len() as u64));
+        assert_ok(read_response_with(xml, &options));
+        let options = ParserOptions::new().max_document_size(Some(50));
+        assert_eq!(
+            read_response_with(xml, &options),
+            Err(ParseError::DocumentTooLarge { limit: 50 })
+        );
+    }
+
+    #[test]
+    fn limits_strings() {
+        let options = ParserOptions::new().max_string_length(Some(5));
+        assert_ok(read_value_with("<value>short</value>", &options));
+        assert_ok(read_value_with(
+            "<value><string>short</string></value>",
+            &options,
+        ));
+        for xml in &[
+            "<value>longer</value>",
+            "<value><string>longer</string></value>",
+            "<value><struct><member><name>longer</name><value/></member></struct></value>",
+        ] {
+            match read_value_with(xml, &options) {
+                Err(ParseError::StringTooLong { limit: 5, .. }) => {}
+                other => panic!("unexpected result for {}: {:?}", xml, other),
+            }
+        }
+    }
+
+    #[test]
+    fn limits_base64() {
+        // "Поехали!" is 15 bytes
+        let xml = "<value><base64>0J/QvtC10YXQsNC70Lgh</base64></value>";
+        assert_ok(read_value_with(
+            xml,
+            &ParserOptions::new().max_base64_size(Some(15)),
+        ));
+        match read_value_with(xml, &ParserOptions::new().max_base64_size(Some(14))) {
+            Err(ParseError::Base64TooLarge { limit: 14, .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn limits_elements() {
+        let xml = "<value><array><data><value>a</value><value>b</value></data></array></value>";
+        assert_ok(read_value_with(
+            xml,
+            &ParserOptions::new().max_elements(Some(5)),
+        ));
+        match read_value_with(xml, &ParserOptions::new().max_elements(Some(4))) {
+            Err(ParseError::TooManyElements { limit: 4, .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_apache_extensions() {
+        let options = ParserOptions::new().apache_extensions(true);
+        let read_ex = |inner: &str| {
+            read_value_with(
+                &format!(
+                    r#"<value xmlns:ex="http://ws.apache.org/xmlrpc/namespaces/extensions">{}</value>"#,
+                    inner
+                ),
+                &options,
+            )
+        };
+
+        assert_eq!(read_ex("<ex:nil/>"), Ok(Value::Nil));
+        assert_eq!(read_ex("<ex:i1>-128</ex:i1>"), Ok(Value::Int(-128)));
+        assert_err(read_ex("<ex:i1>128</ex:i1>"));
+        assert_eq!(read_ex("<ex:i2>1000</ex:i2>"), Ok(Value::Int(1000)));
+        assert_eq!(
+            read_ex("<ex:i8>-100100100100</ex:i8>"),
+            Ok(Value::Int64(-100100100100))
+        );
+        assert_eq!(read_ex("<ex:float>1.5</ex:float>"), Ok(Value::Double(1.5)));
+        assert_ok(read_ex(
+            "<ex:dateTime>2008-01-01T12:00:00.000+0100</ex:dateTime>",
+        ));
+        assert_eq!(
+            read_ex("<ex:biginteger>-123456789012345678901234567890</ex:biginteger>"),
+            Ok(Value::BigInteger(
+                "-123456789012345678901234567890".to_string()
+            ))
+        );
+        assert_err(read_ex("<ex:biginteger>12.5</ex:biginteger>"));
+        assert_eq!(
+            read_ex("<ex:bigdecimal>1.25E+3</ex:bigdecimal>"),
+            Ok(Value::BigDecimal("1.25E+3".to_string()))
+        );
+        assert_err(read_ex("<ex:bigdecimal>NaN</ex:bigdecimal>"));
+        assert_eq!(
+            read_ex("<ex:serializable>rO0ABQ==</ex:serializable>"),
+            Ok(Value::Serializable(vec![0xac, 0xed, 0x00, 0x05]))
+        );
+        assert_eq!(
+            read_ex(
+                r#"<ex:dom><doc a="&lt;"><!--c--><p:x xmlns:p="urn:p">1 &amp; 2</p:x><y/></doc></ex:dom>"#
+            ),
+            Ok(Value::Dom(
+                r#"<doc a="&lt;"><p:x xmlns:p="urn:p">1 &amp; 2</p:x><y></y></doc>"#.to_string()
+            ))
+        );
+        assert_err(read_ex("<ex:string>a</ex:string>"));
+
+        // The extension types are rejected unless enabled
+        assert_err(read_value(
+            r#"<value xmlns:ex="http://ws.apache.org/xmlrpc/namespaces/extensions"><ex:nil/></value>"#,
+        ));
+        // ...and other namespaces are always rejected
+        assert_err(read_value_with(
+            r#"<value xmlns:ex="urn:other"><ex:nil/></value>"#,
+            &options,
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_xml() {
+        for xml in &[
+            "",
+            "<methodResponse><params><param><value>",
+            "<methodResponse><params></param></params></methodResponse>",
+            "text<methodResponse/>",
+            "<methodResponse><params><param><value>&unknown;</value></param></params></methodResponse>",
+            "<methodResponse><params><param><value><x:y/></value></param></params></methodResponse>",
+        ] {
+            match read_response(xml) {
+                Err(ParseError::XmlError(_)) => {}
+                result => panic!("{:?} parsed as {:?}", xml, result),
+            }
+        }
+    }
+
+    #[test]
+    fn parses_latin1_response() {
+        let xml: &[u8] = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>
+<methodResponse>
+    <params>
+        <param>
+            <value><string>Gr\xfc\xdfe</string></value>
+        </param>
+    </params>
+</methodResponse>";
+
+        assert_eq!(
+            read_bytes_with(xml, &ParserOptions::default()),
+            Ok(Ok(Value::from("Grüße")))
+        );
+    }
+
+    #[test]
+    fn charset_overrides_declared_encoding() {
+        let xml = "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?>
+<methodResponse><params><param>
+    <value><string>Grüße</string></value>
+</param></params></methodResponse>";
+        assert_eq!(
+            parse_response_with_charset(&mut xml.as_bytes(), &ParserOptions::new(), Some("utf-8")),
+            Ok(Ok(Value::from("Grüße")))
+        );
+
+        let xml = xml.replace("ISO-8859-1", "x-unknown");
+        assert_eq!(
+            parse_response_with_charset(&mut xml.as_bytes(), &ParserOptions::new(), Some("utf-8")),
+            Ok(Ok(Value::from("Grüße")))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "quick-xml")]
+    fn borrows_unescaped_strings() {
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse><params><param><value><struct>
+  <member><name>plain</name><value><string>chair</string></value></member>
+  <member><name>raw</name><value>table</value></member>
+  <member><name>esc&amp;aped</name><value>a &lt; b</value></member>
+  <member><name>list</name><value><array><data>
+    <value><i4>1</i4></value>
+    <value/>
+  </data></array></value></member>
+</struct></value></param></params></methodResponse>"#;
+
+        let value = parse_response_ref(xml, &ParserOptions::new())
+            .unwrap()
+            .unwrap();
+        let members = match value {
+            ValueRef::Struct(ref members) => members,
+            ref other => panic!("unexpected value: {:?}", other),
+        };
+        for (name, value) in members {
+            match (&**name, name, value) {
+                ("plain", &Cow::Borrowed(_), &ValueRef::String(Cow::Borrowed("chair")))
+                | ("raw", &Cow::Borrowed(_), &ValueRef::String(Cow::Borrowed("table")))
+                | ("esc&aped", &Cow::Owned(_), &ValueRef::String(Cow::Owned(_)))
+                | ("list", &Cow::Borrowed(_), &ValueRef::Array(_)) => {}
+                other => panic!("unexpected member: {:?}", other),
+            }
+        }
+        assert_eq!(
+            Ok(Ok(value.to_value())),
+            parse_response(&mut xml.as_bytes(), &ParserOptions::new())
+        );
+    }
 }
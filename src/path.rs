@@ -0,0 +1,546 @@
+//! Lookup of nested values by path.
+
+use Value;
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+/// A step of a path.
+#[derive(Debug)]
+enum Segment<'p> {
+    /// A struct member (`.name` in a path).
+    Member(&'p str),
+    /// An array element (`[3]` in a path).
+    Element(usize),
+    /// A JSON Pointer reference token, which selects a struct member or an array element
+    /// depending on the value it is applied to.
+    Token(Cow<'p, str>),
+}
+
+/// A parsed path, storing the byte offset at which each segment starts.
+type Segments<'p> = Vec<(usize, Segment<'p>)>;
+
+/// Parses a path like `a.b[3].c`.
+fn parse_path(path: &str) -> Result<Segments<'_>, PathError> {
+    let mut segments = Vec::new();
+    let mut pos = 0;
+    while pos < path.len() {
+        let start = pos;
+        let rest = &path[pos..];
+        if let Some(rest) = rest.strip_prefix('[') {
+            let digits = match rest.find(']') {
+                Some(end) => &rest[..end],
+                None => return Err(PathError::syntax(path, start)),
+            };
+            let index = match digits.parse::<usize>() {
+                Ok(index) if digits.bytes().all(|b| b.is_ascii_digit()) => index,
+                _ => return Err(PathError::syntax(path, start)),
+            };
+            segments.push((start, Segment::Element(index)));
+            pos += digits.len() + 2;
+        } else {
+            // The first member doesn't need a leading dot
+            let name_start = match rest.strip_prefix('.') {
+                Some(_) => pos + 1,
+                None if pos == 0 => pos,
+                None => return Err(PathError::syntax(path, start)),
+            };
+            let name_end = path[name_start..]
+                .find(['.', '['])
+                .map_or(path.len(), |end| name_start + end);
+            if name_start == name_end {
+                return Err(PathError::syntax(path, start));
+            }
+            segments.push((start, Segment::Member(&path[name_start..name_end])));
+            pos = name_end;
+        }
+    }
+    Ok(segments)
+}
+
+/// Parses a JSON Pointer like `/a/b/3/c`.
+fn parse_pointer(pointer: &str) -> Result<Segments<'_>, PathError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if !pointer.starts_with('/') {
+        return Err(PathError::syntax(pointer, 0));
+    }
+
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for token in pointer[1..].split('/') {
+        let token = if token.contains('~') {
+            let mut unescaped = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                match (c, chars.clone().next()) {
+                    ('~', Some('0')) => unescaped.push('~'),
+                    ('~', Some('1')) => unescaped.push('/'),
+                    ('~', _) => return Err(PathError::syntax(pointer, start)),
+                    _ => {
+                        unescaped.push(c);
+                        continue;
+                    }
+                }
+                chars.next();
+            }
+            Cow::Owned(unescaped)
+        } else {
+            Cow::Borrowed(token)
+        };
+        segments.push((start, Segment::Token(token)));
+        start += token_len(pointer, start);
+    }
+    Ok(segments)
+}
+
+/// Returns the length of the pointer segment starting at `start`, including its `/`.
+fn token_len(pointer: &str, start: usize) -> usize {
+    pointer[start + 1..]
+        .find('/')
+        .map_or(pointer.len() - start, |end| end + 1)
+}
+
+/// Parses a JSON Pointer reference token as an array index, which must not have leading zeros.
+fn parse_index(token: &str) -> Option<usize> {
+    if token.is_empty() || token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+    if !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+/// Checks that `segment` can be applied to `value`, returning the index or member name to look
+/// up.
+fn check<'s>(value: &Value, segment: &'s Segment<'_>) -> Result<Step<'s>, PathErrorKind> {
+    let wrong_type = |expected| PathErrorKind::WrongType {
+        expected,
+        found: value.type_name(),
+    };
+    let name = match (segment, value) {
+        (&Segment::Element(index), &Value::Array(_)) => return Ok(Step::Element(index)),
+        (&Segment::Element(_), _) => return Err(wrong_type("array")),
+        (&Segment::Member(name), _) => name,
+        (Segment::Token(token), Value::Array(_)) => match parse_index(token) {
+            Some(index) => return Ok(Step::Element(index)),
+            None => return Err(wrong_type("struct")),
+        },
+        (Segment::Token(token), _) => token,
+    };
+    match *value {
+        Value::Struct(_) => Ok(Step::Member(name)),
+        _ => Err(wrong_type("struct")),
+    }
+}
+
+/// A segment resolved against the value it is applied to.
+enum Step<'s> {
+    Member(&'s str),
+    Element(usize),
+}
+
+impl<'s> Step<'s> {
+    fn missing(&self, value: &Value) -> PathErrorKind {
+        match *self {
+            Step::Member(name) => PathErrorKind::MissingMember(name.to_string()),
+            Step::Element(index) => PathErrorKind::IndexOutOfBounds {
+                index,
+                len: value.as_array().map_or(0, <[Value]>::len),
+            },
+        }
+    }
+}
+
+fn resolve<'v>(
+    mut value: &'v Value,
+    path: &str,
+    segments: Segments<'_>,
+) -> Result<&'v Value, PathError> {
+    for (start, segment) in segments {
+        let step = check(value, &segment).map_err(|kind| PathError::new(path, start, kind))?;
+        let next = match step {
+            Step::Member(name) => value.get(name),
+            Step::Element(index) => value.get(index),
+        };
+        value = next.ok_or_else(|| PathError::new(path, start, step.missing(value)))?;
+    }
+    Ok(value)
+}
+
+fn resolve_mut<'v>(mut value: &'v mut Value, segments: Segments<'_>) -> Option<&'v mut Value> {
+    for (_, segment) in segments {
+        value = match check(value, &segment).ok()? {
+            Step::Member(name) => value.get_mut(name)?,
+            Step::Element(index) => value.get_mut(index)?,
+        };
+    }
+    Some(value)
+}
+
+/// The location of a nested value, in both path and JSON Pointer syntax.
+///
+/// Used to report where a difference or violation was found.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Location {
+    pub path: String,
+    pub pointer: String,
+}
+
+impl Location {
+    /// Returns the location of the member `name` of the struct at this location.
+    pub fn member(&self, name: &str) -> Location {
+        let path = if self.path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.path, name)
+        };
+        let token = name.replace('~', "~0").replace('/', "~1");
+        Location {
+            path,
+            pointer: format!("{}/{}", self.pointer, token),
+        }
+    }
+
+    /// Returns the location of the element at `index` of the array at this location.
+    pub fn element(&self, index: usize) -> Location {
+        Location {
+            path: format!("{}[{}]", self.path, index),
+            pointer: format!("{}/{}", self.pointer, index),
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    /// Writes the path in backticks, or "the value" for the empty path.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str("the value")
+        } else {
+            write!(f, "`{}`", self.path)
+        }
+    }
+}
+
+impl Value {
+    /// Returns the nested value at `path`, or `None` if it doesn't exist.
+    ///
+    /// A path consists of struct member names separated by dots and array indices in square
+    /// brackets, such as `a.b[3].c`. The empty path refers to the value itself. Member names
+    /// containing `.` or `[` can be looked up with [`pointer`] instead.
+    ///
+    /// Use [`try_get_path`] to find out why a lookup failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use xmlrpc::Value;
+    /// let person = Value::Struct(vec![
+    ///     ("children".to_string(), Value::Array(vec![
+    ///         Value::Struct(vec![
+    ///             ("name".to_string(), Value::from("Mark")),
+    ///         ].into_iter().collect()),
+    ///     ])),
+    /// ].into_iter().collect());
+    ///
+    /// assert_eq!(person.get_path("children[0].name"), Some(&Value::from("Mark")));
+    /// assert_eq!(person.pointer("/children/0/name"), Some(&Value::from("Mark")));
+    /// assert_eq!(person.get_path("children[1].name"), None);
+    ///
+    /// let err = person.try_get_path("children[0].age").unwrap_err();
+    /// assert_eq!(err.to_string(), "`children[0]` has no member `age`");
+    /// ```
+    ///
+    /// [`pointer`]: #method.pointer
+    /// [`try_get_path`]: #method.try_get_path
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        self.try_get_path(path).ok()
+    }
+
+    /// Returns a mutable reference to the nested value at `path`, or `None` if it doesn't exist.
+    ///
+    /// See [`get_path`] for the path syntax.
+    ///
+    /// [`get_path`]: #method.get_path
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        resolve_mut(self, parse_path(path).ok()?)
+    }
+
+    /// Returns the nested value at `path`, or an error describing the first segment of the path
+    /// that could not be resolved.
+    ///
+    /// See [`get_path`] for the path syntax.
+    ///
+    /// [`get_path`]: #method.get_path
+    pub fn try_get_path(&self, path: &str) -> Result<&Value, PathError> {
+        resolve(self, path, parse_path(path)?)
+    }
+
+    /// Returns the nested value referenced by a [JSON Pointer], or `None` if it doesn't exist.
+    ///
+    /// A pointer consists of reference tokens that are each prefixed by `/`, such as `/a/b/3/c`.
+    /// A token selects a struct member or, if applied to an array, the element with that index.
+    /// Within a token, `~1` stands for `/` and `~0` for `~`. The empty pointer refers to the
+    /// value itself.
+    ///
+    /// [JSON Pointer]: https://www.rfc-editor.org/rfc/rfc6901
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        self.try_pointer(pointer).ok()
+    }
+
+    /// Returns a mutable reference to the nested value referenced by a JSON Pointer, or `None`
+    /// if it doesn't exist.
+    ///
+    /// See [`pointer`] for the pointer syntax.
+    ///
+    /// [`pointer`]: #method.pointer
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        resolve_mut(self, parse_pointer(pointer).ok()?)
+    }
+
+    /// Returns the nested value referenced by a JSON Pointer, or an error describing the first
+    /// reference token that could not be resolved.
+    ///
+    /// See [`pointer`] for the pointer syntax.
+    ///
+    /// [`pointer`]: #method.pointer
+    pub fn try_pointer(&self, pointer: &str) -> Result<&Value, PathError> {
+        resolve(self, pointer, parse_pointer(pointer)?)
+    }
+}
+
+/// An error looking up a nested value by path.
+///
+/// Returned by [`Value::try_get_path`] and [`Value::try_pointer`].
+///
+/// [`Value::try_get_path`]: enum.Value.html#method.try_get_path
+/// [`Value::try_pointer`]: enum.Value.html#method.try_pointer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathError {
+    path: String,
+    position: usize,
+    kind: PathErrorKind,
+}
+
+impl PathError {
+    fn new(path: &str, position: usize, kind: PathErrorKind) -> Self {
+        PathError {
+            path: path.to_string(),
+            position,
+            kind,
+        }
+    }
+
+    fn syntax(path: &str, position: usize) -> Self {
+        Self::new(path, position, PathErrorKind::Syntax)
+    }
+
+    /// Returns the path that was looked up.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the part of the path that was resolved successfully, which ends right before the
+    /// segment that caused the error.
+    pub fn resolved(&self) -> &str {
+        &self.path[..self.position]
+    }
+
+    /// Returns the rest of the path, starting with the segment that caused the error.
+    pub fn remaining(&self) -> &str {
+        &self.path[self.position..]
+    }
+
+    /// Returns the reason why the path could not be resolved.
+    pub fn kind(&self) -> &PathErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let resolved = self.resolved();
+        let value: Cow<'_, str> = if resolved.is_empty() {
+            "the value".into()
+        } else {
+            format!("`{}`", resolved).into()
+        };
+        match self.kind {
+            PathErrorKind::Syntax => {
+                write!(f, "invalid path `{}` at `{}`", self.path, self.remaining())
+            }
+            PathErrorKind::MissingMember(ref name) => {
+                write!(f, "{} has no member `{}`", value, name)
+            }
+            PathErrorKind::IndexOutOfBounds { index, len } => write!(
+                f,
+                "index {} is out of bounds for {} of length {}",
+                index, value, len
+            ),
+            PathErrorKind::WrongType { expected, found } => write!(
+                f,
+                "{} is of type `{}`, expected `{}`",
+                value, found, expected
+            ),
+        }
+    }
+}
+
+impl error::Error for PathError {}
+
+/// The reason why a path could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PathErrorKind {
+    /// The path is malformed, such as `a..b` or `a[x]`, or the pointer doesn't start with `/`.
+    Syntax,
+    /// The struct has no member with the given name.
+    MissingMember(String),
+    /// The array index is out of bounds.
+    IndexOutOfBounds {
+        /// The index in the path.
+        index: usize,
+        /// The length of the array.
+        len: usize,
+    },
+    /// The segment can't be applied to the value, such as an index into a struct or a member of
+    /// a string.
+    WrongType {
+        /// The type the segment can be applied to (`struct` or `array`).
+        expected: &'static str,
+        /// The XML-RPC type of the value, such as `i4` or `string`.
+        found: &'static str,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Map;
+
+    fn sample() -> Value {
+        let mut child = Map::new();
+        child.insert("name".to_string(), Value::from("Mark"));
+        child.insert("a/b~c".to_string(), Value::Int(1));
+        let mut map = Map::new();
+        map.insert(
+            "children".to_string(),
+            Value::Array(vec![Value::Struct(child), Value::Int(2)]),
+        );
+        map.insert("0".to_string(), Value::Bool(true));
+        Value::Struct(map)
+    }
+
+    #[test]
+    fn looks_up_paths() {
+        let value = sample();
+        assert_eq!(value.get_path(""), Some(&value));
+        assert_eq!(
+            value.get_path("children[0].name"),
+            Some(&Value::from("Mark"))
+        );
+        assert_eq!(value.get_path("children[1]"), Some(&Value::Int(2)));
+        assert_eq!(value.get_path("0"), Some(&Value::Bool(true)));
+        assert_eq!(value["children"][1].get_path("[0]"), None);
+
+        assert_eq!(value.pointer(""), Some(&value));
+        assert_eq!(
+            value.pointer("/children/0/name"),
+            Some(&Value::from("Mark"))
+        );
+        assert_eq!(value.pointer("/children/0/a~1b~0c"), Some(&Value::Int(1)));
+        assert_eq!(value.pointer("/0"), Some(&Value::Bool(true)));
+        assert_eq!(value.pointer("/children/01"), None);
+    }
+
+    #[test]
+    fn reports_failing_segment() {
+        let value = sample();
+        let err = value.try_get_path("children[0].age").unwrap_err();
+        assert_eq!(err.resolved(), "children[0]");
+        assert_eq!(err.remaining(), ".age");
+        assert_eq!(err.kind(), &PathErrorKind::MissingMember("age".into()));
+
+        let err = value.try_get_path("children[5].name").unwrap_err();
+        assert_eq!(err.resolved(), "children");
+        assert_eq!(
+            err.kind(),
+            &PathErrorKind::IndexOutOfBounds { index: 5, len: 2 }
+        );
+        assert_eq!(
+            err.to_string(),
+            "index 5 is out of bounds for `children` of length 2"
+        );
+
+        let err = value.try_get_path("children[1].name").unwrap_err();
+        assert_eq!(err.resolved(), "children[1]");
+        assert_eq!(
+            err.to_string(),
+            "`children[1]` is of type `i4`, expected `struct`"
+        );
+
+        let err = value.try_get_path("[0]").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the value is of type `struct`, expected `array`"
+        );
+
+        let err = value.try_pointer("/children/x/name").unwrap_err();
+        assert_eq!(err.resolved(), "/children");
+        assert_eq!(err.remaining(), "/x/name");
+        assert_eq!(
+            err.kind(),
+            &PathErrorKind::WrongType {
+                expected: "struct",
+                found: "array",
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        let value = sample();
+        for (path, remaining) in &[
+            ("a..b", "..b"),
+            ("a.", "."),
+            ("a[1", "[1"),
+            ("a[-1]", "[-1]"),
+            ("a[]", "[]"),
+            ("a[0]b", "b"),
+        ] {
+            let err = value.try_get_path(path).unwrap_err();
+            assert_eq!(err.kind(), &PathErrorKind::Syntax, "{}", path);
+            assert_eq!(err.remaining(), *remaining, "{}", path);
+        }
+
+        for (pointer, remaining) in &[("a", "a"), ("/a/b~2", "/b~2"), ("/~", "/~")] {
+            let err = value.try_pointer(pointer).unwrap_err();
+            assert_eq!(err.kind(), &PathErrorKind::Syntax, "{}", pointer);
+            assert_eq!(err.remaining(), *remaining, "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn mutates_nested_values() {
+        let mut value = sample();
+        *value.get_path_mut("children[0].name").unwrap() = Value::from("Jennyfer");
+        assert_eq!(value["children"][0]["name"], Value::from("Jennyfer"));
+
+        let children = value.pointer_mut("/children").unwrap();
+        children.push(Value::Nil).unwrap();
+        assert_eq!(children.remove(1), Some(Value::Int(2)));
+        assert_eq!(children.remove(5), None);
+        assert_eq!(children.as_array().unwrap().len(), 2);
+        assert_eq!(children.insert("x", Value::Nil), Err(Value::Nil));
+
+        let child = value.get_path_mut("children[0]").unwrap();
+        assert_eq!(child.insert("age", Value::Int(3)), Ok(None));
+        assert_eq!(child.remove("name"), Some(Value::from("Jennyfer")));
+        assert_eq!(child.push(Value::Nil), Err(Value::Nil));
+        assert_eq!(value.get_path("children[0].age"), Some(&Value::Int(3)));
+        assert_eq!(value.get_path_mut("children[0].name"), None);
+    }
+}
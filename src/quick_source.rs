@@ -0,0 +1,363 @@
+//! An `EventSource` using the quick-xml parser.
+//!
+//! Unlike xml-rs, quick-xml parses documents that are completely held in memory. This allows it
+//! to hand out text and names borrowed from the document instead of allocating them.
+
+use encoding::Utf8Reader;
+use error::ParseError;
+use source::{write_namespace_declaration, Event, EventSource, Name};
+use utils::{escape_xml, LimitedReader};
+
+use quick_xml::events::{BytesStart, Event as XmlEvent};
+use quick_xml::name::{PrefixDeclaration, QName, ResolveResult};
+use quick_xml::NsReader;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use std::io::Read;
+use std::str;
+use xml::common::TextPosition;
+use xml::escape::escape_str_attribute;
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// Tag names that are returned without allocating.
+const KNOWN_NAMES: &[&str] = &[
+    "methodResponse",
+    "params",
+    "param",
+    "fault",
+    "value",
+    "struct",
+    "member",
+    "name",
+    "array",
+    "data",
+    "string",
+    "int",
+    "i4",
+    "i8",
+    "boolean",
+    "double",
+    "dateTime.iso8601",
+    "base64",
+    "nil",
+];
+
+/// Reads a complete document from `reader` into memory, transcoding it to UTF-8.
+///
+/// `charset` is the character encoding of the document specified by the transport, if any.
+pub fn read_document<R: Read>(
+    reader: &mut R,
+    max_document_size: Option<u64>,
+    charset: Option<&str>,
+) -> ParseResult<String> {
+    let mut reader = LimitedReader::new(Utf8Reader::new(reader, charset)?, max_document_size);
+    let mut document = Vec::new();
+    match reader.read_to_end(&mut document) {
+        Ok(_) => {}
+        Err(_) if reader.limit_exceeded() => {
+            return Err(ParseError::DocumentTooLarge {
+                limit: max_document_size.unwrap_or(0),
+            })
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    String::from_utf8(document).map_err(|err| {
+        let valid = &err.as_bytes()[..err.utf8_error().valid_up_to()];
+        let valid = str::from_utf8(valid).unwrap();
+        xml_error(text_position(valid, valid.len()), "invalid UTF-8")
+    })
+}
+
+/// An `EventSource` using the quick-xml parser.
+pub struct QuickXmlSource<'a> {
+    document: &'a str,
+    reader: NsReader<&'a [u8]>,
+    /// Byte offset of the last returned event.
+    offset: usize,
+    /// An event (and its offset) that was read while looking for the end of a text node.
+    peeked: Option<(usize, XmlEvent<'a>)>,
+    /// Number of currently open elements.
+    depth: usize,
+    /// Whether the root element has been encountered.
+    seen_root: bool,
+    /// The last position computed by `text_position`, to avoid rescanning the whole document.
+    last_position: Cell<(usize, TextPosition)>,
+}
+
+impl<'a> QuickXmlSource<'a> {
+    /// Creates a new source parsing `document`.
+    pub fn new(document: &'a str) -> Self {
+        let mut reader = NsReader::from_str(document);
+        reader.config_mut().expand_empty_elements = true;
+        QuickXmlSource {
+            document,
+            reader,
+            offset: 0,
+            peeked: None,
+            depth: 0,
+            seen_root: false,
+            last_position: Cell::new((0, TextPosition::new())),
+        }
+    }
+
+    /// Reads the next event from the document, along with its byte offset.
+    fn read(&mut self) -> ParseResult<(usize, XmlEvent<'a>)> {
+        if let Some(peeked) = self.peeked.take() {
+            return Ok(peeked);
+        }
+
+        let offset = self.reader.buffer_position() as usize;
+        match self.reader.read_event() {
+            Ok(event) => Ok((offset, event)),
+            Err(err) => Err(self.error(self.reader.error_position() as usize, err)),
+        }
+    }
+
+    /// Converts a start tag to a `Name`, resolving its namespace.
+    fn name(&self, offset: usize, name: QName<'_>) -> ParseResult<Name<'a>> {
+        let namespace = match self.reader.resolve_element(name).0 {
+            ResolveResult::Bound(namespace) => Some(Cow::Owned(self.string(offset, namespace.0)?)),
+            ResolveResult::Unbound => None,
+            ResolveResult::Unknown(prefix) => {
+                let prefix = String::from_utf8_lossy(&prefix);
+                return Err(self.error(offset, format!("unbound namespace prefix '{}'", prefix)));
+            }
+        };
+        let local_name = self.string(offset, name.local_name().as_ref())?;
+        Ok(Name {
+            local_name: match KNOWN_NAMES.iter().find(|known| **known == local_name) {
+                Some(known) => Cow::Borrowed(*known),
+                None => Cow::Owned(local_name),
+            },
+            prefix: match name.prefix() {
+                Some(prefix) => Some(Cow::Owned(self.string(offset, prefix.as_ref())?)),
+                None => None,
+            },
+            namespace,
+        })
+    }
+
+    /// Returns whether a start tag has any attributes (apart from namespace declarations).
+    fn has_attributes(&self, offset: usize, start: &BytesStart<'_>) -> ParseResult<bool> {
+        for attribute in start.attributes() {
+            let attribute = attribute.map_err(|err| self.error(offset, err))?;
+            if attribute.key.as_namespace_binding().is_none() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns the namespace bindings currently in scope.
+    fn scope(&self) -> ParseResult<BTreeMap<String, String>> {
+        let mut scope = BTreeMap::new();
+        scope.insert(String::new(), String::new());
+        for (prefix, namespace) in self.reader.prefixes() {
+            let prefix = match prefix {
+                PrefixDeclaration::Default => String::new(),
+                PrefixDeclaration::Named(prefix) => self.string(self.offset, prefix)?,
+            };
+            scope.insert(prefix, self.string(self.offset, namespace.0)?);
+        }
+        Ok(scope)
+    }
+
+    fn string(&self, offset: usize, bytes: &[u8]) -> ParseResult<String> {
+        str::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|err| self.error(offset, err))
+    }
+
+    /// Converts a byte offset to a row and column, continuing from the last computed position if
+    /// possible.
+    fn text_position(&self, offset: usize) -> TextPosition {
+        let (last_offset, last_position) = self.last_position.get();
+        let position = if offset >= last_offset {
+            advance(
+                last_position,
+                &self.document[last_offset..floor(self.document, offset)],
+            )
+        } else {
+            text_position(self.document, offset)
+        };
+        self.last_position
+            .set((floor(self.document, offset), position));
+        position
+    }
+
+    fn error<E: Display>(&self, offset: usize, err: E) -> ParseError {
+        xml_error(self.text_position(offset), err)
+    }
+}
+
+impl<'a> EventSource<'a> for QuickXmlSource<'a> {
+    fn next_event(&mut self) -> ParseResult<Event<'a>> {
+        let mut text: Option<(usize, Cow<'a, str>)> = None;
+        loop {
+            let (offset, event) = self.read()?;
+            let data = match event {
+                XmlEvent::Text(ref data) => Some(data.unescape()),
+                XmlEvent::CData(ref data) => Some(data.decode().map_err(Into::into)),
+                _ => None,
+            };
+            if let Some(data) = data {
+                let data = data.map_err(|err| self.error(offset, err))?;
+                match text {
+                    Some((_, ref mut text)) => text.to_mut().push_str(&data),
+                    None => text = Some((offset, data)),
+                }
+                continue;
+            }
+
+            // Whitespace between tags is skipped, other text is returned before this event
+            if let Some((text_offset, text)) = text.take() {
+                if !text.bytes().all(|b| b" \t\r\n".contains(&b)) {
+                    if self.depth == 0 {
+                        return Err(self.error(text_offset, "text outside of the root element"));
+                    }
+                    self.peeked = Some((offset, event));
+                    self.offset = text_offset;
+                    return Ok(Event::Characters(text));
+                }
+            }
+
+            self.offset = offset;
+            return Ok(match event {
+                XmlEvent::Start(ref start) => {
+                    if self.depth == 0 && self.seen_root {
+                        return Err(self.error(offset, "multiple root elements"));
+                    }
+                    self.depth += 1;
+                    self.seen_root = true;
+                    Event::StartElement {
+                        name: self.name(offset, start.name())?,
+                        has_attributes: self.has_attributes(offset, start)?,
+                    }
+                }
+                XmlEvent::End(ref end) => {
+                    self.depth -= 1;
+                    Event::EndElement {
+                        name: self.name(offset, end.name())?,
+                    }
+                }
+                XmlEvent::Eof if self.depth > 0 || !self.seen_root => {
+                    return Err(self.error(offset, "unexpected end of document"));
+                }
+                XmlEvent::Eof => Event::EndDocument,
+                XmlEvent::Decl(_)
+                | XmlEvent::Comment(_)
+                | XmlEvent::PI(_)
+                | XmlEvent::DocType(_) => continue, // skip these
+                XmlEvent::Empty(_) | XmlEvent::Text(_) | XmlEvent::CData(_) => unreachable!(),
+            });
+        }
+    }
+
+    fn position(&self) -> TextPosition {
+        self.text_position(self.offset)
+    }
+
+    fn read_raw_element(&mut self) -> ParseResult<String> {
+        let mut xml = String::new();
+        let mut scopes = vec![self.scope()?];
+        loop {
+            let (offset, event) = self.read()?;
+            match event {
+                XmlEvent::Start(ref start) => {
+                    xml.push('<');
+                    xml.push_str(&self.string(offset, start.name().as_ref())?);
+                    let scope = self.scope()?;
+                    let parent = &scopes[scopes.len() - 1];
+                    for (prefix, uri) in &scope {
+                        if prefix == "xml" || prefix == "xmlns" || parent.get(prefix) == Some(uri) {
+                            continue;
+                        }
+                        write_namespace_declaration(&mut xml, prefix, uri);
+                    }
+                    for attribute in start.attributes() {
+                        let attribute = attribute.map_err(|err| self.error(offset, err))?;
+                        if attribute.key.as_namespace_binding().is_some() {
+                            continue;
+                        }
+                        let value = attribute
+                            .unescape_value()
+                            .map_err(|err| self.error(offset, err))?;
+                        write!(
+                            xml,
+                            " {}=\"{}\"",
+                            self.string(offset, attribute.key.as_ref())?,
+                            escape_str_attribute(&value)
+                        )
+                        .unwrap();
+                    }
+                    xml.push('>');
+                    scopes.push(scope);
+                }
+                XmlEvent::End(ref end) => {
+                    if scopes.len() == 1 {
+                        self.offset = offset;
+                        self.depth -= 1;
+                        return Ok(xml);
+                    }
+                    write!(xml, "</{}>", self.string(offset, end.name().as_ref())?).unwrap();
+                    scopes.pop();
+                }
+                XmlEvent::Text(ref data) => {
+                    let data = data.unescape().map_err(|err| self.error(offset, err))?;
+                    xml.push_str(&escape_xml(&data));
+                }
+                XmlEvent::CData(ref data) => {
+                    let data = data.decode().map_err(|err| self.error(offset, err))?;
+                    xml.push_str(&escape_xml(&data));
+                }
+                XmlEvent::PI(ref pi) => {
+                    let target = self.string(offset, pi.target())?;
+                    let content = self.string(offset, pi.content())?;
+                    let content = content.trim_start();
+                    if content.is_empty() {
+                        write!(xml, "<?{}?>", target).unwrap();
+                    } else {
+                        write!(xml, "<?{} {}?>", target, content).unwrap();
+                    }
+                }
+                XmlEvent::Decl(_) | XmlEvent::Comment(_) | XmlEvent::DocType(_) => {}
+                XmlEvent::Eof => return Err(self.error(offset, "unexpected end of document")),
+                XmlEvent::Empty(_) => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Converts a byte offset into `document` to a row and column.
+fn text_position(document: &str, offset: usize) -> TextPosition {
+    advance(TextPosition::new(), &document[..floor(document, offset)])
+}
+
+/// Returns the position after `text`, which starts at `position`.
+fn advance(mut position: TextPosition, text: &str) -> TextPosition {
+    match text.rfind('\n') {
+        Some(last_line) => {
+            position.row += text.matches('\n').count() as u64;
+            position.column = text[last_line + 1..].chars().count() as u64;
+        }
+        None => position.column += text.chars().count() as u64,
+    }
+    position
+}
+
+/// Rounds `offset` down to the closest character boundary in `document`.
+fn floor(document: &str, offset: usize) -> usize {
+    let mut offset = offset.min(document.len());
+    while !document.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+fn xml_error<E: Display>(position: TextPosition, err: E) -> ParseError {
+    ParseError::XmlError((&position, err.to_string()).into())
+}
@@ -1,13 +1,24 @@
 #[cfg(feature = "http")]
 extern crate reqwest;
 
-use error::{Error, RequestErrorKind};
-use parser::parse_response;
+#[cfg(feature = "serde")]
+use de::deserialize_response_with_charset;
+use encoding::charset_from_content_type;
+use error::{Error, ParseError, RequestErrorKind};
+use parser::{parse_response_with_charset, ParserOptions};
+#[cfg(feature = "serde")]
+use ser::{to_value, SerializeError};
+use stream::ResponseStream;
 use transport::Transport;
-use utils::escape_xml;
-use Value;
+use utils::{escape_xml, RecordingReader};
+use writer::{write_document, WriterOptions, XmlDocument, XmlWriter};
+use {Fault, Map, RawResponse, Value};
+
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use serde::ser::Serialize;
 
-use std::collections::BTreeMap;
 use std::io::{self, Write};
 
 /// A request to call a procedure.
@@ -15,6 +26,8 @@ use std::io::{self, Write};
 pub struct Request<'a> {
     name: &'a str,
     args: Vec<Value>,
+    parser_options: ParserOptions,
+    writer_options: WriterOptions,
 }
 
 impl<'a> Request<'a> {
@@ -25,6 +38,8 @@ impl<'a> Request<'a> {
         Request {
             name,
             args: Vec::new(),
+            parser_options: ParserOptions::default(),
+            writer_options: WriterOptions::default(),
         }
     }
 
@@ -45,7 +60,7 @@ impl<'a> Request<'a> {
                 requests
                     .into_iter()
                     .map(|req| {
-                        let mut multicall_struct: BTreeMap<String, Value> = BTreeMap::new();
+                        let mut multicall_struct = Map::new();
 
                         multicall_struct.insert("methodName".into(), req.name.into());
                         multicall_struct.insert("params".into(), Value::Array(req.args.clone()));
@@ -54,6 +69,8 @@ impl<'a> Request<'a> {
                     })
                     .collect(),
             )],
+            parser_options: ParserOptions::default(),
+            writer_options: WriterOptions::default(),
         }
     }
 
@@ -63,6 +80,54 @@ impl<'a> Request<'a> {
         self
     }
 
+    /// Serializes `value` and appends it to the list of arguments.
+    ///
+    /// Refer to [`serialize_value`] for how Rust types are mapped to XML-RPC values.
+    ///
+    /// This method is only available when the `serde` feature is enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `value` can not be represented as an XML-RPC value.
+    ///
+    /// [`serialize_value`]: fn.serialize_value.html
+    #[cfg(feature = "serde")]
+    pub fn serialize_arg<T: Serialize + ?Sized>(
+        mut self,
+        value: &T,
+    ) -> Result<Self, SerializeError> {
+        self.args.push(to_value(value)?);
+        Ok(self)
+    }
+
+    /// Returns the arguments that will be passed to the method.
+    ///
+    /// This can be used to check the arguments against a [`Signature`] before calling the method.
+    ///
+    /// [`Signature`]: struct.Signature.html
+    pub fn args(&self) -> &[Value] {
+        &self.args
+    }
+
+    /// Sets the options used to parse the server's response.
+    pub fn parser_options(mut self, options: ParserOptions) -> Self {
+        self.parser_options = options;
+        self
+    }
+
+    /// Sets the options used to format this request as XML.
+    pub fn writer_options(mut self, options: WriterOptions) -> Self {
+        self.writer_options = options;
+        self
+    }
+
+    /// Returns the name of the character encoding this request is written in.
+    ///
+    /// Transports should specify this as the `charset` of the `Content-Type`.
+    pub fn charset(&self) -> &'static str {
+        self.writer_options.get_encoding().name()
+    }
+
     /// Performs the request using a [`Transport`].
     ///
     /// If you want to send the request using an HTTP POST request, you can also use [`call_url`],
@@ -74,19 +139,116 @@ impl<'a> Request<'a> {
     /// the caller. Additionally, if the response is malformed (invalid XML), or indicates that the
     /// method call failed, an error will also be returned.
     ///
+    /// If the response could not be parsed, the start of the response body (and any metadata
+    /// provided by the [`Transport`]) is available via [`Error::raw_response`].
+    ///
     /// [`call_url`]: #method.call_url
     /// [`Transport`]: trait.Transport.html
+    /// [`Error::raw_response`]: struct.Error.html#method.raw_response
     pub fn call<T: Transport>(&self, transport: T) -> Result<Value, Error> {
-        let mut reader = transport
-            .transmit(self)
-            .map_err(RequestErrorKind::TransportError)?;
+        self.receive(transport, parse_response_with_charset)
+    }
+
+    /// Performs the request using a [`Transport`], deserializing the returned value into a `D`.
+    ///
+    /// The value is deserialized directly from the response, without building a [`Value`] first.
+    /// Refer to [`deserialize_response`] for how XML-RPC values are mapped to Rust types.
+    ///
+    /// This method is only available when the `serde` feature is enabled.
+    ///
+    /// # Errors
+    ///
+    /// The same error conditions as for [`call`] apply. Additionally, an error is returned if the
+    /// value can't be deserialized into a `D`.
+    ///
+    /// [`Transport`]: trait.Transport.html
+    /// [`Value`]: enum.Value.html
+    /// [`deserialize_response`]: fn.deserialize_response.html
+    /// [`call`]: #method.call
+    #[cfg(feature = "serde")]
+    pub fn call_deserialize<T: Transport, D: DeserializeOwned>(
+        &self,
+        transport: T,
+    ) -> Result<D, Error> {
+        self.receive(transport, deserialize_response_with_charset)
+    }
+
+    /// Performs the request using a [`Transport`], decoding the response incrementally.
+    ///
+    /// This returns as soon as the start of the response has been received. The elements of a
+    /// returned array (or the members of a returned struct) can then be processed one at a time
+    /// while the rest of the response is still being read. Refer to [`ResponseStream`] for details.
+    ///
+    /// # Errors
+    ///
+    /// The same error conditions as for [`call`] apply, except that errors in the remainder of the
+    /// response are returned by the [`ResponseStream`]. No [`Error::raw_response`] is attached to
+    /// parse errors.
+    ///
+    /// [`Transport`]: trait.Transport.html
+    /// [`ResponseStream`]: struct.ResponseStream.html
+    /// [`call`]: #method.call
+    /// [`Error::raw_response`]: struct.Error.html#method.raw_response
+    pub fn call_streaming<T: Transport>(
+        &self,
+        transport: T,
+    ) -> Result<ResponseStream<T::Stream>, Error> {
+        let (stream, _, charset) = self.transmit(transport)?;
+        ResponseStream::with_charset(stream, &self.parser_options, charset.as_deref())
+    }
 
-        let response = parse_response(&mut reader).map_err(RequestErrorKind::ParseError)?;
+    /// Sends this request using `transport` and parses the response using `parse`.
+    ///
+    /// The start of the response body is attached to parse errors.
+    fn receive<T, F, V>(&self, transport: T, parse: F) -> Result<V, Error>
+    where
+        T: Transport,
+        F: FnOnce(
+            &mut RecordingReader<T::Stream>,
+            &ParserOptions,
+            Option<&str>,
+        ) -> Result<Result<V, Fault>, ParseError>,
+    {
+        let (stream, raw, charset) = self.transmit(transport)?;
+        let mut reader = RecordingReader::new(stream, RawResponse::MAX_BODY_LEN);
+
+        let response = match parse(&mut reader, &self.parser_options, charset.as_deref()) {
+            Ok(response) => response,
+            Err(err) => {
+                let (body, body_truncated) = reader.finish();
+                return Err(
+                    Error::from(RequestErrorKind::ParseError(err)).with_response(RawResponse {
+                        body,
+                        body_truncated,
+                        ..raw
+                    }),
+                );
+            }
+        };
 
         let value = response.map_err(RequestErrorKind::Fault)?;
         Ok(value)
     }
 
+    /// Sends this request using `transport`, returning the response stream, its metadata and the
+    /// character encoding specified by the transport (if any).
+    fn transmit<T: Transport>(
+        &self,
+        transport: T,
+    ) -> Result<(T::Stream, RawResponse, Option<String>), Error> {
+        let stream = transport
+            .transmit(self)
+            .map_err(RequestErrorKind::TransportError)?;
+        let raw = T::describe_response(&stream);
+        let charset = raw
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .and_then(|(_, value)| charset_from_content_type(value))
+            .map(str::to_string);
+        Ok((stream, raw, charset))
+    }
+
     /// Performs the request on a URL.
     ///
     /// You can pass a `&str` or an already parsed reqwest URL.
@@ -114,29 +276,20 @@ impl<'a> Request<'a> {
         self.call(reqwest::blocking::Client::new().post(url))
     }
 
-    /// Formats this `Request` as a UTF-8 encoded XML document.
+    /// Formats this `Request` as an XML document.
+    ///
+    /// The document is UTF-8 encoded, unless a different encoding was selected using
+    /// [`writer_options`].
     ///
     /// # Errors
     ///
-    /// Any errors reported by the writer will be propagated to the caller. If the writer never
-    /// returns an error, neither will this method.
+    /// Any errors reported by the writer will be propagated to the caller. An error of kind
+    /// `InvalidData` is returned if an argument can't be written (see [`Value::write_as_xml`]).
+    ///
+    /// [`writer_options`]: #method.writer_options
+    /// [`Value::write_as_xml`]: enum.Value.html#method.write_as_xml
     pub fn write_as_xml<W: Write>(&self, fmt: &mut W) -> io::Result<()> {
-        writeln!(fmt, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
-        writeln!(fmt, r#"<methodCall>"#)?;
-        writeln!(
-            fmt,
-            r#"<methodName>{}</methodName>"#,
-            escape_xml(&self.name)
-        )?;
-        writeln!(fmt, r#"<params>"#)?;
-        for value in &self.args {
-            writeln!(fmt, r#"<param>"#)?;
-            value.write_as_xml(fmt)?;
-            writeln!(fmt, r#"</param>"#)?;
-        }
-        writeln!(fmt, r#"</params>"#)?;
-        write!(fmt, r#"</methodCall>"#)?;
-        Ok(())
+        write_document(fmt, self, &self.writer_options)
     }
 
     /// Serialize this `Request` into an XML-RPC struct that can be passed to
@@ -147,7 +300,7 @@ impl<'a> Request<'a> {
     /// * `params`: the request arguments
     #[deprecated(since = "0.11.2", note = "use `Request::multicall` instead")]
     pub fn into_multicall_struct(self) -> Value {
-        let mut multicall_struct: BTreeMap<String, Value> = BTreeMap::new();
+        let mut multicall_struct = Map::new();
 
         multicall_struct.insert("methodName".into(), self.name.into());
         multicall_struct.insert("params".into(), Value::Array(self.args));
@@ -156,9 +309,28 @@ impl<'a> Request<'a> {
     }
 }
 
+impl<'a> XmlDocument for Request<'a> {
+    fn write_root<W: Write>(&self, writer: &mut XmlWriter<'_, W>) -> io::Result<()> {
+        writer.open(&["methodCall"])?;
+        writer.line(format_args!(
+            "<methodName>{}</methodName>",
+            escape_xml(self.name)
+        ))?;
+        writer.open(&["params"])?;
+        for value in &self.args {
+            writer.open(&["param"])?;
+            value.visit(writer)?;
+            writer.close(&["param"])?;
+        }
+        writer.close(&["params"])?;
+        writer.close_root("methodCall")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::error;
     use std::str;
 
     #[test]
@@ -171,4 +343,56 @@ mod tests {
             .unwrap()
             .contains("<methodName>x&lt;&amp;x</methodName>"));
     }
+
+    #[test]
+    fn writes_selected_encoding() {
+        let mut output: Vec<u8> = Vec::new();
+        let req = Request::new("grüße")
+            .arg("€ ✓")
+            .writer_options(WriterOptions::new().encoding(encoding_rs::WINDOWS_1252));
+
+        req.write_as_xml(&mut output).unwrap();
+        assert!(output.starts_with(b"<?xml version=\"1.0\" encoding=\"windows-1252\"?>\n"));
+        assert!(contains(&output, b"<methodName>gr\xfc\xdfe</methodName>"));
+        assert!(contains(&output, b"<string>\x80 &#10003;</string>"));
+        assert_eq!(req.charset(), "windows-1252");
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[test]
+    fn attaches_raw_response_to_parse_errors() {
+        struct HtmlTransport;
+
+        impl Transport for HtmlTransport {
+            type Stream = &'static [u8];
+
+            fn transmit(
+                self,
+                _request: &Request<'_>,
+            ) -> Result<Self::Stream, Box<dyn error::Error + Send + Sync>> {
+                Ok(b"<html><body>502 Bad Gateway</body></html>")
+            }
+
+            fn describe_response(_stream: &Self::Stream) -> RawResponse {
+                RawResponse {
+                    status: Some(200),
+                    ..RawResponse::default()
+                }
+            }
+        }
+
+        let err = Request::new("x").call(HtmlTransport).unwrap_err();
+        let raw = err.raw_response().expect("no raw response attached");
+        assert_eq!(raw.status, Some(200));
+        assert_eq!(
+            raw.body_lossy(),
+            "<html><body>502 Bad Gateway</body></html>"
+        );
+        assert!(!raw.body_truncated);
+    }
 }
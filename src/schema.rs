@@ -0,0 +1,701 @@
+//! Validation of values against expected types.
+
+use path::Location;
+use {Fault, Value};
+
+use std::error;
+use std::fmt;
+
+/// The XML-RPC scalar types, named after their XML elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ScalarType {
+    /// A 32-bit integer (`<i4>` or `<int>`, `Value::Int`).
+    I4,
+    /// A 64-bit integer (`<i8>`, `Value::Int64`).
+    I8,
+    /// A boolean (`<boolean>`, `Value::Bool`).
+    Boolean,
+    /// A string (`<string>`, `Value::String`).
+    String,
+    /// A double (`<double>`, `Value::Double`).
+    Double,
+    /// A date/time (`<dateTime.iso8601>`, `Value::DateTime`).
+    DateTime,
+    /// Binary data (`<base64>`, `Value::Base64`).
+    Base64,
+    /// The nil value (`<nil/>`, `Value::Nil`).
+    Nil,
+    /// An arbitrary-precision integer (`<ex:biginteger>`, `Value::BigInteger`).
+    BigInteger,
+    /// An arbitrary-precision decimal (`<ex:bigdecimal>`, `Value::BigDecimal`).
+    BigDecimal,
+    /// A serialized Java object (`<ex:serializable>`, `Value::Serializable`).
+    Serializable,
+    /// A DOM node (`<ex:dom>`, `Value::Dom`).
+    Dom,
+}
+
+impl ScalarType {
+    const ALL: [ScalarType; 12] = [
+        ScalarType::I4,
+        ScalarType::I8,
+        ScalarType::Boolean,
+        ScalarType::String,
+        ScalarType::Double,
+        ScalarType::DateTime,
+        ScalarType::Base64,
+        ScalarType::Nil,
+        ScalarType::BigInteger,
+        ScalarType::BigDecimal,
+        ScalarType::Serializable,
+        ScalarType::Dom,
+    ];
+
+    /// Returns the name of the XML element of this type, such as `i4` or `dateTime.iso8601`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ScalarType::I4 => "i4",
+            ScalarType::I8 => "i8",
+            ScalarType::Boolean => "boolean",
+            ScalarType::String => "string",
+            ScalarType::Double => "double",
+            ScalarType::DateTime => "dateTime.iso8601",
+            ScalarType::Base64 => "base64",
+            ScalarType::Nil => "nil",
+            ScalarType::BigInteger => "ex:biginteger",
+            ScalarType::BigDecimal => "ex:bigdecimal",
+            ScalarType::Serializable => "ex:serializable",
+            ScalarType::Dom => "ex:dom",
+        }
+    }
+
+    /// Returns the type with the given XML element name, also accepting `int` for `i4`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name == "int" {
+            return Some(ScalarType::I4);
+        }
+        Self::ALL.iter().cloned().find(|ty| ty.name() == name)
+    }
+}
+
+/// The expected type of a value.
+///
+/// Schemas describe the parameters and results of methods. They can be used to check arguments
+/// before calling a method, or the parameters of an incoming call before dispatching it (see
+/// [`Signature`]).
+///
+/// # Examples
+///
+/// ```
+/// # use xmlrpc::{ScalarType, Schema, StructSchema, Value};
+/// let schema = Schema::Struct(
+///     StructSchema::new()
+///         .required("name", ScalarType::String)
+///         .optional("tags", Schema::array_of(ScalarType::String)),
+/// );
+///
+/// let value = Value::Struct(vec![
+///     ("tags".to_string(), Value::Array(vec![Value::from("a"), Value::Int(1)])),
+/// ].into_iter().collect());
+///
+/// let err = schema.validate(&value).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "`name` is missing; `tags[1]` is of type `i4`, expected `string`"
+/// );
+/// ```
+///
+/// [`Signature`]: struct.Signature.html
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Schema {
+    /// Accepts any value.
+    Any,
+    /// Accepts values of a scalar type.
+    Scalar(ScalarType),
+    /// Accepts arrays whose elements all match the schema.
+    Array(Box<Schema>),
+    /// Accepts structs with the described members.
+    Struct(StructSchema),
+    /// Accepts `Value::Nil` as well as values matching the schema.
+    Nullable(Box<Schema>),
+}
+
+impl Schema {
+    /// Creates a schema for arrays whose elements match `element`.
+    pub fn array_of<S: Into<Schema>>(element: S) -> Self {
+        Schema::Array(Box::new(element.into()))
+    }
+
+    /// Creates a schema accepting `Value::Nil` as well as values matching `schema`.
+    pub fn nullable<S: Into<Schema>>(schema: S) -> Self {
+        Schema::Nullable(Box::new(schema.into()))
+    }
+
+    /// Creates a schema from a type name as returned by the `system.methodSignature`
+    /// introspection method.
+    ///
+    /// Scalar types are named as in [`ScalarType::from_name`]. `array` and `struct` accept any
+    /// array or struct, and `undef` (used by some servers for untyped values) accepts any value.
+    /// Returns `None` for unknown names.
+    ///
+    /// [`ScalarType::from_name`]: enum.ScalarType.html#method.from_name
+    pub fn from_type_name(name: &str) -> Option<Self> {
+        match name {
+            "array" => Some(Schema::array_of(Schema::Any)),
+            "struct" => Some(Schema::Struct(StructSchema::new())),
+            "undef" => Some(Schema::Any),
+            _ => ScalarType::from_name(name).map(Schema::Scalar),
+        }
+    }
+
+    /// Checks that `value` matches this schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns all violations of the schema, each with the path of the offending value.
+    pub fn validate(&self, value: &Value) -> Result<(), ValidationError> {
+        let mut validator = Validator::default();
+        validator.check(&Location::default(), self, value);
+        validator.finish()
+    }
+
+    /// Returns whether the type of `value` matches, without looking at nested values.
+    fn type_matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Schema::Any, _) => true,
+            (Schema::Scalar(ty), _) => ty.name() == value.type_name(),
+            (Schema::Array(_), Value::Array(_)) | (Schema::Struct(_), Value::Struct(_)) => true,
+            (Schema::Array(_), _) | (Schema::Struct(_), _) => false,
+            (Schema::Nullable(schema), _) => *value == Value::Nil || schema.type_matches(value),
+        }
+    }
+}
+
+impl From<ScalarType> for Schema {
+    fn from(ty: ScalarType) -> Self {
+        Schema::Scalar(ty)
+    }
+}
+
+impl From<StructSchema> for Schema {
+    fn from(schema: StructSchema) -> Self {
+        Schema::Struct(schema)
+    }
+}
+
+/// Describes the expected type, such as `i4`, `array of string` or `struct or nil`.
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Schema::Any => f.write_str("any"),
+            Schema::Scalar(ty) => f.write_str(ty.name()),
+            Schema::Array(ref element) => write!(f, "array of {}", element),
+            Schema::Struct(_) => f.write_str("struct"),
+            Schema::Nullable(ref schema) => write!(f, "{} or nil", schema),
+        }
+    }
+}
+
+/// The expected members of a struct.
+///
+/// Members that aren't described are allowed unless [`deny_unknown_members`] is enabled.
+///
+/// [`deny_unknown_members`]: #method.deny_unknown_members
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StructSchema {
+    members: Vec<MemberSchema>,
+    deny_unknown_members: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct MemberSchema {
+    name: String,
+    schema: Schema,
+    required: bool,
+}
+
+impl StructSchema {
+    /// Creates a schema for structs without any required members.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a member that must be present and match `schema`.
+    pub fn required<N: Into<String>, S: Into<Schema>>(self, name: N, schema: S) -> Self {
+        self.member(name.into(), schema.into(), true)
+    }
+
+    /// Adds a member that may be absent, but must match `schema` if present.
+    pub fn optional<N: Into<String>, S: Into<Schema>>(self, name: N, schema: S) -> Self {
+        self.member(name.into(), schema.into(), false)
+    }
+
+    fn member(mut self, name: String, schema: Schema, required: bool) -> Self {
+        self.members.retain(|member| member.name != name);
+        self.members.push(MemberSchema {
+            name,
+            schema,
+            required,
+        });
+        self
+    }
+
+    /// Sets whether members that haven't been added to the schema are rejected.
+    ///
+    /// Disabled by default.
+    pub fn deny_unknown_members(mut self, deny: bool) -> Self {
+        self.deny_unknown_members = deny;
+        self
+    }
+}
+
+/// The types of the parameters and the result of a method.
+///
+/// # Examples
+///
+/// Checking the arguments of a request before sending it:
+///
+/// ```
+/// # use xmlrpc::{Request, ScalarType, Signature};
+/// let signature = Signature::new(vec![ScalarType::I4.into(), ScalarType::I4.into()])
+///     .returns(ScalarType::I4);
+///
+/// let request = Request::new("add").arg(1).arg("2");
+/// let err = signature.validate_params(request.args()).unwrap_err();
+/// assert_eq!(err.to_string(), "`[1]` is of type `string`, expected `i4`");
+/// ```
+///
+/// On the server side, the error can be converted to an `invalid_params` fault with
+/// `Fault::from(&err)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    params: Vec<Schema>,
+    result: Schema,
+}
+
+impl Signature {
+    /// Creates a signature for a method taking parameters matching `params`, in order.
+    ///
+    /// The result may be any value, unless set with [`returns`].
+    ///
+    /// [`returns`]: #method.returns
+    pub fn new(params: Vec<Schema>) -> Self {
+        Signature {
+            params,
+            result: Schema::Any,
+        }
+    }
+
+    /// Sets the schema of the method's result.
+    pub fn returns<S: Into<Schema>>(mut self, result: S) -> Self {
+        self.result = result.into();
+        self
+    }
+
+    /// Creates a signature from one of the signatures returned by the `system.methodSignature`
+    /// introspection method: an array of type names, starting with the type of the result.
+    ///
+    /// Returns `None` if `signature` is not a non-empty array of known type names (see
+    /// [`Schema::from_type_name`]).
+    ///
+    /// [`Schema::from_type_name`]: enum.Schema.html#method.from_type_name
+    pub fn from_method_signature(signature: &Value) -> Option<Self> {
+        let mut schemas = signature
+            .as_array()?
+            .iter()
+            .map(|name| Schema::from_type_name(name.as_str()?))
+            .collect::<Option<Vec<_>>>()?;
+        if schemas.is_empty() {
+            return None;
+        }
+        let result = schemas.remove(0);
+        Some(Signature::new(schemas).returns(result))
+    }
+
+    /// Returns the schemas of the parameters.
+    pub fn params(&self) -> &[Schema] {
+        &self.params
+    }
+
+    /// Returns the schema of the result.
+    pub fn result(&self) -> &Schema {
+        &self.result
+    }
+
+    /// Checks that `params` match the parameters of this signature.
+    ///
+    /// The paths of violations start with the index of the parameter, as in `[0].name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns all violations, including a wrong number of parameters.
+    pub fn validate_params(&self, params: &[Value]) -> Result<(), ValidationError> {
+        let mut validator = Validator::default();
+        let root = Location::default();
+        if params.len() != self.params.len() {
+            validator.report(
+                &root,
+                ViolationKind::ParamCount {
+                    expected: self.params.len(),
+                    found: params.len(),
+                },
+            );
+        }
+        for (index, (schema, param)) in self.params.iter().zip(params).enumerate() {
+            validator.check(&root.element(index), schema, param);
+        }
+        validator.finish()
+    }
+
+    /// Checks that `result` matches the result of this signature.
+    ///
+    /// # Errors
+    ///
+    /// Returns all violations of the result schema.
+    pub fn validate_result(&self, result: &Value) -> Result<(), ValidationError> {
+        self.result.validate(result)
+    }
+}
+
+#[derive(Default)]
+struct Validator {
+    violations: Vec<Violation>,
+}
+
+impl Validator {
+    fn report(&mut self, location: &Location, kind: ViolationKind) {
+        self.violations.push(Violation {
+            location: location.clone(),
+            kind,
+        });
+    }
+
+    fn check(&mut self, location: &Location, schema: &Schema, value: &Value) {
+        if !schema.type_matches(value) {
+            let kind = ViolationKind::WrongType {
+                expected: schema.to_string(),
+                found: value.type_name(),
+            };
+            return self.report(location, kind);
+        }
+
+        match (schema, value) {
+            (Schema::Nullable(schema), _) if *value != Value::Nil => {
+                self.check(location, schema, value)
+            }
+            (Schema::Array(element), Value::Array(array)) => {
+                for (index, value) in array.iter().enumerate() {
+                    self.check(&location.element(index), element, value);
+                }
+            }
+            (Schema::Struct(schema), Value::Struct(map)) => {
+                for member in &schema.members {
+                    match map.get(&member.name) {
+                        Some(value) => {
+                            self.check(&location.member(&member.name), &member.schema, value)
+                        }
+                        None if member.required => {
+                            self.report(&location.member(&member.name), ViolationKind::Missing)
+                        }
+                        None => {}
+                    }
+                }
+                if schema.deny_unknown_members {
+                    for name in map.keys() {
+                        if !schema.members.iter().any(|member| member.name == *name) {
+                            self.report(&location.member(name), ViolationKind::Unknown);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self) -> Result<(), ValidationError> {
+        if self.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError {
+                violations: self.violations,
+            })
+        }
+    }
+}
+
+/// A value not matching a schema, found by [`Schema::validate`] or [`Signature`].
+///
+/// [`Schema::validate`]: enum.Schema.html#method.validate
+/// [`Signature`]: struct.Signature.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    location: Location,
+    kind: ViolationKind,
+}
+
+impl Violation {
+    /// Returns the path of the offending value, in the syntax accepted by [`Value::get_path`].
+    ///
+    /// [`Value::get_path`]: enum.Value.html#method.get_path
+    pub fn path(&self) -> &str {
+        &self.location.path
+    }
+
+    /// Returns the location of the offending value as a JSON Pointer (see [`Value::pointer`]).
+    ///
+    /// [`Value::pointer`]: enum.Value.html#method.pointer
+    pub fn pointer(&self) -> &str {
+        &self.location.pointer
+    }
+
+    /// Returns how the value violates the schema.
+    pub fn kind(&self) -> &ViolationKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = &self.location;
+        match self.kind {
+            ViolationKind::WrongType {
+                ref expected,
+                found,
+            } => write!(
+                f,
+                "{} is of type `{}`, expected `{}`",
+                location, found, expected
+            ),
+            ViolationKind::Missing => write!(f, "{} is missing", location),
+            ViolationKind::Unknown => write!(f, "{} is not an allowed member", location),
+            ViolationKind::ParamCount { expected, found } => {
+                write!(f, "expected {} parameters, found {}", expected, found)
+            }
+        }
+    }
+}
+
+/// The ways in which a value can violate a schema.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ViolationKind {
+    /// The value has the wrong type.
+    WrongType {
+        /// A description of the expected type, such as `i4` or `array of string`.
+        expected: String,
+        /// The XML-RPC type of the value, such as `i4` or `struct`.
+        found: &'static str,
+    },
+    /// A required struct member is missing.
+    Missing,
+    /// The struct member is not part of a schema that denies unknown members.
+    Unknown,
+    /// The number of method parameters is wrong.
+    ParamCount {
+        /// The number of parameters in the signature.
+        expected: usize,
+        /// The number of parameters passed.
+        found: usize,
+    },
+}
+
+/// The error returned when a value doesn't match a schema, containing all violations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    violations: Vec<Violation>,
+}
+
+impl ValidationError {
+    /// Returns the violations, of which there is at least one.
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+}
+
+impl fmt::Display for ValidationError {
+    /// Writes all violations, separated by semicolons.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, violation) in self.violations.iter().enumerate() {
+            if index > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ValidationError {}
+
+/// Reports invalid parameters of an incoming call as [`Fault::INVALID_PARAMS`].
+///
+/// [`Fault::INVALID_PARAMS`]: struct.Fault.html#associatedconstant.INVALID_PARAMS
+impl<'a> From<&'a ValidationError> for Fault {
+    fn from(err: &'a ValidationError) -> Self {
+        Fault::invalid_params(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Map;
+
+    fn person_schema() -> Schema {
+        Schema::Struct(
+            StructSchema::new()
+                .required("name", ScalarType::String)
+                .required("age", ScalarType::I4)
+                .optional("email", Schema::nullable(ScalarType::String))
+                .optional(
+                    "children",
+                    Schema::array_of(StructSchema::new().required("name", ScalarType::String)),
+                )
+                .deny_unknown_members(true),
+        )
+    }
+
+    fn person(members: Vec<(&str, Value)>) -> Value {
+        Value::Struct(
+            members
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect::<Map>(),
+        )
+    }
+
+    #[test]
+    fn accepts_matching_values() {
+        let schema = person_schema();
+        let value = person(vec![
+            ("name", Value::from("John")),
+            ("age", Value::Int(37)),
+            ("email", Value::Nil),
+            (
+                "children",
+                Value::Array(vec![person(vec![("name", Value::from("Mark"))])]),
+            ),
+        ]);
+        assert_eq!(schema.validate(&value), Ok(()));
+        assert_eq!(Schema::Any.validate(&value), Ok(()));
+    }
+
+    #[test]
+    fn reports_all_violations() {
+        let value = person(vec![
+            ("name", Value::Int(1)),
+            ("email", Value::Bool(false)),
+            (
+                "children",
+                Value::Array(vec![
+                    person(vec![("name", Value::from("Mark"))]),
+                    person(vec![("age", Value::Int(3))]),
+                ]),
+            ),
+            ("a/b", Value::Nil),
+        ]);
+        let err = person_schema().validate(&value).unwrap_err();
+        let mut violations = err
+            .violations()
+            .iter()
+            .map(|v| (v.path(), v.pointer(), v.to_string()))
+            .collect::<Vec<_>>();
+        violations.sort();
+        assert_eq!(
+            violations,
+            [
+                ("a/b", "/a~1b", "`a/b` is not an allowed member".to_string()),
+                ("age", "/age", "`age` is missing".to_string()),
+                (
+                    "children[1].name",
+                    "/children/1/name",
+                    "`children[1].name` is missing".to_string()
+                ),
+                (
+                    "email",
+                    "/email",
+                    "`email` is of type `boolean`, expected `string or nil`".to_string()
+                ),
+                (
+                    "name",
+                    "/name",
+                    "`name` is of type `i4`, expected `string`".to_string()
+                ),
+            ]
+        );
+
+        let err = person_schema().validate(&Value::Nil).unwrap_err();
+        assert_eq!(
+            err.violations()[0].kind(),
+            &ViolationKind::WrongType {
+                expected: "struct".to_string(),
+                found: "nil",
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "the value is of type `nil`, expected `struct`"
+        );
+    }
+
+    #[test]
+    fn validates_signatures() {
+        let signature = Signature::new(vec![
+            ScalarType::String.into(),
+            Schema::array_of(ScalarType::I8),
+        ])
+        .returns(ScalarType::Boolean);
+
+        let params = [
+            Value::from("a"),
+            Value::Array(vec![Value::Int64(1), Value::Int(2)]),
+        ];
+        let err = signature.validate_params(&params).unwrap_err();
+        assert_eq!(err.to_string(), "`[1][1]` is of type `i4`, expected `i8`");
+        assert_eq!(err.violations()[0].pointer(), "/1/1");
+
+        let err = signature.validate_params(&params[..1]).unwrap_err();
+        assert_eq!(err.to_string(), "expected 2 parameters, found 1");
+
+        assert_eq!(signature.validate_result(&Value::Bool(true)), Ok(()));
+        assert!(signature.validate_result(&Value::Int(1)).is_err());
+
+        let fault = Fault::from(&err);
+        assert_eq!(fault.fault_code, Fault::INVALID_PARAMS);
+        assert_eq!(fault.fault_string, "expected 2 parameters, found 1");
+    }
+
+    #[test]
+    fn parses_method_signatures() {
+        let signature = Value::Array(vec![
+            Value::from("struct"),
+            Value::from("int"),
+            Value::from("dateTime.iso8601"),
+            Value::from("array"),
+        ]);
+        let signature = Signature::from_method_signature(&signature).unwrap();
+        assert_eq!(signature.result(), &Schema::Struct(StructSchema::new()));
+        assert_eq!(
+            signature.params(),
+            [
+                Schema::Scalar(ScalarType::I4),
+                Schema::Scalar(ScalarType::DateTime),
+                Schema::array_of(Schema::Any),
+            ]
+        );
+
+        assert_eq!(
+            Signature::from_method_signature(&Value::Array(vec![Value::from("float")])),
+            None
+        );
+        assert_eq!(
+            Signature::from_method_signature(&Value::Array(Vec::new())),
+            None
+        );
+        for ty in &ScalarType::ALL {
+            assert_eq!(ScalarType::from_name(ty.name()), Some(*ty));
+        }
+    }
+}
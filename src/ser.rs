@@ -0,0 +1,712 @@
+//! Serialization of values with serde.
+//!
+//! The serializer drives a `ValueVisitor`, so values can be written as XML directly (using
+//! `XmlWriter`) or collected into a `Value` (using `ValueBuilder`).
+
+use error::ParseError;
+use visitor::{ValueBuilder, ValueVisitor};
+use writer::{WriterOptions, XmlWriter};
+use Value;
+
+use serde::ser::{self, Serialize};
+use std::convert::TryFrom;
+use std::error;
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+
+/// An error that occurred while serializing a value.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SerializeError {
+    /// The value could not be written.
+    Io(io::Error),
+    /// The value can not be represented in XML-RPC (for example, because it contains a map with
+    /// non-string keys), or its `Serialize` implementation failed.
+    Message(String),
+}
+
+impl Display for SerializeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SerializeError::Io(ref err) => write!(fmt, "failed to write value: {}", err),
+            SerializeError::Message(ref message) => fmt.write_str(message),
+        }
+    }
+}
+
+impl error::Error for SerializeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            SerializeError::Io(ref err) => Some(err),
+            SerializeError::Message(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SerializeError {
+    fn from(err: io::Error) -> Self {
+        SerializeError::Io(err)
+    }
+}
+
+// Only reported by `ValueBuilder` for unbalanced events, which the serializer doesn't produce
+impl From<ParseError> for SerializeError {
+    fn from(err: ParseError) -> Self {
+        SerializeError::Message(err.to_string())
+    }
+}
+
+impl ser::Error for SerializeError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SerializeError::Message(msg.to_string())
+    }
+}
+
+/// Serializes a value as an XML-RPC `<value>` element and writes it to `writer`.
+///
+/// This produces the same XML as [`Value::write_as_xml`] does for the equivalent [`Value`], but
+/// doesn't build the `Value` first. The exception is the order of struct members: they're written
+/// in the order produced by the `Serialize` impl (the declaration order of the fields for derived
+/// impls), while the members of a `Value::Struct` are sorted by name unless the `indexmap` feature
+/// is enabled.
+///
+/// Rust types are mapped to XML-RPC values as follows:
+///
+/// * Integers are written as `<i4>` if they fit into an `i32`, and as `<i8>` otherwise (integers
+///   larger than `i64::MAX` can't be serialized).
+/// * Floating-point numbers are written as `<double>`, booleans as `<boolean>`, and strings and
+///   characters as `<string>`.
+/// * Byte buffers (such as [`serde_bytes::ByteBuf`]) are written as `<base64>`.
+/// * Sequences and tuples are written as `<array>`, structs and maps as `<struct>`. Map keys must
+///   be strings or integers.
+/// * `None` and `()` are written as `<nil/>`.
+/// * Unit variants are written as a string containing the variant name. Other variants are
+///   written as a struct with a single member named after the variant.
+///
+/// # Errors
+///
+/// Returns an error if the value can not be serialized, or if writing to `writer` fails. Parts of
+/// the value may already have been written in that case.
+///
+/// [`Value::write_as_xml`]: enum.Value.html#method.write_as_xml
+/// [`Value`]: enum.Value.html
+/// [`serde_bytes::ByteBuf`]: https://docs.rs/serde_bytes/0.11/serde_bytes/struct.ByteBuf.html
+pub fn serialize_value<W: Write, T: Serialize + ?Sized>(
+    writer: &mut W,
+    value: &T,
+) -> Result<(), SerializeError> {
+    serialize_value_with(writer, value, &WriterOptions::default())
+}
+
+/// Serializes a value as an XML-RPC `<value>` element using the given [`WriterOptions`], and
+/// writes it to `writer`.
+///
+/// This produces the same XML as [`Value::write_as_xml_with`], except for the order of struct
+/// members (see [`serialize_value`]). Like there, the value is always written as UTF-8, regardless
+/// of the encoding selected in `options`.
+///
+/// # Errors
+///
+/// Returns an error if the value can not be serialized, or if writing to `writer` fails. This
+/// includes values rejected by `options`, such as doubles rejected by
+/// [`NonFiniteDoubles::Error`].
+///
+/// [`WriterOptions`]: struct.WriterOptions.html
+/// [`Value::write_as_xml_with`]: enum.Value.html#method.write_as_xml_with
+/// [`serialize_value`]: fn.serialize_value.html
+/// [`NonFiniteDoubles::Error`]: enum.NonFiniteDoubles.html#variant.Error
+pub fn serialize_value_with<W: Write, T: Serialize + ?Sized>(
+    writer: &mut W,
+    value: &T,
+    options: &WriterOptions,
+) -> Result<(), SerializeError> {
+    let mut xml = XmlWriter::with_options(writer, options);
+    value.serialize(Serializer { visitor: &mut xml })
+}
+
+/// Serializes a value into a `Value`, using the same mapping as `serialize_value`.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, SerializeError> {
+    let mut builder = ValueBuilder::new();
+    value.serialize(Serializer {
+        visitor: &mut builder,
+    })?;
+    Ok(builder.finish())
+}
+
+/// A serializer passing the serialized value to a `ValueVisitor`.
+struct Serializer<'v, V> {
+    visitor: &'v mut V,
+}
+
+impl<'v, V: ValueVisitor> Serializer<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    fn integer(self, value: i64) -> Result<(), SerializeError> {
+        match i32::try_from(value) {
+            Ok(value) => self.visitor.int(value)?,
+            Err(_) => self.visitor.int64(value)?,
+        }
+        Ok(())
+    }
+}
+
+impl<'v, V: ValueVisitor> ser::Serializer for Serializer<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    type Ok = ();
+    type Error = SerializeError;
+    type SerializeSeq = Compound<'v, V>;
+    type SerializeTuple = Compound<'v, V>;
+    type SerializeTupleStruct = Compound<'v, V>;
+    type SerializeTupleVariant = Compound<'v, V>;
+    type SerializeMap = Compound<'v, V>;
+    type SerializeStruct = Compound<'v, V>;
+    type SerializeStructVariant = Compound<'v, V>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), SerializeError> {
+        self.visitor.bool(v)?;
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), SerializeError> {
+        self.integer(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), SerializeError> {
+        self.integer(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), SerializeError> {
+        self.integer(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), SerializeError> {
+        self.integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), SerializeError> {
+        self.integer(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), SerializeError> {
+        self.integer(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), SerializeError> {
+        self.integer(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), SerializeError> {
+        match i64::try_from(v) {
+            Ok(v) => self.integer(v),
+            Err(_) => Err(SerializeError::Message(format!(
+                "integer {} is too large for XML-RPC",
+                v
+            ))),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), SerializeError> {
+        self.visitor.double(v.into())?;
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), SerializeError> {
+        self.visitor.double(v)?;
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), SerializeError> {
+        self.visitor.string(v.encode_utf8(&mut [0; 4]))?;
+        Ok(())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), SerializeError> {
+        self.visitor.string(v)?;
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), SerializeError> {
+        self.visitor.start_base64()?;
+        self.visitor.base64_chunk(v)?;
+        self.visitor.end_base64()?;
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), SerializeError> {
+        self.visitor.nil()?;
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), SerializeError> {
+        self.visitor.nil()?;
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), SerializeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), SerializeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.visitor.start_struct()?;
+        self.visitor.member(variant)?;
+        value.serialize(Serializer {
+            visitor: &mut *self.visitor,
+        })?;
+        self.visitor.end_struct()?;
+        Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'v, V>, SerializeError> {
+        self.visitor.start_array()?;
+        Ok(Compound::new(self.visitor, false))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'v, V>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'v, V>, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'v, V>, SerializeError> {
+        self.visitor.start_struct()?;
+        self.visitor.member(variant)?;
+        self.visitor.start_array()?;
+        Ok(Compound::new(self.visitor, true))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'v, V>, SerializeError> {
+        self.visitor.start_struct()?;
+        Ok(Compound::new(self.visitor, false))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'v, V>, SerializeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'v, V>, SerializeError> {
+        self.visitor.start_struct()?;
+        self.visitor.member(variant)?;
+        self.visitor.start_struct()?;
+        Ok(Compound::new(self.visitor, true))
+    }
+}
+
+/// Serializes the elements of an array or the members of a struct.
+struct Compound<'v, V> {
+    visitor: &'v mut V,
+    /// Whether the array or struct is wrapped in a struct naming an enum variant.
+    variant: bool,
+}
+
+impl<'v, V: ValueVisitor> Compound<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    fn new(visitor: &'v mut V, variant: bool) -> Self {
+        Compound { visitor, variant }
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        value.serialize(Serializer {
+            visitor: &mut *self.visitor,
+        })
+    }
+
+    fn end_array(self) -> Result<(), SerializeError> {
+        self.visitor.end_array()?;
+        if self.variant {
+            self.visitor.end_struct()?;
+        }
+        Ok(())
+    }
+
+    fn end_struct(self) -> Result<(), SerializeError> {
+        self.visitor.end_struct()?;
+        if self.variant {
+            self.visitor.end_struct()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'v, V: ValueVisitor> ser::SerializeSeq for Compound<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_array()
+    }
+}
+
+impl<'v, V: ValueVisitor> ser::SerializeTuple for Compound<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_array()
+    }
+}
+
+impl<'v, V: ValueVisitor> ser::SerializeTupleStruct for Compound<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_array()
+    }
+}
+
+impl<'v, V: ValueVisitor> ser::SerializeTupleVariant for Compound<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_array()
+    }
+}
+
+impl<'v, V: ValueVisitor> ser::SerializeMap for Compound<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        let mut name = MemberName::default();
+        key.serialize(Serializer { visitor: &mut name })?;
+        match name.into_name() {
+            Some(name) => {
+                self.visitor.member(&name)?;
+                Ok(())
+            }
+            None => Err(SerializeError::Message(
+                "struct member names must be strings or integers".to_string(),
+            )),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_struct()
+    }
+}
+
+impl<'v, V: ValueVisitor> ser::SerializeStruct for Compound<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.visitor.member(key)?;
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_struct()
+    }
+}
+
+impl<'v, V: ValueVisitor> ser::SerializeStructVariant for Compound<'v, V>
+where
+    SerializeError: From<V::Error>,
+{
+    type Ok = ();
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.visitor.member(key)?;
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), SerializeError> {
+        self.end_struct()
+    }
+}
+
+/// A `ValueVisitor` capturing a struct member name, which can be a string or an integer.
+#[derive(Default)]
+struct MemberName {
+    name: Option<String>,
+    /// Whether the key is an array or struct.
+    nested: bool,
+}
+
+impl MemberName {
+    fn into_name(self) -> Option<String> {
+        if self.nested {
+            None
+        } else {
+            self.name
+        }
+    }
+}
+
+impl ValueVisitor for MemberName {
+    type Error = SerializeError;
+
+    fn int(&mut self, value: i32) -> Result<(), SerializeError> {
+        self.name = Some(value.to_string());
+        Ok(())
+    }
+
+    fn int64(&mut self, value: i64) -> Result<(), SerializeError> {
+        self.name = Some(value.to_string());
+        Ok(())
+    }
+
+    fn string(&mut self, value: &str) -> Result<(), SerializeError> {
+        self.name = Some(value.to_string());
+        Ok(())
+    }
+
+    fn start_struct(&mut self) -> Result<(), SerializeError> {
+        self.nested = true;
+        Ok(())
+    }
+
+    fn start_array(&mut self) -> Result<(), SerializeError> {
+        self.nested = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use de::deserialize_response;
+    use std::collections::BTreeMap;
+    use std::str;
+    use {NonFiniteDoubles, ParserOptions, Request, WriterLayout};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { h: u8, w: u8 },
+        Polygon(Vec<(i32, i32)>),
+    }
+
+    // Fields are declared sorted by name, so that a `Value::Struct` writes them in the same order
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        id: u64,
+        name: String,
+        parent: Option<Box<Item>>,
+        shapes: Vec<Shape>,
+        tags: Vec<char>,
+    }
+
+    fn item() -> Item {
+        Item {
+            id: 1 << 40,
+            name: "<chair>".to_string(),
+            parent: None,
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(0.5),
+                Shape::Rect { h: 2, w: 1 },
+                Shape::Polygon(vec![(0, 0), (1, -1)]),
+            ],
+            tags: vec!['a', 'ß'],
+        }
+    }
+
+    fn xml<T: Serialize + ?Sized>(value: &T) -> String {
+        let mut output = Vec::new();
+        serialize_value(&mut output, value).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn writes_same_xml_as_value() {
+        let item = item();
+        let value = to_value(&item).unwrap();
+        assert_eq!(value["id"], Value::Int64(1 << 40));
+        assert_eq!(value["shapes"][0], Value::from("Empty"));
+        assert_eq!(value["shapes"][2]["Rect"]["h"], Value::Int(2));
+        assert_eq!(value["parent"], Value::Nil);
+
+        let mut expected = Vec::new();
+        value.write_as_xml(&mut expected).unwrap();
+        assert_eq!(xml(&item), str::from_utf8(&expected).unwrap());
+    }
+
+    #[test]
+    fn writes_members_in_serialization_order() {
+        #[derive(Serialize)]
+        struct Point {
+            y: i32,
+            x: i32,
+        }
+
+        assert_eq!(
+            xml(&Point { y: 1, x: 2 }),
+            "<value><struct>\n<member>\n<name>y</name>\n<value><i4>1</i4></value>\n</member>\n\
+             <member>\n<name>x</name>\n<value><i4>2</i4></value>\n</member>\n</struct></value>\n"
+        );
+    }
+
+    #[test]
+    fn writes_with_options() {
+        let options = WriterOptions::new()
+            .layout(WriterLayout::Compact)
+            .int_tag(true)
+            .apache_nil(true)
+            .non_finite_doubles(NonFiniteDoubles::Error);
+        let mut output = Vec::new();
+        serialize_value_with(&mut output, &(1, None::<u8>), &options).unwrap();
+        let mut expected = Vec::new();
+        Value::Array(vec![Value::Int(1), Value::Nil])
+            .write_as_xml_with(&mut expected, &options)
+            .unwrap();
+        assert_eq!(output, expected);
+        assert!(String::from_utf8(output).unwrap().contains("<int>1</int>"));
+
+        match serialize_value_with(&mut Vec::new(), &f64::NAN, &options) {
+            Err(SerializeError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::InvalidData),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn roundtrips_through_deserializer() {
+        let item = item();
+        let response = format!(
+            "<?xml version=\"1.0\"?><methodResponse><params><param>{}</param></params></methodResponse>",
+            xml(&item)
+        );
+        let result: Item = deserialize_response(&mut response.as_bytes(), &ParserOptions::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(result, item);
+    }
+
+    #[test]
+    fn maps_keys_and_integers() {
+        let mut map = BTreeMap::new();
+        map.insert(3, "three");
+        assert_eq!(
+            xml(&map),
+            "<value><struct>\n<member>\n<name>3</name>\n<value><string>three</string></value>\n</member>\n</struct></value>\n"
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert(vec![1], 1);
+        match to_value(&map) {
+            Err(SerializeError::Message(message)) => assert!(message.contains("member names")),
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        assert_eq!(to_value(&-1i64).unwrap(), Value::Int(-1));
+        assert_eq!(to_value(&u32::MAX).unwrap(), Value::Int64(u32::MAX.into()));
+        assert!(to_value(&u64::MAX).is_err());
+    }
+
+    #[test]
+    fn serializes_request_args() {
+        let request = Request::new("add")
+            .serialize_arg(&(1, 2))
+            .unwrap()
+            .serialize_arg("x")
+            .unwrap();
+        let mut output = Vec::new();
+        request.write_as_xml(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("<param>\n<value><array><data>\n<value><i4>1</i4></value>"));
+        assert!(output.contains("<param>\n<value><string>x</string></value>\n</param>"));
+    }
+}
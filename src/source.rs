@@ -0,0 +1,239 @@
+//! Sources of XML events for the response parser.
+//!
+//! The parser is written against the `EventSource` trait, which is implemented for each supported
+//! XML library. This module contains the implementation based on xml-rs.
+
+use encoding::Utf8Reader;
+use error::ParseError;
+use utils::{escape_xml, LimitedReader};
+
+use std::borrow::Cow;
+use std::fmt::{self, Display, Write};
+use std::io::Read;
+use xml::common::{Position, TextPosition};
+use xml::escape::escape_str_attribute;
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
+use xml::reader::{EventReader, XmlEvent};
+use xml::{Encoding as XmlEncoding, ParserConfig};
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// An XML event relevant to the parser.
+///
+/// Declarations, comments, processing instructions and whitespace between tags are skipped by
+/// the event source. Adjacent text and CDATA sections are merged into a single `Characters`
+/// event.
+#[derive(Debug)]
+pub enum Event<'a> {
+    StartElement {
+        name: Name<'a>,
+        has_attributes: bool,
+    },
+    EndElement {
+        name: Name<'a>,
+    },
+    Characters(Cow<'a, str>),
+    EndDocument,
+}
+
+/// A (possibly namespaced) element name.
+#[derive(Debug)]
+pub struct Name<'a> {
+    pub local_name: Cow<'a, str>,
+    pub prefix: Option<Cow<'a, str>>,
+    /// The namespace URI the name belongs to, if any.
+    pub namespace: Option<Cow<'a, str>>,
+}
+
+impl<'a> Display for Name<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref namespace) = self.namespace {
+            write!(f, "{{{}}}", namespace)?;
+        }
+        if let Some(ref prefix) = self.prefix {
+            write!(f, "{}:", prefix)?;
+        }
+        f.write_str(&self.local_name)
+    }
+}
+
+/// A pull parser producing XML events from a document.
+pub trait EventSource<'a> {
+    /// Pulls the next event from the document.
+    fn next_event(&mut self) -> ParseResult<Event<'a>>;
+
+    /// Returns the position of the last event returned by `next_event`.
+    fn position(&self) -> TextPosition;
+
+    /// Reads the contents of the element whose `StartElement` event was returned last as raw XML,
+    /// up to and including its closing tag (which is not included in the returned string).
+    ///
+    /// Namespace declarations are emitted where the mapping differs from the scope of the
+    /// enclosing element, so that the returned XML is self-contained.
+    fn read_raw_element(&mut self) -> ParseResult<String>;
+}
+
+/// An `EventSource` using the xml-rs parser.
+pub struct XmlRsSource<R: Read> {
+    reader: EventReader<LimitedReader<Utf8Reader<R>>>,
+    /// The limit passed to the `LimitedReader`, for error reporting.
+    max_document_size: Option<u64>,
+    /// The namespace mapping in scope at the last `StartElement` event.
+    namespace: Namespace,
+}
+
+impl<R: Read> XmlRsSource<R> {
+    /// Creates a new source reading from `reader`.
+    ///
+    /// `charset` is the character encoding of the document specified by the transport, if any.
+    pub fn new(
+        reader: R,
+        max_document_size: Option<u64>,
+        charset: Option<&str>,
+    ) -> ParseResult<Self> {
+        let reader = EventReader::new_with_config(
+            LimitedReader::new(Utf8Reader::new(reader, charset)?, max_document_size),
+            // `Utf8Reader` always produces UTF-8, so encoding declarations must be ignored
+            ParserConfig::new()
+                .cdata_to_characters(true)
+                .override_encoding(Some(XmlEncoding::Utf8))
+                .ignore_invalid_encoding_declarations(true),
+        );
+
+        Ok(XmlRsSource {
+            reader,
+            max_document_size,
+            namespace: Namespace::empty(),
+        })
+    }
+
+    /// Pulls the next event from the XML parser, without skipping anything.
+    fn next_raw(&mut self) -> ParseResult<XmlEvent> {
+        match self.reader.next() {
+            Ok(event) => Ok(event),
+            Err(_) if self.reader.source().limit_exceeded() => Err(ParseError::DocumentTooLarge {
+                limit: self.max_document_size.unwrap_or(0),
+            }),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl<'a, R: Read> EventSource<'a> for XmlRsSource<R> {
+    fn next_event(&mut self) -> ParseResult<Event<'a>> {
+        loop {
+            return Ok(match self.next_raw()? {
+                XmlEvent::StartDocument { .. }
+                | XmlEvent::Comment(_)
+                | XmlEvent::Whitespace(_)
+                | XmlEvent::ProcessingInstruction { .. } => continue, // skip these
+                XmlEvent::StartElement {
+                    name,
+                    attributes,
+                    namespace,
+                } => {
+                    self.namespace = namespace;
+                    Event::StartElement {
+                        name: owned_name(name),
+                        has_attributes: !attributes.is_empty(),
+                    }
+                }
+                XmlEvent::EndElement { name } => Event::EndElement {
+                    name: owned_name(name),
+                },
+                XmlEvent::Characters(data) | XmlEvent::CData(data) => {
+                    Event::Characters(Cow::Owned(data))
+                }
+                XmlEvent::EndDocument => Event::EndDocument,
+            });
+        }
+    }
+
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+
+    fn read_raw_element(&mut self) -> ParseResult<String> {
+        let mut xml = String::new();
+        let mut scopes = vec![self.namespace.clone()];
+        loop {
+            match self.next_raw()? {
+                XmlEvent::StartElement {
+                    name,
+                    attributes,
+                    namespace,
+                } => {
+                    write!(xml, "<{}", qualified_name(&name)).unwrap();
+                    let parent = &scopes[scopes.len() - 1];
+                    for (prefix, uri) in &namespace.0 {
+                        if prefix == "xml" || prefix == "xmlns" || parent.0.get(prefix) == Some(uri)
+                        {
+                            continue;
+                        }
+                        write_namespace_declaration(&mut xml, prefix, uri);
+                    }
+                    for attribute in &attributes {
+                        write!(
+                            xml,
+                            " {}=\"{}\"",
+                            qualified_name(&attribute.name),
+                            escape_str_attribute(&attribute.value)
+                        )
+                        .unwrap();
+                    }
+                    xml.push('>');
+                    scopes.push(namespace);
+                }
+                XmlEvent::EndElement { name } => {
+                    if scopes.len() == 1 {
+                        return Ok(xml);
+                    }
+                    write!(xml, "</{}>", qualified_name(&name)).unwrap();
+                    scopes.pop();
+                }
+                XmlEvent::Characters(ref data)
+                | XmlEvent::Whitespace(ref data)
+                | XmlEvent::CData(ref data) => xml.push_str(&escape_xml(data)),
+                XmlEvent::ProcessingInstruction { ref name, ref data } => match *data {
+                    Some(ref data) => write!(xml, "<?{} {}?>", name, data).unwrap(),
+                    None => write!(xml, "<?{}?>", name).unwrap(),
+                },
+                XmlEvent::StartDocument { .. } | XmlEvent::Comment(_) => {}
+                XmlEvent::EndDocument => {
+                    return Err(ParseError::UnexpectedXml {
+                        expected: "closing tag".to_string(),
+                        found: Some("end of data".to_string()),
+                        position: self.reader.position(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+fn owned_name<'a>(name: OwnedName) -> Name<'a> {
+    Name {
+        local_name: Cow::Owned(name.local_name),
+        prefix: name.prefix.map(Cow::Owned),
+        namespace: name.namespace.map(Cow::Owned),
+    }
+}
+
+/// Formats a name as it appears in the document (with its prefix, but without namespace URI).
+fn qualified_name(name: &OwnedName) -> String {
+    match name.prefix {
+        Some(ref prefix) => format!("{}:{}", prefix, name.local_name),
+        None => name.local_name.clone(),
+    }
+}
+
+/// Writes an `xmlns` attribute binding `prefix` (or the default namespace, if empty) to `uri`.
+pub fn write_namespace_declaration(xml: &mut String, prefix: &str, uri: &str) {
+    if prefix.is_empty() {
+        xml.push_str(" xmlns");
+    } else {
+        write!(xml, " xmlns:{}", prefix).unwrap();
+    }
+    write!(xml, "=\"{}\"", escape_str_attribute(uri)).unwrap();
+}
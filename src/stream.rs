@@ -0,0 +1,280 @@
+//! Incremental decoding of large responses.
+
+use error::{Error, RequestErrorKind};
+use parser::{Parser, ParserOptions};
+use source::XmlRsSource;
+use Value;
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::Read;
+
+/// An item of a response decoded by a [`ResponseStream`].
+///
+/// [`ResponseStream`]: struct.ResponseStream.html
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ResponseItem {
+    /// An element of the array returned by the server.
+    Element(Value),
+    /// A member of the struct returned by the server, with its name.
+    Member(String, Value),
+    /// The value returned by the server, if it is neither an array nor a struct.
+    Value(Value),
+}
+
+/// A response that is decoded incrementally while it is being read.
+///
+/// If the server returned an array, the stream yields its elements one at a time as
+/// [`ResponseItem::Element`]s. Likewise, the members of a returned struct are yielded as
+/// [`ResponseItem::Member`]s. Only the item currently being decoded is kept in memory, so even
+/// huge arrays and structs can be processed with bounded memory. Any other value is yielded as a
+/// single [`ResponseItem::Value`].
+///
+/// Create a `ResponseStream` with [`Request::call_streaming`], or with [`ResponseStream::new`] from
+/// a response received through other means.
+///
+/// Responses are always parsed with [`ParserBackend::XmlRs`], regardless of the backend selected
+/// in the `ParserOptions`, since the quick-xml backend needs the whole response in memory.
+///
+/// # Errors
+///
+/// If the response is a `<fault>`, it is returned as an error when creating the stream. Once the
+/// stream returns an error, the rest of the response is skipped and the stream ends.
+///
+/// [`ResponseItem::Element`]: enum.ResponseItem.html#variant.Element
+/// [`ResponseItem::Member`]: enum.ResponseItem.html#variant.Member
+/// [`ResponseItem::Value`]: enum.ResponseItem.html#variant.Value
+/// [`Request::call_streaming`]: struct.Request.html#method.call_streaming
+/// [`ResponseStream::new`]: #method.new
+/// [`ParserBackend::XmlRs`]: enum.ParserBackend.html#variant.XmlRs
+pub struct ResponseStream<R: Read> {
+    parser: Parser<'static, XmlRsSource<R>>,
+    state: State,
+    /// Names of the struct members yielded so far (only tracked if duplicates are rejected).
+    names: BTreeSet<String>,
+}
+
+#[derive(Debug)]
+enum State {
+    /// Yielding the elements of an array.
+    Array,
+    /// Yielding the members of a struct.
+    Struct,
+    /// Yielding a single value.
+    Value(Value),
+    /// The response has been consumed or an error occurred.
+    Done,
+}
+
+impl<R: Read> ResponseStream<R> {
+    /// Starts decoding the response read from `reader`.
+    ///
+    /// This reads the response up to the first array element or struct member.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the response is a `<fault>`, or if its start could not be parsed.
+    pub fn new(reader: R, options: &ParserOptions) -> Result<Self, Error> {
+        Self::with_charset(reader, options, None)
+    }
+
+    /// Like `new`, but with the character encoding of the document specified by the transport.
+    pub(crate) fn with_charset(
+        reader: R,
+        options: &ParserOptions,
+        charset: Option<&str>,
+    ) -> Result<Self, Error> {
+        let parser =
+            Parser::from_reader(reader, options, charset).map_err(RequestErrorKind::ParseError)?;
+        let mut stream = ResponseStream {
+            parser,
+            state: State::Done,
+            names: BTreeSet::new(),
+        };
+        stream.state = stream.start().map_err(Error::from)?;
+        Ok(stream)
+    }
+
+    /// Parses the start of the response and determines what to yield.
+    fn start(&mut self) -> Result<State, RequestErrorKind> {
+        let parser = &mut self.parser;
+        if let Some(fault) = parser.parse_response_start()? {
+            return Err(RequestErrorKind::Fault(fault));
+        }
+
+        parser.open_value()?;
+        if parser.is_open("array") {
+            parser.expect_open("array")?;
+            parser.expect_open("data")?;
+            Ok(State::Array)
+        } else if parser.is_open("struct") {
+            parser.expect_open("struct")?;
+            Ok(State::Struct)
+        } else if parser.try_close("value")? {
+            parser.expect_close("param")?;
+            Ok(State::Value(parser.empty_value()))
+        } else {
+            let value = parser.parse_value_inner()?;
+            parser.close_value()?;
+            parser.expect_close("param")?;
+            Ok(State::Value(value))
+        }
+    }
+
+    /// Parses the next item, or the end of the response.
+    fn next_item(&mut self) -> Result<Option<ResponseItem>, RequestErrorKind> {
+        let parser = &mut self.parser;
+        match self.state {
+            State::Array => {
+                if !parser.try_close("data")? {
+                    return Ok(Some(ResponseItem::Element(parser.parse_value()?)));
+                }
+                parser.expect_close("array")?;
+            }
+            State::Struct => {
+                if !parser.try_close("struct")? {
+                    let names = &self.names;
+                    let (name, value) = parser.parse_member(|name| names.contains(name))?;
+                    if parser.rejects_duplicate_members() {
+                        self.names.insert(name.clone());
+                    }
+                    return Ok(Some(ResponseItem::Member(name, value)));
+                }
+            }
+            State::Value(_) => match ::std::mem::replace(&mut self.state, State::Done) {
+                State::Value(value) => return Ok(Some(ResponseItem::Value(value))),
+                _ => unreachable!(),
+            },
+            State::Done => return Ok(None),
+        }
+
+        // End of the array or struct
+        self.state = State::Done;
+        parser.close_value()?;
+        parser.expect_close("param")?;
+        Ok(None)
+    }
+}
+
+impl<R: Read> Iterator for ResponseStream<R> {
+    type Item = Result<ResponseItem, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_item() {
+            Ok(item) => item.map(Ok),
+            Err(err) => {
+                self.state = State::Done;
+                Some(Err(err.into()))
+            }
+        }
+    }
+}
+
+impl<R: Read> fmt::Debug for ResponseStream<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseStream")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::ParseError;
+
+    fn items(xml: &str, options: &ParserOptions) -> Result<Vec<ResponseItem>, Error> {
+        ResponseStream::new(xml.as_bytes(), options)?.collect()
+    }
+
+    #[test]
+    fn streams_array_elements() {
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse>
+  <params>
+    <param>
+      <value><array><data>
+        <value><int>1</int></value>
+        <value>two</value>
+        <value><array><data><value><int>3</int></value></data></array></value>
+      </data></array></value>
+    </param>
+  </params>
+</methodResponse>"#;
+
+        let mut stream = ResponseStream::new(xml.as_bytes(), &ParserOptions::new()).unwrap();
+        assert_eq!(
+            stream.next().unwrap().unwrap(),
+            ResponseItem::Element(Value::Int(1))
+        );
+        assert_eq!(
+            stream.next().unwrap().unwrap(),
+            ResponseItem::Element(Value::from("two"))
+        );
+        assert_eq!(
+            stream.next().unwrap().unwrap(),
+            ResponseItem::Element(Value::Array(vec![Value::Int(3)]))
+        );
+        assert!(stream.next().is_none());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn streams_struct_members() {
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse><params><param><value><struct>
+  <member><name>a</name><value><int>1</int></value></member>
+  <member><name>b</name><value><boolean>1</boolean></value></member>
+  <member><name>a</name><value>again</value></member>
+</struct></value></param></params></methodResponse>"#;
+
+        assert_eq!(
+            items(xml, &ParserOptions::new()).unwrap(),
+            vec![
+                ResponseItem::Member("a".to_string(), Value::Int(1)),
+                ResponseItem::Member("b".to_string(), Value::Bool(true)),
+                ResponseItem::Member("a".to_string(), Value::from("again")),
+            ]
+        );
+
+        let err = items(xml, &ParserOptions::strict()).unwrap_err();
+        match err.parse_error() {
+            Some(ParseError::DuplicateMember { name, .. }) => assert_eq!(name, "a"),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn streams_scalars_and_faults() {
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse><params><param><value><i4>42</i4></value></param></params></methodResponse>"#;
+        assert_eq!(
+            items(xml, &ParserOptions::new()).unwrap(),
+            vec![ResponseItem::Value(Value::Int(42))]
+        );
+
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse><fault><value><struct>
+  <member><name>faultCode</name><value><int>4</int></value></member>
+  <member><name>faultString</name><value>Too many parameters.</value></member>
+</struct></value></fault></methodResponse>"#;
+        let err = ResponseStream::new(xml.as_bytes(), &ParserOptions::new()).unwrap_err();
+        assert_eq!(err.fault().unwrap().fault_code, 4);
+    }
+
+    #[test]
+    fn stops_after_error() {
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse><params><param><value><array><data>
+  <value><int>1</int></value>
+  <value><int>x</int></value>
+  <value><int>3</int></value>
+</data></array></value></param></params></methodResponse>"#;
+
+        let mut stream = ResponseStream::new(xml.as_bytes(), &ParserOptions::new()).unwrap();
+        assert!(stream.next().unwrap().is_ok());
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use Request;
+use {RawResponse, Request};
 
 use std::error::Error;
 use std::io::Read;
@@ -26,8 +26,12 @@ pub trait Transport {
     /// Transmits an XML-RPC request and returns the server's response.
     ///
     /// The response is returned as a `Self::Stream` - some type implementing the `Read` trait. The
-    /// library will read all of the data and parse it as a response. It must be UTF-8 encoded XML,
-    /// otherwise the call will fail.
+    /// library will read all of the data and parse it as a response. Documents in encodings other
+    /// than UTF-8 are transcoded according to their byte order mark, the `charset` of the
+    /// `Content-Type` header (if provided by [`describe_response`]), or their XML declaration.
+    ///
+    /// The request should be sent using [`Request::write_as_xml`], declaring
+    /// [`Request::charset`] as its character encoding if the protocol allows it.
     ///
     /// # Errors
     ///
@@ -35,7 +39,27 @@ pub trait Transport {
     /// return an appropriate [`Error`] to the caller.
     ///
     /// [`Error`]: struct.Error.html
+    /// [`describe_response`]: #method.describe_response
+    /// [`Request::write_as_xml`]: struct.Request.html#method.write_as_xml
+    /// [`Request::charset`]: struct.Request.html#method.charset
     fn transmit(self, request: &Request<'_>) -> Result<Self::Stream, Box<dyn Error + Send + Sync>>;
+
+    /// Describes a response stream returned by `transmit`.
+    ///
+    /// If the response can not be parsed, the returned [`RawResponse`] is completed with the start
+    /// of the response body and attached to the [`Error`] returned to the caller. Transports should
+    /// fill in the status and headers of the response, if they have any.
+    ///
+    /// The default implementation returns an empty `RawResponse`.
+    ///
+    /// [`RawResponse`]: struct.RawResponse.html
+    /// [`Error`]: struct.Error.html
+    fn describe_response(_stream: &Self::Stream) -> RawResponse
+    where
+        Self: Sized,
+    {
+        RawResponse::default()
+    }
 }
 
 // FIXME: Link to `Transport` and `RequestBuilder` using intra-rustdoc links. Relative links break
@@ -50,11 +74,13 @@ pub trait Transport {
 /// ```notrust
 /// // serialize request into `body` (a `Vec<u8>`)
 ///
-/// build_headers(builder, body.len());
+/// build_headers_with_charset(builder, body.len(), request.charset());
 ///
 /// // send `body` using `builder` and get response
 ///
 /// check_response(&response)?;
+///
+/// // on error, attach `read_raw_response(response)` to a `ResponseError`
 /// ```
 ///
 /// From this, you can build your own custom transports.
@@ -66,11 +92,12 @@ pub mod http {
     extern crate reqwest;
 
     use self::mime::Mime;
-    use self::reqwest::blocking::RequestBuilder;
+    use self::reqwest::blocking::{RequestBuilder, Response};
     use self::reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, USER_AGENT};
-    use {Request, Transport};
+    use {RawResponse, Request, ResponseError, Transport};
 
     use std::error::Error;
+    use std::io::Read;
     use std::str::FromStr;
 
     /// Appends all HTTP headers required by the XML-RPC specification to the `RequestBuilder`.
@@ -83,20 +110,29 @@ pub mod http {
     /// Content-Length: $body_len
     /// ```
     pub fn build_headers(builder: RequestBuilder, body_len: u64) -> RequestBuilder {
+        build_headers_with_charset(builder, body_len, "utf-8")
+    }
+
+    /// Like [`build_headers`], but declares the request body to be encoded with `charset`.
+    ///
+    /// [`build_headers`]: fn.build_headers.html
+    pub fn build_headers_with_charset(
+        builder: RequestBuilder,
+        body_len: u64,
+        charset: &str,
+    ) -> RequestBuilder {
         // Set all required request headers
         // NB: The `Host` header is also required, but reqwest adds it automatically, since
         // HTTP/1.1 requires it.
         builder
             .header(USER_AGENT, "Rust xmlrpc")
-            .header(CONTENT_TYPE, "text/xml; charset=utf-8")
+            .header(CONTENT_TYPE, format!("text/xml; charset={}", charset))
             .header(CONTENT_LENGTH, body_len)
     }
 
     /// Checks that a reqwest `Response` has a status code indicating success and verifies certain
     /// headers.
-    pub fn check_response(
-        response: &reqwest::blocking::Response,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    pub fn check_response(response: &Response) -> Result<(), Box<dyn Error + Send + Sync>> {
         // This is essentially an open-coded version of `Response::error_for_status` that does not
         // consume the response.
         if response.status().is_client_error() || response.status().is_server_error() {
@@ -128,12 +164,59 @@ pub mod http {
         Ok(())
     }
 
+    /// Collects the status and headers of a reqwest `Response` into a [`RawResponse`], without
+    /// reading the body.
+    ///
+    /// [`RawResponse`]: ../struct.RawResponse.html
+    pub fn response_metadata(response: &Response) -> RawResponse {
+        RawResponse {
+            status: Some(response.status().as_u16()),
+            headers: response
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+                    )
+                })
+                .collect(),
+            ..RawResponse::default()
+        }
+    }
+
+    /// Consumes a reqwest `Response` and returns its status, headers and the start of its body.
+    ///
+    /// At most [`RawResponse::MAX_BODY_LEN`] bytes of the body are read. Errors while reading the
+    /// body are ignored.
+    ///
+    /// [`RawResponse::MAX_BODY_LEN`]: ../struct.RawResponse.html#associatedconstant.MAX_BODY_LEN
+    pub fn read_raw_response(response: Response) -> RawResponse {
+        let mut raw = response_metadata(&response);
+        let mut body = Vec::new();
+        let _ = response
+            .take(RawResponse::MAX_BODY_LEN as u64 + 1)
+            .read_to_end(&mut body);
+        if body.len() > RawResponse::MAX_BODY_LEN {
+            body.truncate(RawResponse::MAX_BODY_LEN);
+            raw.body_truncated = true;
+        }
+        raw.body = body;
+        raw
+    }
+
     /// Use a `RequestBuilder` as the transport.
     ///
     /// The request will be sent as specified in the XML-RPC specification: A default `User-Agent`
     /// will be set, along with the correct `Content-Type` and `Content-Length`.
+    ///
+    /// If the response is rejected by [`check_response`], a [`ResponseError`] containing the
+    /// response status, headers and the start of the body is returned.
+    ///
+    /// [`check_response`]: fn.check_response.html
+    /// [`ResponseError`]: ../struct.ResponseError.html
     impl Transport for RequestBuilder {
-        type Stream = reqwest::blocking::Response;
+        type Stream = Response;
 
         fn transmit(
             self,
@@ -141,15 +224,26 @@ pub mod http {
         ) -> Result<Self::Stream, Box<dyn Error + Send + Sync>> {
             // First, build the body XML
             let mut body = Vec::new();
-            // This unwrap never panics as we are using `Vec<u8>` as a `Write` implementor,
-            // and not doing anything else that could return an `Err` in `write_as_xml()`.
-            request.write_as_xml(&mut body).unwrap();
-
-            let response = build_headers(self, body.len() as u64).body(body).send()?;
-
-            check_response(&response)?;
+            // Writing to a `Vec<u8>` can't fail, but the request may contain values that can't be
+            // written (see `NonFiniteDoubles::Error`).
+            request.write_as_xml(&mut body)?;
+
+            let response = build_headers_with_charset(self, body.len() as u64, request.charset())
+                .body(body)
+                .send()?;
+
+            if let Err(error) = check_response(&response) {
+                return Err(Box::new(ResponseError::new(
+                    error,
+                    read_raw_response(response),
+                )));
+            }
 
             Ok(response)
         }
+
+        fn describe_response(response: &Response) -> RawResponse {
+            response_metadata(response)
+        }
     }
 }
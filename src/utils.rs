@@ -1,8 +1,13 @@
 use iso8601::{Date, DateTime, Time};
+use writer::DateTimeFormat;
 use xml::escape::escape_str_pcdata;
 
 use std::borrow::Cow;
 use std::fmt::Write;
+use std::io::{self, Read};
+
+/// The namespace of the Apache XML-RPC extension types.
+pub const APACHE_EXTENSIONS_NS: &str = "http://ws.apache.org/xmlrpc/namespaces/extensions";
 
 /// Escape a string for use as XML characters.
 ///
@@ -11,49 +16,286 @@ pub fn escape_xml(s: &str) -> Cow<'_, str> {
     escape_str_pcdata(s)
 }
 
-/// Formats a `DateTime` for use in XML-RPC.
+/// Formats a `DateTime` for use in XML-RPC in the given format.
 ///
 /// Note that XML-RPC is extremely underspecified when it comes to datetime values. Apparently,
 /// some clients [don't even support timezone information][wp-bug] (we do). For maximum
-/// interoperability, this will omit fractional time and time zone if not specified.
+/// interoperability, this will omit fractional time and time zone if not specified. Week and
+/// ordinal dates are converted to calendar dates, which is the only format servers can be expected
+/// to understand.
+///
+/// Returns `None` if the date and time is invalid, or can't be written in this format (such as
+/// years before 0 or after 9999).
 ///
 /// [wp-bug]: https://core.trac.wordpress.org/ticket/1633#comment:4
-pub fn format_datetime(date_time: &DateTime) -> String {
+pub fn format_datetime(date_time: &DateTime, format: DateTimeFormat) -> Option<String> {
     let Time {
-        hour,
-        minute,
+        mut hour,
+        mut minute,
         second,
         millisecond,
         tz_offset_hours,
         tz_offset_minutes,
     } = date_time.time;
 
-    match date_time.date {
+    let (mut year, mut month, mut day) = calendar_date(&date_time.date)?;
+    let mut offset = tz_offset_hours * 60 + tz_offset_minutes;
+    if hour > 24 || minute > 59 || second > 60 || millisecond > 999 || offset.abs() >= 24 * 60 {
+        return None;
+    }
+
+    let to_utc = format == DateTimeFormat::Utc || format == DateTimeFormat::NaiveUtc;
+    if to_utc && offset != 0 {
+        let minutes = days_from_civil(year, month, day) * MINUTES_PER_DAY
+            + i64::from(hour * 60 + minute)
+            - i64::from(offset);
+        let (utc_year, utc_month, utc_day) = civil_from_days(minutes.div_euclid(MINUTES_PER_DAY));
+        let time = minutes.rem_euclid(MINUTES_PER_DAY) as u32;
+        year = utc_year;
+        month = utc_month;
+        day = utc_day;
+        hour = time / 60;
+        minute = time % 60;
+        offset = 0;
+    }
+    if !(0..=9999).contains(&year) {
+        return None;
+    }
+
+    // The basic format is based directly on the example in the spec and should always work:
+    let mut string = match format {
+        DateTimeFormat::Basic | DateTimeFormat::NaiveUtc => {
+            format!("{:04}{:02}{:02}", year, month, day)
+        }
+        DateTimeFormat::Extended | DateTimeFormat::Utc => {
+            format!("{:04}-{:02}-{:02}", year, month, day)
+        }
+    };
+    write!(string, "T{:02}:{:02}:{:02}", hour, minute, second).unwrap();
+    // Only append milliseconds when they're >0
+    if millisecond > 0 {
+        write!(string, ".{:03}", millisecond).unwrap();
+    }
+    if format == DateTimeFormat::Utc {
+        string.push('Z');
+    } else if offset != 0 {
+        // Only append time zone info if the offset is specified and not 00:00
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.abs();
+        write!(string, "{}{:02}:{:02}", sign, offset / 60, offset % 60).unwrap();
+    }
+
+    Some(string)
+}
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// Converts a date to a calendar date, returning the year, month and day.
+///
+/// Returns `None` if the date is invalid.
+pub fn calendar_date(date: &Date) -> Option<(i32, u32, u32)> {
+    match *date {
         Date::YMD { year, month, day } => {
-            // The base format is based directly on the example in the spec and should always work:
-            let mut string = format!(
-                "{:04}{:02}{:02}T{:02}:{:02}:{:02}",
-                year, month, day, hour, minute, second
-            );
-            // Only append milliseconds when they're >0
-            if millisecond > 0 {
-                write!(string, ".{:.3}", millisecond).unwrap();
+            if (1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month) {
+                Some((year, month, day))
+            } else {
+                None
+            }
+        }
+        Date::Ordinal { year, ddd } => date_from_ordinal(year, ddd),
+        Date::Week { year, ww, d } => {
+            if ww < 1 || ww > weeks_in_year(year) || !(1..=7).contains(&d) {
+                return None;
+            }
+            // Week 1 is the week containing January 4th
+            let ordinal = (ww * 7 + d) as i32 - (weekday(year, 1, 4) + 3) as i32;
+            if ordinal < 1 {
+                let previous = year - 1;
+                date_from_ordinal(previous, (ordinal + days_in_year(previous) as i32) as u32)
+            } else if ordinal as u32 > days_in_year(year) {
+                date_from_ordinal(year + 1, ordinal as u32 - days_in_year(year))
+            } else {
+                date_from_ordinal(year, ordinal as u32)
             }
-            // Only append time zone info if the offset is specified and not 00:00
-            if tz_offset_hours != 0 || tz_offset_minutes != 0 {
-                write!(
-                    string,
-                    "{:+03}:{:02}",
-                    tz_offset_hours,
-                    tz_offset_minutes.abs()
-                )
-                .unwrap();
+        }
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_year(year: i32) -> u32 {
+    if is_leap_year(year) {
+        366
+    } else {
+        365
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Returns the number of ISO weeks in `year` (52 or 53).
+fn weeks_in_year(year: i32) -> u32 {
+    // Years starting on a Thursday, and leap years starting on a Wednesday, have 53 weeks
+    match weekday(year, 1, 1) {
+        4 => 53,
+        3 if is_leap_year(year) => 53,
+        _ => 52,
+    }
+}
+
+/// Returns the ISO day of the week of a date, from 1 (Monday) to 7 (Sunday).
+fn weekday(year: i32, month: u32, day: u32) -> u32 {
+    // 1970-01-01 was a Thursday
+    ((days_from_civil(year, month, day) + 3).rem_euclid(7) + 1) as u32
+}
+
+/// Returns the number of days since 1970-01-01, using Howard Hinnant's `days_from_civil`
+/// algorithm.
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year } as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of `days_from_civil`, returning the year, month and day.
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as i32, month, day)
+}
+
+fn date_from_ordinal(year: i32, mut ordinal: u32) -> Option<(i32, u32, u32)> {
+    if ordinal < 1 || ordinal > days_in_year(year) {
+        return None;
+    }
+    for month in 1..=12 {
+        let days = days_in_month(year, month);
+        if ordinal <= days {
+            return Some((year, month, ordinal));
+        }
+        ordinal -= days;
+    }
+    unreachable!()
+}
+
+/// A `Read` adapter that keeps a copy of the first `limit` bytes read from the inner reader.
+///
+/// Used to attach the start of a response body to parse errors.
+pub struct RecordingReader<R> {
+    inner: R,
+    recorded: Vec<u8>,
+    limit: usize,
+    truncated: bool,
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub fn new(inner: R, limit: usize) -> Self {
+        RecordingReader {
+            inner,
+            recorded: Vec::new(),
+            limit,
+            truncated: false,
+        }
+    }
+
+    /// Reads the rest of the first `limit` bytes from the inner reader and returns the recorded
+    /// data, along with a flag indicating whether the data was truncated.
+    ///
+    /// Errors while reading the remaining data are ignored, since this is only used for
+    /// diagnostics.
+    pub fn finish(mut self) -> (Vec<u8>, bool) {
+        let mut buf = [0; 512];
+        while !self.truncated {
+            match self.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
             }
+        }
+
+        (self.recorded, self.truncated)
+    }
+}
+
+impl<R: Read> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        let remaining = self.limit - self.recorded.len();
+        if len > remaining {
+            self.truncated = true;
+        }
+        self.recorded.extend_from_slice(&buf[..len.min(remaining)]);
+        Ok(len)
+    }
+}
+
+/// A `Read` adapter that fails once more than `limit` bytes have been read.
+pub struct LimitedReader<R> {
+    inner: R,
+    remaining: Option<u64>,
+    exceeded: bool,
+}
+
+impl<R: Read> LimitedReader<R> {
+    /// Creates a new `LimitedReader`. If `limit` is `None`, no limit will be enforced.
+    pub fn new(inner: R, limit: Option<u64>) -> Self {
+        LimitedReader {
+            inner,
+            remaining: limit,
+            exceeded: false,
+        }
+    }
 
-            string
+    /// Returns whether the inner reader contained more than `limit` bytes.
+    pub fn limit_exceeded(&self) -> bool {
+        self.exceeded
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = match self.remaining {
+            Some(remaining) => remaining,
+            None => return self.inner.read(buf),
+        };
+
+        // Try to read one byte more than allowed to detect when the limit is exceeded
+        let max = buf.len().min(remaining.saturating_add(1) as usize);
+        let len = self.inner.read(&mut buf[..max])?;
+        if len as u64 > remaining {
+            self.exceeded = true;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "document size limit exceeded",
+            ));
         }
-        // Other format are just not supported at all:
-        Date::Week { .. } | Date::Ordinal { .. } => unimplemented!(),
+        self.remaining = Some(remaining - len as u64);
+        Ok(len)
     }
 }
 
@@ -66,20 +308,157 @@ mod tests {
     fn formats_datetimes() {
         let date_time = iso8601::datetime("2016-05-02T06:01:05-0830").unwrap();
 
-        let formatted = format_datetime(&date_time);
+        let formatted = format_datetime(&date_time, DateTimeFormat::Basic).unwrap();
         assert_eq!(formatted, "20160502T06:01:05-08:30");
         assert_eq!(iso8601::datetime(&formatted).unwrap(), date_time);
 
         // milliseconds / fraction
         let date_time = iso8601::datetime("20160502T06:01:05.400").unwrap();
-        let formatted = format_datetime(&date_time);
+        let formatted = format_datetime(&date_time, DateTimeFormat::Basic).unwrap();
         assert_eq!(formatted, "20160502T06:01:05.400");
         assert_eq!(iso8601::datetime(&formatted).unwrap(), date_time);
 
+        // milliseconds with leading zeros
+        let date_time = iso8601::datetime("20160502T06:01:05.050").unwrap();
+        let formatted = format_datetime(&date_time, DateTimeFormat::Basic).unwrap();
+        assert_eq!(formatted, "20160502T06:01:05.050");
+        assert_eq!(iso8601::datetime(&formatted).unwrap(), date_time);
+
         // milliseconds / fraction + time zone
         let date_time = iso8601::datetime("20160502T06:01:05.400+01:02").unwrap();
-        let formatted = format_datetime(&date_time);
+        let formatted = format_datetime(&date_time, DateTimeFormat::Basic).unwrap();
         assert_eq!(formatted, "20160502T06:01:05.400+01:02");
         assert_eq!(iso8601::datetime(&formatted).unwrap(), date_time);
     }
+
+    fn at_noon(date: Date) -> DateTime {
+        DateTime {
+            date,
+            time: Time {
+                hour: 12,
+                ..Time::default()
+            },
+        }
+    }
+
+    #[test]
+    fn formats_week_and_ordinal_dates() {
+        let week = |year, ww, d| at_noon(Date::Week { year, ww, d });
+        let ordinal = |year, ddd| at_noon(Date::Ordinal { year, ddd });
+        for (date_time, expected) in &[
+            (week(2020, 53, 4), "20201231T12:00:00"),
+            (week(2021, 1, 1), "20210104T12:00:00"),
+            // Week 1 of 2020 starts in 2019, week 53 of 2015 ends in 2016
+            (week(2020, 1, 1), "20191230T12:00:00"),
+            (week(2015, 53, 7), "20160103T12:00:00"),
+            (ordinal(2020, 366), "20201231T12:00:00"),
+            (ordinal(2021, 60), "20210301T12:00:00"),
+        ] {
+            assert_eq!(
+                format_datetime(date_time, DateTimeFormat::Basic).unwrap(),
+                *expected
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unrepresentable_datetimes() {
+        for date in &[
+            Date::Week {
+                year: 2021,
+                ww: 53,
+                d: 1,
+            },
+            Date::Week {
+                year: 2021,
+                ww: 1,
+                d: 8,
+            },
+            Date::Ordinal {
+                year: 2021,
+                ddd: 366,
+            },
+            Date::YMD {
+                year: 2021,
+                month: 2,
+                day: 29,
+            },
+            Date::YMD {
+                year: 10000,
+                month: 1,
+                day: 1,
+            },
+        ] {
+            assert_eq!(
+                format_datetime(&at_noon(*date), DateTimeFormat::Basic),
+                None,
+                "{:?}",
+                date
+            );
+        }
+    }
+
+    #[test]
+    fn formats_negative_offsets_below_one_hour() {
+        let date_time = iso8601::datetime("2016-05-02T06:01:05-00:30").unwrap();
+        let formatted = format_datetime(&date_time, DateTimeFormat::Basic).unwrap();
+        assert_eq!(formatted, "20160502T06:01:05-00:30");
+        assert_eq!(iso8601::datetime(&formatted).unwrap(), date_time);
+    }
+
+    #[test]
+    fn formats_datetimes_in_each_format() {
+        let date_time = iso8601::datetime("2016-05-02T06:01:05.400+01:30").unwrap();
+        for (format, expected) in &[
+            (DateTimeFormat::Basic, "20160502T06:01:05.400+01:30"),
+            (DateTimeFormat::Extended, "2016-05-02T06:01:05.400+01:30"),
+            (DateTimeFormat::Utc, "2016-05-02T04:31:05.400Z"),
+            (DateTimeFormat::NaiveUtc, "20160502T04:31:05.400"),
+        ] {
+            assert_eq!(format_datetime(&date_time, *format).unwrap(), *expected);
+        }
+
+        // Without an offset, values are assumed to be in UTC already
+        let date_time = iso8601::datetime("19980717T14:08:55").unwrap();
+        for (format, expected) in &[
+            (DateTimeFormat::Basic, "19980717T14:08:55"),
+            (DateTimeFormat::Extended, "1998-07-17T14:08:55"),
+            (DateTimeFormat::Utc, "1998-07-17T14:08:55Z"),
+            (DateTimeFormat::NaiveUtc, "19980717T14:08:55"),
+        ] {
+            assert_eq!(format_datetime(&date_time, *format).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn converts_to_utc_across_dates() {
+        for (input, expected) in &[
+            ("2016-12-31T23:30:00-01:00", "2017-01-01T00:30:00Z"),
+            ("2016-03-01T00:15:00+00:30", "2016-02-29T23:45:00Z"),
+            ("2000-01-01T00:00:00+01:00", "1999-12-31T23:00:00Z"),
+            ("2016-05-02T24:00:00+02:00", "2016-05-02T22:00:00Z"),
+        ] {
+            let date_time = iso8601::datetime(input).unwrap();
+            assert_eq!(
+                format_datetime(&date_time, DateTimeFormat::Utc).unwrap(),
+                *expected
+            );
+        }
+
+        // Converting can leave the representable range
+        let date_time = iso8601::datetime("0000-01-01T00:00:00+01:00").unwrap();
+        assert_eq!(format_datetime(&date_time, DateTimeFormat::Utc), None);
+        assert!(format_datetime(&date_time, DateTimeFormat::Basic).is_some());
+    }
+
+    #[test]
+    fn records_prefix() {
+        let mut reader = RecordingReader::new(&b"<html>oops</html>"[..], 6);
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.finish(), (b"<html>".to_vec(), true));
+
+        let reader = RecordingReader::new(&b"<html>"[..], 6);
+        assert_eq!(reader.finish(), (b"<html>".to_vec(), false));
+    }
 }
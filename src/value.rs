@@ -1,11 +1,11 @@
 //! Contains the different types of values understood by XML-RPC.
 
-use utils::{escape_xml, format_datetime};
+use visitor::ValueVisitor;
+use writer::{WriterOptions, XmlWriter};
+use Map;
 
-use base64::encode;
 use iso8601::DateTime;
 
-use std::collections::BTreeMap;
 use std::io::{self, Write};
 
 /// The possible XML-RPC values.
@@ -14,7 +14,9 @@ use std::io::{self, Write};
 /// indexing operator.
 ///
 /// A string index can be used to access a value in a `Struct`, and a `usize` index can be used to
-/// access an element of an `Array`.
+/// access an element of an `Array`. Values nested more deeply can be looked up by path with
+/// [`get_path`](#method.get_path) and [`pointer`](#method.pointer), which also have variants
+/// returning mutable references or an error describing which part of the path is missing.
 ///
 /// # Examples
 ///
@@ -52,6 +54,7 @@ use std::io::{self, Write};
 /// assert_eq!(person["children"].as_array().unwrap().len(), 2);
 /// ```
 #[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Value {
     /// A 32-bit signed integer (`<i4>` or `<int>`).
     Int(i32),
@@ -62,7 +65,10 @@ pub enum Value {
     /// A boolean value (`<boolean>`, 0 == `false`, 1 == `true`).
     Bool(bool),
     /// A string (`<string>`).
-    // FIXME zero-copy? `Cow<'static, ..>`?
+    ///
+    /// Use [`ValueRef`] to avoid copying strings out of a parsed document.
+    ///
+    /// [`ValueRef`]: enum.ValueRef.html
     String(String),
     /// A double-precision IEEE 754 floating point number (`<double>`).
     Double(f64),
@@ -74,9 +80,16 @@ pub enum Value {
     /// struct only contains the raw fields specified by the server, without any real date/time
     /// functionality like what's offered by the `chrono` crate.
     ///
+    /// With the `chrono` feature, values can be converted from chrono's date/time types using
+    /// `From`, and back using [`as_naive_datetime`] and [`as_chrono_datetime`], which takes the
+    /// offset of the server's time zone. Likewise, the `time` feature provides `From` and
+    /// `TryFrom` conversions for `PrimitiveDateTime` and `OffsetDateTime`.
+    ///
     /// To make matters worse, some clients [don't seem to support][wp-bug] time zone information in
     /// datetime values. To ensure compatiblity, the xmlrpc crate will try to format datetime values
-    /// like the example given in the [specification] if the timezone offset is zero.
+    /// like the example given in the [specification] if the timezone offset is zero. Week and
+    /// ordinal dates are written as the equivalent calendar date. Invalid dates and times, and
+    /// years outside of 0 to 9999, can't be written and cause an error.
     ///
     /// Recommendation: Avoid `DateTime` if possible. A date and time can be specified more
     /// precisely by formatting it using RFC 3339 and putting it in a [`String`].
@@ -84,12 +97,18 @@ pub enum Value {
     /// [wp-bug]: https://core.trac.wordpress.org/ticket/1633#comment:4
     /// [specification]: http://xmlrpc.scripting.com/spec.html
     /// [`String`]: #variant.String
+    /// [`as_naive_datetime`]: #method.as_naive_datetime
+    /// [`as_chrono_datetime`]: #method.as_chrono_datetime
     DateTime(DateTime),
     /// Base64-encoded binary data (`<base64>`).
     Base64(Vec<u8>),
 
     /// A mapping of named values (`<struct>`).
-    Struct(BTreeMap<String, Value>),
+    ///
+    /// Members are sorted by name, unless the `indexmap` feature is enabled (see [`Map`]).
+    ///
+    /// [`Map`]: map/struct.Map.html
+    Struct(Map),
     /// A list of arbitrary (heterogeneous) values (`<array>`).
     Array(Vec<Value>),
 
@@ -99,6 +118,43 @@ pub enum Value {
     ///
     /// [ext]: https://web.archive.org/web/20050911054235/http://ontosys.com/xml-rpc/extensions.php
     Nil,
+
+    /// An arbitrary-precision integer in decimal notation (`<ex:biginteger>`).
+    ///
+    /// This is an [Apache XML-RPC extension][ext] and may not be supported by all clients /
+    /// servers. It is only parsed if [`ParserOptions::apache_extensions`] is enabled.
+    ///
+    /// [ext]: https://ws.apache.org/xmlrpc/types.html
+    /// [`ParserOptions::apache_extensions`]: struct.ParserOptions.html#method.apache_extensions
+    BigInteger(String),
+    /// An arbitrary-precision decimal number (`<ex:bigdecimal>`).
+    ///
+    /// This is an [Apache XML-RPC extension][ext] and may not be supported by all clients /
+    /// servers. It is only parsed if [`ParserOptions::apache_extensions`] is enabled.
+    ///
+    /// [ext]: https://ws.apache.org/xmlrpc/types.html
+    /// [`ParserOptions::apache_extensions`]: struct.ParserOptions.html#method.apache_extensions
+    BigDecimal(String),
+    /// A serialized Java object (`<ex:serializable>`).
+    ///
+    /// This is an [Apache XML-RPC extension][ext] and may not be supported by all clients /
+    /// servers. It is only parsed if [`ParserOptions::apache_extensions`] is enabled.
+    ///
+    /// [ext]: https://ws.apache.org/xmlrpc/types.html
+    /// [`ParserOptions::apache_extensions`]: struct.ParserOptions.html#method.apache_extensions
+    Serializable(Vec<u8>),
+    /// A DOM node, stored as an XML string (`<ex:dom>`).
+    ///
+    /// The string is written to the request verbatim, so it must contain a single well-formed XML
+    /// element. Writing a value fails with an `InvalidData` error if it doesn't, so that the
+    /// string can't change the structure of the surrounding document.
+    ///
+    /// This is an [Apache XML-RPC extension][ext] and may not be supported by all clients /
+    /// servers. It is only parsed if [`ParserOptions::apache_extensions`] is enabled.
+    ///
+    /// [ext]: https://ws.apache.org/xmlrpc/types.html
+    /// [`ParserOptions::apache_extensions`]: struct.ParserOptions.html#method.apache_extensions
+    Dom(String),
 }
 
 impl Value {
@@ -106,62 +162,71 @@ impl Value {
     ///
     /// # Errors
     ///
-    /// Any error reported by the writer will be propagated to the caller.
+    /// Any error reported by the writer will be propagated to the caller. An error of kind
+    /// `InvalidData` is returned if the value contains a date/time that can't be written (see
+    /// [`Value::DateTime`]).
+    ///
+    /// [`Value::DateTime`]: #variant.DateTime
     pub fn write_as_xml<W: Write>(&self, fmt: &mut W) -> io::Result<()> {
-        write!(fmt, "<value>")?;
+        self.write_as_xml_with(fmt, &WriterOptions::default())
+    }
 
+    /// Formats this `Value` as an XML `<value>` element, using the given [`WriterOptions`].
+    ///
+    /// The value is always written as UTF-8, regardless of the encoding selected in `options`.
+    ///
+    /// # Errors
+    ///
+    /// Any error reported by the writer will be propagated to the caller. An error of kind
+    /// `InvalidData` is returned if the value contains a date/time that can't be written (see
+    /// [`Value::DateTime`]), or a double rejected by [`NonFiniteDoubles::Error`].
+    ///
+    /// [`NonFiniteDoubles::Error`]: enum.NonFiniteDoubles.html#variant.Error
+    /// [`WriterOptions`]: struct.WriterOptions.html
+    /// [`Value::DateTime`]: #variant.DateTime
+    pub fn write_as_xml_with<W: Write>(
+        &self,
+        fmt: &mut W,
+        options: &WriterOptions,
+    ) -> io::Result<()> {
+        self.visit(&mut XmlWriter::with_options(fmt, options))
+    }
+
+    /// Passes this `Value` to `visitor`, in the same way as the parser would.
+    pub(crate) fn visit<V: ValueVisitor>(&self, visitor: &mut V) -> Result<(), V::Error> {
         match *self {
-            Value::Int(i) => {
-                write!(fmt, "<i4>{}</i4>", i)?;
-            }
-            Value::Int64(i) => {
-                write!(fmt, "<i8>{}</i8>", i)?;
-            }
-            Value::Bool(b) => {
-                write!(fmt, "<boolean>{}</boolean>", if b { "1" } else { "0" })?;
-            }
-            Value::String(ref s) => {
-                write!(fmt, "<string>{}</string>", escape_xml(s))?;
-            }
-            Value::Double(d) => {
-                write!(fmt, "<double>{}</double>", d)?;
-            }
-            Value::DateTime(date_time) => {
-                write!(
-                    fmt,
-                    "<dateTime.iso8601>{}</dateTime.iso8601>",
-                    format_datetime(&date_time)
-                )?;
-            }
+            Value::Int(i) => visitor.int(i),
+            Value::Int64(i) => visitor.int64(i),
+            Value::Bool(b) => visitor.bool(b),
+            Value::String(ref s) => visitor.string(s),
+            Value::Double(d) => visitor.double(d),
+            Value::DateTime(date_time) => visitor.datetime(date_time),
             Value::Base64(ref data) => {
-                write!(fmt, "<base64>{}</base64>", encode(data))?;
+                visitor.start_base64()?;
+                visitor.base64_chunk(data)?;
+                visitor.end_base64()
             }
             Value::Struct(ref map) => {
-                writeln!(fmt, "<struct>")?;
-                for (ref name, ref value) in map {
-                    writeln!(fmt, "<member>")?;
-                    writeln!(fmt, "<name>{}</name>", escape_xml(name))?;
-                    value.write_as_xml(fmt)?;
-                    writeln!(fmt, "</member>")?;
+                visitor.start_struct()?;
+                for (name, value) in map {
+                    visitor.member(name)?;
+                    value.visit(visitor)?;
                 }
-                write!(fmt, "</struct>")?;
+                visitor.end_struct()
             }
             Value::Array(ref array) => {
-                write!(fmt, "<array>")?;
-                writeln!(fmt, "<data>")?;
+                visitor.start_array()?;
                 for value in array {
-                    value.write_as_xml(fmt)?;
+                    value.visit(visitor)?;
                 }
-                write!(fmt, "</data>")?;
-                write!(fmt, "</array>")?;
-            }
-            Value::Nil => {
-                write!(fmt, "<nil/>")?;
+                visitor.end_array()
             }
+            Value::Nil => visitor.nil(),
+            Value::BigInteger(ref i) => visitor.big_integer(i),
+            Value::BigDecimal(ref d) => visitor.big_decimal(d),
+            Value::Serializable(ref data) => visitor.serializable(data),
+            Value::Dom(ref xml) => visitor.dom(xml),
         }
-
-        writeln!(fmt, "</value>")?;
-        Ok(())
     }
 
     /// Returns an inner struct or array value indexed by `index`.
@@ -175,6 +240,73 @@ impl Value {
         index.get(self)
     }
 
+    /// Returns a mutable reference to an inner struct or array value indexed by `index`.
+    ///
+    /// Returns `None` if the member doesn't exist or `self` is neither a struct nor an array.
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut Value> {
+        index.get_mut(self)
+    }
+
+    /// Inserts a member into a struct, returning the previous value of the member, if any.
+    ///
+    /// # Errors
+    ///
+    /// If `self` is not a struct, `value` is returned back as the error.
+    pub fn insert<S: Into<String>>(
+        &mut self,
+        name: S,
+        value: Value,
+    ) -> Result<Option<Value>, Value> {
+        match *self {
+            Value::Struct(ref mut map) => Ok(map.insert(name.into(), value)),
+            _ => Err(value),
+        }
+    }
+
+    /// Removes the struct member or array element indexed by `index` and returns it.
+    ///
+    /// Removing an array element shifts the following elements down, and removing a struct member
+    /// keeps the order of the remaining members. Returns `None` if the member doesn't exist or
+    /// `self` is neither a struct nor an array.
+    pub fn remove<I: Index>(&mut self, index: I) -> Option<Value> {
+        index.remove(self)
+    }
+
+    /// Appends an element to an array.
+    ///
+    /// # Errors
+    ///
+    /// If `self` is not an array, `value` is returned back as the error.
+    pub fn push(&mut self, value: Value) -> Result<(), Value> {
+        match *self {
+            Value::Array(ref mut array) => {
+                array.push(value);
+                Ok(())
+            }
+            _ => Err(value),
+        }
+    }
+
+    /// Returns the name of the XML-RPC type of this value, such as `i4` or `struct`.
+    pub(crate) fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Int(_) => "i4",
+            Value::Int64(_) => "i8",
+            Value::Bool(_) => "boolean",
+            Value::String(_) => "string",
+            Value::Double(_) => "double",
+            Value::DateTime(_) => "dateTime.iso8601",
+            Value::Base64(_) => "base64",
+            Value::Struct(_) => "struct",
+            Value::Array(_) => "array",
+            Value::Nil => "nil",
+            Value::BigInteger(_) => "ex:biginteger",
+            Value::BigDecimal(_) => "ex:bigdecimal",
+            Value::Serializable(_) => "ex:serializable",
+            Value::Dom(_) => "ex:dom",
+        }
+    }
+
     /// If the `Value` is a normal integer (`Value::Int`), returns associated value. Returns `None`
     /// otherwise.
     ///
@@ -242,13 +374,22 @@ impl Value {
     }
 
     /// If the `Value` is a struct, returns associated map. Returns `None` otherwise.
-    pub fn as_struct(&self) -> Option<&BTreeMap<String, Value>> {
+    pub fn as_struct(&self) -> Option<&Map> {
         match *self {
             Value::Struct(ref map) => Some(map),
             _ => None,
         }
     }
 
+    /// If the `Value` is a struct, returns a mutable reference to the associated map. Returns
+    /// `None` otherwise.
+    pub fn as_struct_mut(&mut self) -> Option<&mut Map> {
+        match *self {
+            Value::Struct(ref mut map) => Some(map),
+            _ => None,
+        }
+    }
+
     /// If the `Value` is an array, returns associated slice. Returns `None` otherwise.
     pub fn as_array(&self) -> Option<&[Value]> {
         match *self {
@@ -256,6 +397,15 @@ impl Value {
             _ => None,
         }
     }
+
+    /// If the `Value` is an array, returns a mutable reference to the associated vector. Returns
+    /// `None` otherwise.
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match *self {
+            Value::Array(ref mut array) => Some(array),
+            _ => None,
+        }
+    }
 }
 
 impl From<i32> for Value {
@@ -382,7 +532,7 @@ mod sealed {
     impl Sealed for str {}
     impl Sealed for String {}
     impl Sealed for usize {}
-    impl<'a, I> Sealed for &'a I where I: Sealed + ?Sized {}
+    impl<I> Sealed for &I where I: Sealed + ?Sized {}
 }
 
 /// A type that can be used to index into a [`Value`].
@@ -397,6 +547,14 @@ pub trait Index: sealed::Sealed {
     /// Gets an inner value of a given value represented by self.
     #[doc(hidden)]
     fn get<'v>(&self, value: &'v Value) -> Option<&'v Value>;
+
+    /// Gets a mutable reference to an inner value of a given value represented by self.
+    #[doc(hidden)]
+    fn get_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value>;
+
+    /// Removes an inner value of a given value represented by self.
+    #[doc(hidden)]
+    fn remove(&self, value: &mut Value) -> Option<Value>;
 }
 
 impl Index for str {
@@ -407,18 +565,38 @@ impl Index for str {
             None
         }
     }
-}
 
-impl Index for String {
-    fn get<'v>(&self, value: &'v Value) -> Option<&'v Value> {
-        if let Value::Struct(ref map) = *value {
-            map.get(self)
+    fn get_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        if let Value::Struct(ref mut map) = *value {
+            map.get_mut(self)
+        } else {
+            None
+        }
+    }
+
+    fn remove(&self, value: &mut Value) -> Option<Value> {
+        if let Value::Struct(ref mut map) = *value {
+            map.remove(self)
         } else {
             None
         }
     }
 }
 
+impl Index for String {
+    fn get<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        Index::get(self.as_str(), value)
+    }
+
+    fn get_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        Index::get_mut(self.as_str(), value)
+    }
+
+    fn remove(&self, value: &mut Value) -> Option<Value> {
+        Index::remove(self.as_str(), value)
+    }
+}
+
 impl Index for usize {
     fn get<'v>(&self, value: &'v Value) -> Option<&'v Value> {
         if let Value::Array(ref array) = *value {
@@ -427,15 +605,38 @@ impl Index for usize {
             None
         }
     }
+
+    fn get_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        if let Value::Array(ref mut array) = *value {
+            array.get_mut(*self)
+        } else {
+            None
+        }
+    }
+
+    fn remove(&self, value: &mut Value) -> Option<Value> {
+        match *value {
+            Value::Array(ref mut array) if *self < array.len() => Some(array.remove(*self)),
+            _ => None,
+        }
+    }
 }
 
-impl<'a, I> Index for &'a I
+impl<I> Index for &I
 where
     I: Index + ?Sized,
 {
     fn get<'v>(&self, value: &'v Value) -> Option<&'v Value> {
         (*self).get(value)
     }
+
+    fn get_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        (*self).get_mut(value)
+    }
+
+    fn remove(&self, value: &mut Value) -> Option<Value> {
+        (*self).remove(value)
+    }
 }
 
 impl<I> ::std::ops::Index<I> for Value
@@ -451,7 +652,6 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::BTreeMap;
     use std::str;
 
     #[test]
@@ -463,14 +663,14 @@ mod tests {
             .unwrap();
         assert_eq!(
             str::from_utf8(&output).unwrap(),
-            "<value><string>&lt;xml>&amp;nbsp;string</string></value>\n"
+            "<value><string>&lt;xml&gt;&amp;nbsp;string</string></value>\n"
         );
     }
 
     #[test]
     fn escapes_struct_member_names() {
         let mut output: Vec<u8> = Vec::new();
-        let mut map: BTreeMap<String, Value> = BTreeMap::new();
+        let mut map = Map::new();
         map.insert("x&<x".to_string(), Value::from(true));
 
         Value::Struct(map).write_as_xml(&mut output).unwrap();
@@ -479,7 +679,7 @@ mod tests {
 
     #[test]
     fn access_nested_values() {
-        let mut map: BTreeMap<String, Value> = BTreeMap::new();
+        let mut map = Map::new();
         map.insert("name".to_string(), Value::from("John Doe"));
         map.insert("age".to_string(), Value::from(37));
         map.insert(
@@ -503,4 +703,26 @@ mod tests {
         assert_eq!(value["age"].as_i32(), Some(37));
         assert_eq!(value["children"][0].as_str(), Some("Mark"));
     }
+
+    #[test]
+    fn writes_apache_extensions() {
+        let mut output: Vec<u8> = Vec::new();
+
+        Value::BigInteger("-12345678901234567890".to_string())
+            .write_as_xml(&mut output)
+            .unwrap();
+        assert_eq!(
+            str::from_utf8(&output).unwrap(),
+            "<value><ex:biginteger xmlns:ex=\"http://ws.apache.org/xmlrpc/namespaces/extensions\">-12345678901234567890</ex:biginteger></value>\n"
+        );
+
+        output.clear();
+        Value::Dom("<doc>1 &amp; 2</doc>".to_string())
+            .write_as_xml(&mut output)
+            .unwrap();
+        assert_eq!(
+            str::from_utf8(&output).unwrap(),
+            "<value><ex:dom xmlns:ex=\"http://ws.apache.org/xmlrpc/namespaces/extensions\"><doc>1 &amp; 2</doc></ex:dom></value>\n"
+        );
+    }
 }
@@ -0,0 +1,258 @@
+//! A borrowed counterpart to `Value`.
+
+use visitor::ValueVisitor;
+use writer::XmlWriter;
+use {Map, Value};
+
+use iso8601::DateTime;
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+
+/// An XML-RPC value that may borrow its strings and binary data.
+///
+/// This mirrors [`Value`], except that strings, struct member names and binary data are stored as
+/// [`Cow`]s. When a response held in memory is parsed with [`parse_response_ref`], strings that
+/// don't contain any escape sequences are borrowed from the document instead of being copied.
+/// A `ValueRef` can also borrow from a `Value` (see the `From<&Value>` impl).
+///
+/// Use [`to_value`] or [`into_owned`] to convert a `ValueRef` into a `Value`.
+///
+/// [`Value`]: enum.Value.html
+/// [`Cow`]: https://doc.rust-lang.org/std/borrow/enum.Cow.html
+/// [`parse_response_ref`]: fn.parse_response_ref.html
+/// [`to_value`]: #method.to_value
+/// [`into_owned`]: #method.into_owned
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ValueRef<'a> {
+    /// A 32-bit signed integer (`<i4>` or `<int>`).
+    Int(i32),
+    /// A 64-bit signed integer (`<i8>`).
+    Int64(i64),
+    /// A boolean value (`<boolean>`, 0 == `false`, 1 == `true`).
+    Bool(bool),
+    /// A string (`<string>`).
+    String(Cow<'a, str>),
+    /// A double-precision IEEE 754 floating point number (`<double>`).
+    Double(f64),
+    /// An ISO 8601 formatted date/time value (`<dateTime.iso8601>`).
+    DateTime(DateTime),
+    /// Base64-encoded binary data (`<base64>`).
+    Base64(Cow<'a, [u8]>),
+    /// A mapping of named values (`<struct>`).
+    Struct(Map<Cow<'a, str>, ValueRef<'a>>),
+    /// A list of arbitrary (heterogeneous) values (`<array>`).
+    Array(Vec<ValueRef<'a>>),
+    /// The empty (Unit) value (`<nil/>`).
+    Nil,
+    /// An arbitrary-precision integer in decimal notation (`<ex:biginteger>`).
+    BigInteger(Cow<'a, str>),
+    /// An arbitrary-precision decimal number (`<ex:bigdecimal>`).
+    BigDecimal(Cow<'a, str>),
+    /// A serialized Java object (`<ex:serializable>`).
+    Serializable(Cow<'a, [u8]>),
+    /// A DOM node, stored as an XML string (`<ex:dom>`).
+    Dom(Cow<'a, str>),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Copies this `ValueRef` into an owned `Value`.
+    pub fn to_value(&self) -> Value {
+        match *self {
+            ValueRef::Int(i) => Value::Int(i),
+            ValueRef::Int64(i) => Value::Int64(i),
+            ValueRef::Bool(b) => Value::Bool(b),
+            ValueRef::String(ref s) => Value::String(s.to_string()),
+            ValueRef::Double(d) => Value::Double(d),
+            ValueRef::DateTime(date_time) => Value::DateTime(date_time),
+            ValueRef::Base64(ref data) => Value::Base64(data.to_vec()),
+            ValueRef::Struct(ref map) => Value::Struct(
+                map.iter()
+                    .map(|(name, value)| (name.to_string(), value.to_value()))
+                    .collect(),
+            ),
+            ValueRef::Array(ref array) => {
+                Value::Array(array.iter().map(ValueRef::to_value).collect())
+            }
+            ValueRef::Nil => Value::Nil,
+            ValueRef::BigInteger(ref i) => Value::BigInteger(i.to_string()),
+            ValueRef::BigDecimal(ref d) => Value::BigDecimal(d.to_string()),
+            ValueRef::Serializable(ref data) => Value::Serializable(data.to_vec()),
+            ValueRef::Dom(ref xml) => Value::Dom(xml.to_string()),
+        }
+    }
+
+    /// Converts this `ValueRef` into an owned `Value`, copying only borrowed data.
+    pub fn into_owned(self) -> Value {
+        match self {
+            ValueRef::Int(i) => Value::Int(i),
+            ValueRef::Int64(i) => Value::Int64(i),
+            ValueRef::Bool(b) => Value::Bool(b),
+            ValueRef::String(s) => Value::String(s.into_owned()),
+            ValueRef::Double(d) => Value::Double(d),
+            ValueRef::DateTime(date_time) => Value::DateTime(date_time),
+            ValueRef::Base64(data) => Value::Base64(data.into_owned()),
+            ValueRef::Struct(map) => Value::Struct(
+                map.into_iter()
+                    .map(|(name, value)| (name.into_owned(), value.into_owned()))
+                    .collect(),
+            ),
+            ValueRef::Array(array) => {
+                Value::Array(array.into_iter().map(ValueRef::into_owned).collect())
+            }
+            ValueRef::Nil => Value::Nil,
+            ValueRef::BigInteger(i) => Value::BigInteger(i.into_owned()),
+            ValueRef::BigDecimal(d) => Value::BigDecimal(d.into_owned()),
+            ValueRef::Serializable(data) => Value::Serializable(data.into_owned()),
+            ValueRef::Dom(xml) => Value::Dom(xml.into_owned()),
+        }
+    }
+
+    /// Formats this `ValueRef` as an XML `<value>` element, like [`Value::write_as_xml`].
+    ///
+    /// # Errors
+    ///
+    /// Any error reported by the writer will be propagated to the caller.
+    ///
+    /// [`Value::write_as_xml`]: enum.Value.html#method.write_as_xml
+    pub fn write_as_xml<W: Write>(&self, fmt: &mut W) -> io::Result<()> {
+        self.visit(&mut XmlWriter::new(fmt))
+    }
+
+    /// Passes this `ValueRef` to `visitor`, in the same way as the parser would.
+    pub(crate) fn visit<V: ValueVisitor>(&self, visitor: &mut V) -> Result<(), V::Error> {
+        match *self {
+            ValueRef::Int(i) => visitor.int(i),
+            ValueRef::Int64(i) => visitor.int64(i),
+            ValueRef::Bool(b) => visitor.bool(b),
+            ValueRef::String(ref s) => visitor.string(s),
+            ValueRef::Double(d) => visitor.double(d),
+            ValueRef::DateTime(date_time) => visitor.datetime(date_time),
+            ValueRef::Base64(ref data) => {
+                visitor.start_base64()?;
+                visitor.base64_chunk(data)?;
+                visitor.end_base64()
+            }
+            ValueRef::Struct(ref map) => {
+                visitor.start_struct()?;
+                for (name, value) in map {
+                    visitor.member(name)?;
+                    value.visit(visitor)?;
+                }
+                visitor.end_struct()
+            }
+            ValueRef::Array(ref array) => {
+                visitor.start_array()?;
+                for value in array {
+                    value.visit(visitor)?;
+                }
+                visitor.end_array()
+            }
+            ValueRef::Nil => visitor.nil(),
+            ValueRef::BigInteger(ref i) => visitor.big_integer(i),
+            ValueRef::BigDecimal(ref d) => visitor.big_decimal(d),
+            ValueRef::Serializable(ref data) => visitor.serializable(data),
+            ValueRef::Dom(ref xml) => visitor.dom(xml),
+        }
+    }
+}
+
+impl<'a> From<&'a Value> for ValueRef<'a> {
+    fn from(value: &'a Value) -> Self {
+        match *value {
+            Value::Int(i) => ValueRef::Int(i),
+            Value::Int64(i) => ValueRef::Int64(i),
+            Value::Bool(b) => ValueRef::Bool(b),
+            Value::String(ref s) => ValueRef::String(Cow::Borrowed(s)),
+            Value::Double(d) => ValueRef::Double(d),
+            Value::DateTime(date_time) => ValueRef::DateTime(date_time),
+            Value::Base64(ref data) => ValueRef::Base64(Cow::Borrowed(data)),
+            Value::Struct(ref map) => ValueRef::Struct(
+                map.iter()
+                    .map(|(name, value)| (Cow::Borrowed(&**name), ValueRef::from(value)))
+                    .collect(),
+            ),
+            Value::Array(ref array) => ValueRef::Array(array.iter().map(ValueRef::from).collect()),
+            Value::Nil => ValueRef::Nil,
+            Value::BigInteger(ref i) => ValueRef::BigInteger(Cow::Borrowed(i)),
+            Value::BigDecimal(ref d) => ValueRef::BigDecimal(Cow::Borrowed(d)),
+            Value::Serializable(ref data) => ValueRef::Serializable(Cow::Borrowed(data)),
+            Value::Dom(ref xml) => ValueRef::Dom(Cow::Borrowed(xml)),
+        }
+    }
+}
+
+impl From<Value> for ValueRef<'static> {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Int(i) => ValueRef::Int(i),
+            Value::Int64(i) => ValueRef::Int64(i),
+            Value::Bool(b) => ValueRef::Bool(b),
+            Value::String(s) => ValueRef::String(Cow::Owned(s)),
+            Value::Double(d) => ValueRef::Double(d),
+            Value::DateTime(date_time) => ValueRef::DateTime(date_time),
+            Value::Base64(data) => ValueRef::Base64(Cow::Owned(data)),
+            Value::Struct(map) => ValueRef::Struct(
+                map.into_iter()
+                    .map(|(name, value)| (Cow::Owned(name), ValueRef::from(value)))
+                    .collect(),
+            ),
+            Value::Array(array) => ValueRef::Array(array.into_iter().map(ValueRef::from).collect()),
+            Value::Nil => ValueRef::Nil,
+            Value::BigInteger(i) => ValueRef::BigInteger(Cow::Owned(i)),
+            Value::BigDecimal(d) => ValueRef::BigDecimal(Cow::Owned(d)),
+            Value::Serializable(data) => ValueRef::Serializable(Cow::Owned(data)),
+            Value::Dom(xml) => ValueRef::Dom(Cow::Owned(xml)),
+        }
+    }
+}
+
+impl<'a> From<ValueRef<'a>> for Value {
+    fn from(value: ValueRef<'a>) -> Self {
+        value.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_to_and_from_value() {
+        let mut members = Map::new();
+        members.insert("name".to_string(), Value::from("chair"));
+        members.insert("data".to_string(), Value::Base64(vec![1, 2, 3]));
+        members.insert(
+            "list".to_string(),
+            Value::Array(vec![Value::Int(1), Value::Nil]),
+        );
+        let value = Value::Struct(members);
+
+        let borrowed = ValueRef::from(&value);
+        match borrowed {
+            ValueRef::Struct(ref members) => match members["name"] {
+                ValueRef::String(Cow::Borrowed("chair")) => {}
+                ref other => panic!("unexpected value: {:?}", other),
+            },
+            ref other => panic!("unexpected value: {:?}", other),
+        }
+        assert_eq!(borrowed.to_value(), value);
+        assert_eq!(Value::from(borrowed), value);
+        assert_eq!(ValueRef::from(value.clone()).into_owned(), value);
+    }
+
+    #[test]
+    fn writes_same_xml_as_value() {
+        let value = Value::Array(vec![
+            Value::from("<a>"),
+            Value::Base64(vec![0xff]),
+            Value::BigInteger("123".to_string()),
+        ]);
+        let mut expected = Vec::new();
+        value.write_as_xml(&mut expected).unwrap();
+        let mut output = Vec::new();
+        ValueRef::from(&value).write_as_xml(&mut output).unwrap();
+        assert_eq!(output, expected);
+    }
+}
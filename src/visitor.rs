@@ -0,0 +1,498 @@
+//! Event-based access to parsed values.
+
+use error::ParseError;
+use {Map, Value};
+
+use iso8601::DateTime;
+use std::io;
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// Receives the contents of an XML-RPC value while it is being parsed.
+///
+/// Implementing this trait allows processing a response without building a [`Value`] tree: use
+/// [`visit_response`] to drive a visitor directly from the XML parser. All methods have empty
+/// default implementations, so only the callbacks of interest need to be implemented.
+///
+/// Scalar values result in a single call to the corresponding method. Base64 data is reported by
+/// a call to [`start_base64`], followed by any number of calls to [`base64_chunk`] and a call to
+/// [`end_base64`]. Arrays result in a call to [`start_array`], followed by the callbacks for each
+/// element and a call to [`end_array`]. Structs are reported in the same way, except that the
+/// callbacks for the value of each member are preceded by a call to [`member`] with the member's
+/// name. Visitors may rely on the events being balanced in this way.
+///
+/// Every method can return an error to abort parsing, which is then returned by
+/// [`visit_response`]. Since the parser reports its own errors in the same way, the error type has
+/// to be convertible from [`ParseError`].
+///
+/// [`Value`]: enum.Value.html
+/// [`visit_response`]: fn.visit_response.html
+/// [`start_base64`]: #method.start_base64
+/// [`base64_chunk`]: #method.base64_chunk
+/// [`end_base64`]: #method.end_base64
+/// [`start_array`]: #method.start_array
+/// [`end_array`]: #method.end_array
+/// [`member`]: #method.member
+/// [`ParseError`]: enum.ParseError.html
+#[allow(unused_variables)]
+pub trait ValueVisitor {
+    /// The error returned to abort visiting a value.
+    type Error;
+
+    /// Called for an `<i4>` or `<int>` value (or an `<ex:i1>` or `<ex:i2>` value).
+    fn int(&mut self, value: i32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for an `<i8>` value.
+    fn int64(&mut self, value: i64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for a `<boolean>` value.
+    fn bool(&mut self, value: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for a `<string>` value, or a value without type tag.
+    fn string(&mut self, value: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for a `<double>` value (or an `<ex:float>` value).
+    fn double(&mut self, value: f64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for a `<dateTime.iso8601>` value.
+    fn datetime(&mut self, value: DateTime) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called at the start of a `<base64>` value.
+    fn start_base64(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called with a chunk of the decoded data of a `<base64>` value.
+    ///
+    /// Large values are passed in multiple chunks, which have to be concatenated to obtain the
+    /// complete data.
+    fn base64_chunk(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called at the end of a `<base64>` value.
+    fn end_base64(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called at the start of a `<struct>`.
+    fn start_struct(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called with the name of a struct member, before the callbacks for its value.
+    fn member(&mut self, name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called at the end of a `<struct>`.
+    fn end_struct(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called at the start of an `<array>`.
+    fn start_array(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called at the end of an `<array>`.
+    fn end_array(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for a `<nil/>` value.
+    fn nil(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for an `<ex:biginteger>` value.
+    fn big_integer(&mut self, value: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for an `<ex:bigdecimal>` value.
+    fn big_decimal(&mut self, value: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for an `<ex:serializable>` value.
+    fn serializable(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Called for an `<ex:dom>` value.
+    fn dom(&mut self, xml: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl<V: ValueVisitor + ?Sized> ValueVisitor for &mut V {
+    type Error = V::Error;
+
+    fn int(&mut self, value: i32) -> Result<(), V::Error> {
+        (**self).int(value)
+    }
+    fn int64(&mut self, value: i64) -> Result<(), V::Error> {
+        (**self).int64(value)
+    }
+    fn bool(&mut self, value: bool) -> Result<(), V::Error> {
+        (**self).bool(value)
+    }
+    fn string(&mut self, value: &str) -> Result<(), V::Error> {
+        (**self).string(value)
+    }
+    fn double(&mut self, value: f64) -> Result<(), V::Error> {
+        (**self).double(value)
+    }
+    fn datetime(&mut self, value: DateTime) -> Result<(), V::Error> {
+        (**self).datetime(value)
+    }
+    fn start_base64(&mut self) -> Result<(), V::Error> {
+        (**self).start_base64()
+    }
+    fn base64_chunk(&mut self, data: &[u8]) -> Result<(), V::Error> {
+        (**self).base64_chunk(data)
+    }
+    fn end_base64(&mut self) -> Result<(), V::Error> {
+        (**self).end_base64()
+    }
+    fn start_struct(&mut self) -> Result<(), V::Error> {
+        (**self).start_struct()
+    }
+    fn member(&mut self, name: &str) -> Result<(), V::Error> {
+        (**self).member(name)
+    }
+    fn end_struct(&mut self) -> Result<(), V::Error> {
+        (**self).end_struct()
+    }
+    fn start_array(&mut self) -> Result<(), V::Error> {
+        (**self).start_array()
+    }
+    fn end_array(&mut self) -> Result<(), V::Error> {
+        (**self).end_array()
+    }
+    fn nil(&mut self) -> Result<(), V::Error> {
+        (**self).nil()
+    }
+    fn big_integer(&mut self, value: &str) -> Result<(), V::Error> {
+        (**self).big_integer(value)
+    }
+    fn big_decimal(&mut self, value: &str) -> Result<(), V::Error> {
+        (**self).big_decimal(value)
+    }
+    fn serializable(&mut self, data: &[u8]) -> Result<(), V::Error> {
+        (**self).serializable(data)
+    }
+    fn dom(&mut self, xml: &str) -> Result<(), V::Error> {
+        (**self).dom(xml)
+    }
+}
+
+/// A `ValueVisitor` that builds a `Value`.
+#[derive(Debug, Default)]
+pub struct ValueBuilder {
+    /// The arrays and structs currently being built, innermost last.
+    stack: Vec<Container>,
+    /// The data of the `<base64>` value currently being built.
+    base64: Vec<u8>,
+    /// The complete value.
+    value: Option<Value>,
+}
+
+#[derive(Debug)]
+enum Container {
+    Array(Vec<Value>),
+    /// A struct, and the name of the member whose value is being built.
+    Struct(Map, Option<String>),
+}
+
+impl ValueBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the built value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no complete value has been visited.
+    pub fn finish(self) -> Value {
+        self.value.expect("no value was visited")
+    }
+
+    fn push(&mut self, value: Value) -> ParseResult<()> {
+        match self.stack.last_mut() {
+            Some(&mut Container::Array(ref mut elements)) => elements.push(value),
+            Some(&mut Container::Struct(ref mut members, ref mut name)) => {
+                let name = name
+                    .take()
+                    .ok_or_else(|| invalid_event("struct member without name"))?;
+                members.insert(name, value);
+            }
+            None => self.value = Some(value),
+        }
+        Ok(())
+    }
+}
+
+/// Returns the error reported by `ValueBuilder` for events that don't form a valid value.
+fn invalid_event(message: &str) -> ParseError {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
+
+impl ValueVisitor for ValueBuilder {
+    type Error = ParseError;
+
+    fn int(&mut self, value: i32) -> ParseResult<()> {
+        self.push(Value::Int(value))
+    }
+
+    fn int64(&mut self, value: i64) -> ParseResult<()> {
+        self.push(Value::Int64(value))
+    }
+
+    fn bool(&mut self, value: bool) -> ParseResult<()> {
+        self.push(Value::Bool(value))
+    }
+
+    fn string(&mut self, value: &str) -> ParseResult<()> {
+        self.push(Value::String(value.to_string()))
+    }
+
+    fn double(&mut self, value: f64) -> ParseResult<()> {
+        self.push(Value::Double(value))
+    }
+
+    fn datetime(&mut self, value: DateTime) -> ParseResult<()> {
+        self.push(Value::DateTime(value))
+    }
+
+    fn start_base64(&mut self) -> ParseResult<()> {
+        self.base64.clear();
+        Ok(())
+    }
+
+    fn base64_chunk(&mut self, data: &[u8]) -> ParseResult<()> {
+        self.base64.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn end_base64(&mut self) -> ParseResult<()> {
+        let data = ::std::mem::take(&mut self.base64);
+        self.push(Value::Base64(data))
+    }
+
+    fn start_struct(&mut self) -> ParseResult<()> {
+        self.stack.push(Container::Struct(Map::new(), None));
+        Ok(())
+    }
+
+    fn member(&mut self, name: &str) -> ParseResult<()> {
+        // Members are only meaningful inside of a struct (see `Parser::parse_member`)
+        if let Some(&mut Container::Struct(_, ref mut member)) = self.stack.last_mut() {
+            *member = Some(name.to_string());
+        }
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> ParseResult<()> {
+        match self.stack.pop() {
+            Some(Container::Struct(members, _)) => self.push(Value::Struct(members)),
+            _ => Err(invalid_event("unbalanced end of struct")),
+        }
+    }
+
+    fn start_array(&mut self) -> ParseResult<()> {
+        self.stack.push(Container::Array(Vec::new()));
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> ParseResult<()> {
+        match self.stack.pop() {
+            Some(Container::Array(elements)) => self.push(Value::Array(elements)),
+            _ => Err(invalid_event("unbalanced end of array")),
+        }
+    }
+
+    fn nil(&mut self) -> ParseResult<()> {
+        self.push(Value::Nil)
+    }
+
+    fn big_integer(&mut self, value: &str) -> ParseResult<()> {
+        self.push(Value::BigInteger(value.to_string()))
+    }
+
+    fn big_decimal(&mut self, value: &str) -> ParseResult<()> {
+        self.push(Value::BigDecimal(value.to_string()))
+    }
+
+    fn serializable(&mut self, data: &[u8]) -> ParseResult<()> {
+        self.push(Value::Serializable(data.to_vec()))
+    }
+
+    fn dom(&mut self, xml: &str) -> ParseResult<()> {
+        self.push(Value::Dom(xml.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use {visit_response, ParserOptions};
+
+    /// Records the callbacks it receives.
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl ValueVisitor for Recorder {
+        type Error = ParseError;
+
+        fn int(&mut self, value: i32) -> ParseResult<()> {
+            self.0.push(format!("int {}", value));
+            Ok(())
+        }
+        fn string(&mut self, value: &str) -> ParseResult<()> {
+            self.0.push(format!("string {}", value));
+            Ok(())
+        }
+        fn base64_chunk(&mut self, data: &[u8]) -> ParseResult<()> {
+            self.0.push(format!("base64 {:?}", data));
+            Ok(())
+        }
+        fn start_struct(&mut self) -> ParseResult<()> {
+            self.0.push("{".to_string());
+            Ok(())
+        }
+        fn member(&mut self, name: &str) -> ParseResult<()> {
+            self.0.push(format!("member {}", name));
+            Ok(())
+        }
+        fn end_struct(&mut self) -> ParseResult<()> {
+            self.0.push("}".to_string());
+            Ok(())
+        }
+        fn start_array(&mut self) -> ParseResult<()> {
+            self.0.push("[".to_string());
+            Ok(())
+        }
+        fn end_array(&mut self) -> ParseResult<()> {
+            self.0.push("]".to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn visits_values() {
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse><params><param><value><struct>
+  <member><name>ids</name><value><array><data>
+    <value><int>1</int></value>
+    <value><i4>2</i4></value>
+  </data></array></value></member>
+  <member><name>name</name><value>test</value></member>
+  <member><name>data</name><value><base64>AQID</base64></value></member>
+  <member><name>flag</name><value><boolean>1</boolean></value></member>
+</struct></value></param></params></methodResponse>"#;
+
+        let mut recorder = Recorder::default();
+        let result = visit_response(&mut xml.as_bytes(), &ParserOptions::new(), &mut recorder);
+        assert_eq!(result, Ok(Ok(())));
+        assert_eq!(
+            recorder.0,
+            vec![
+                "{",
+                "member ids",
+                "[",
+                "int 1",
+                "int 2",
+                "]",
+                "member name",
+                "string test",
+                "member data",
+                "base64 [1, 2, 3]",
+                "member flag",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_values() {
+        let mut members = Map::new();
+        members.insert(
+            "a".to_string(),
+            Value::Array(vec![Value::Nil, Value::Int(1)]),
+        );
+        members.insert("b".to_string(), Value::Base64(vec![1, 2, 3, 4]));
+
+        let mut builder = ValueBuilder::new();
+        builder.start_struct().unwrap();
+        builder.member("a").unwrap();
+        builder.start_array().unwrap();
+        builder.nil().unwrap();
+        builder.int(1).unwrap();
+        builder.end_array().unwrap();
+        builder.member("b").unwrap();
+        builder.start_base64().unwrap();
+        builder.base64_chunk(&[1, 2]).unwrap();
+        builder.base64_chunk(&[3, 4]).unwrap();
+        builder.end_base64().unwrap();
+        builder.end_struct().unwrap();
+        assert_eq!(builder.finish(), Value::Struct(members));
+    }
+
+    #[test]
+    fn rejects_unbalanced_events() {
+        let mut builder = ValueBuilder::new();
+        builder.start_array().unwrap();
+        assert!(builder.end_struct().is_err());
+
+        let mut builder = ValueBuilder::new();
+        builder.start_struct().unwrap();
+        assert!(builder.int(1).is_err());
+    }
+
+    /// Stops after a number of elements.
+    struct Take(usize);
+
+    impl ValueVisitor for Take {
+        type Error = ParseError;
+
+        fn int(&mut self, _value: i32) -> ParseResult<()> {
+            if self.0 == 0 {
+                return Err(io::Error::new(io::ErrorKind::Other, "enough").into());
+            }
+            self.0 -= 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn visitors_abort_parsing() {
+        let xml = r#"<?xml version="1.0"?>
+<methodResponse><params><param><value><array><data>
+    <value><i4>1</i4></value>
+    <value><i4>2</i4></value>
+    <value><i4>3</i4></value>
+</data></array></value></param></params></methodResponse>"#;
+
+        let mut take = Take(2);
+        let result = visit_response(&mut xml.as_bytes(), &ParserOptions::new(), &mut take);
+        assert!(result.unwrap_err().to_string().contains("enough"));
+        let mut take = Take(3);
+        let result = visit_response(&mut xml.as_bytes(), &ParserOptions::new(), &mut take);
+        assert_eq!(result, Ok(Ok(())));
+    }
+}
@@ -0,0 +1,902 @@
+//! Formatting of requests and values as XML.
+
+use utils::{escape_xml, format_datetime, APACHE_EXTENSIONS_NS};
+use visitor::ValueVisitor;
+
+use base64::encode;
+use encoding_rs::{Encoding, UTF_8};
+use iso8601::DateTime;
+use std::fmt;
+use std::io::{self, Write};
+use xml::reader::{EventReader, XmlEvent};
+
+/// Options controlling how requests are formatted as XML.
+///
+/// The defaults produce the same output as previous versions of this crate. The other options
+/// exist for servers that only accept a particular dialect.
+///
+/// Pass the options to [`Request::writer_options`].
+///
+/// [`Request::writer_options`]: struct.Request.html#method.writer_options
+#[derive(Clone, Debug)]
+pub struct WriterOptions {
+    encoding: &'static Encoding,
+    layout: WriterLayout,
+    int_tag: bool,
+    bare_strings: bool,
+    apache_nil: bool,
+    apache_extensions: bool,
+    xml_declaration: bool,
+    double_precision: Option<usize>,
+    non_finite_doubles: NonFiniteDoubles,
+    datetime_format: DateTimeFormat,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            encoding: UTF_8,
+            layout: WriterLayout::default(),
+            int_tag: false,
+            bare_strings: false,
+            apache_nil: false,
+            apache_extensions: false,
+            xml_declaration: true,
+            double_precision: None,
+            non_finite_doubles: NonFiniteDoubles::default(),
+            datetime_format: DateTimeFormat::default(),
+        }
+    }
+}
+
+impl WriterOptions {
+    /// Creates the default `WriterOptions`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates `WriterOptions` that only produce documents strictly conforming to the
+    /// specification, like [`ParserOptions::strict`] does for parsing.
+    ///
+    /// In addition to the defaults, this fails to write NaN and infinite doubles (see
+    /// [`NonFiniteDoubles::Error`]) instead of writing them verbatim.
+    ///
+    /// [`ParserOptions::strict`]: struct.ParserOptions.html#method.strict
+    /// [`NonFiniteDoubles::Error`]: enum.NonFiniteDoubles.html#variant.Error
+    pub fn strict() -> Self {
+        Self::default().non_finite_doubles(NonFiniteDoubles::Error)
+    }
+
+    /// Sets the character encoding of the written document.
+    ///
+    /// The encoding is declared in the XML declaration, and characters that can not be represented
+    /// in it are written as numeric character references. Since UTF-16 can not be used as an
+    /// output encoding, it is replaced with UTF-8 (see [`Encoding::output_encoding`]).
+    ///
+    /// Defaults to UTF-8.
+    ///
+    /// [`Encoding::output_encoding`]: https://docs.rs/encoding_rs/0.8/encoding_rs/struct.Encoding.html#method.output_encoding
+    pub fn encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = encoding.output_encoding();
+        self
+    }
+
+    /// Returns the character encoding of the written document.
+    pub fn get_encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// Sets how the written elements are separated by whitespace.
+    ///
+    /// Defaults to [`WriterLayout::Lines`].
+    ///
+    /// [`WriterLayout::Lines`]: enum.WriterLayout.html#variant.Lines
+    pub fn layout(mut self, layout: WriterLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets whether 32-bit integers are written as `<int>` instead of `<i4>`.
+    ///
+    /// Disabled by default.
+    pub fn int_tag(mut self, int: bool) -> Self {
+        self.int_tag = int;
+        self
+    }
+
+    /// Sets whether strings are written as the bare content of their `<value>`, without a
+    /// `<string>` tag.
+    ///
+    /// Disabled by default.
+    pub fn bare_strings(mut self, bare: bool) -> Self {
+        self.bare_strings = bare;
+        self
+    }
+
+    /// Sets whether `Value::Nil` is written as the [Apache extension type] `<ex:nil/>` instead of
+    /// `<nil/>`.
+    ///
+    /// Disabled by default.
+    ///
+    /// [Apache extension type]: https://ws.apache.org/xmlrpc/types.html
+    pub fn apache_nil(mut self, apache: bool) -> Self {
+        self.apache_nil = apache;
+        self
+    }
+
+    /// Sets whether values are written using the [Apache XML-RPC extension types] where Apache
+    /// XML-RPC uses them, for servers that have `enabledForExtensions` set.
+    ///
+    /// * `Value::Int64` is written as `<ex:i8>` instead of `<i8>`.
+    /// * `Value::DateTime` is written as `<ex:dateTime>` instead of `<dateTime.iso8601>`. Since
+    ///   Apache parses these as `xs:dateTime`, they're always written with dashes, in the
+    ///   [`DateTimeFormat::Utc`] format if that or [`DateTimeFormat::NaiveUtc`] is selected and in
+    ///   the [`DateTimeFormat::Extended`] format otherwise.
+    /// * `Value::Nil` is written as `<ex:nil/>`, as with [`apache_nil`].
+    ///
+    /// Doubles are still written as `<double>`, which Apache uses for Java's `double`;
+    /// `<ex:float>` only holds 32-bit floats and would lose precision. The Apache-specific
+    /// variants of `Value` are always written as extension types.
+    ///
+    /// Disabled by default. Use [`ParserOptions::apache_extensions`] to parse these types.
+    ///
+    /// [Apache XML-RPC extension types]: https://ws.apache.org/xmlrpc/types.html
+    /// [`DateTimeFormat::Utc`]: enum.DateTimeFormat.html#variant.Utc
+    /// [`DateTimeFormat::NaiveUtc`]: enum.DateTimeFormat.html#variant.NaiveUtc
+    /// [`DateTimeFormat::Extended`]: enum.DateTimeFormat.html#variant.Extended
+    /// [`apache_nil`]: #method.apache_nil
+    /// [`ParserOptions::apache_extensions`]: struct.ParserOptions.html#method.apache_extensions
+    pub fn apache_extensions(mut self, apache: bool) -> Self {
+        self.apache_extensions = apache;
+        self
+    }
+
+    /// Sets whether requests start with an XML declaration (`<?xml version="1.0" ...?>`).
+    ///
+    /// Without the declaration, the encoding of the document is not declared, so this should only
+    /// be disabled for UTF-8 encoded documents.
+    ///
+    /// Enabled by default.
+    pub fn xml_declaration(mut self, declaration: bool) -> Self {
+        self.xml_declaration = declaration;
+        self
+    }
+
+    /// Sets the number of digits written after the decimal point of a `<double>`, or `None` to
+    /// write the shortest representation that parses back to the same value.
+    ///
+    /// Doubles are always written in plain decimal notation, since the specification doesn't
+    /// allow exponents. With `None`, very large or small values can thus be written with many
+    /// digits.
+    ///
+    /// Defaults to `None`.
+    pub fn double_precision(mut self, precision: Option<usize>) -> Self {
+        self.double_precision = precision;
+        self
+    }
+
+    /// Sets how NaN and infinite doubles, which the specification can not represent, are written.
+    ///
+    /// Defaults to [`NonFiniteDoubles::Verbatim`], which writes invalid XML-RPC. Consider using
+    /// [`NonFiniteDoubles::Error`] or [`NonFiniteDoubles::Nil`] instead.
+    ///
+    /// [`NonFiniteDoubles::Verbatim`]: enum.NonFiniteDoubles.html#variant.Verbatim
+    /// [`NonFiniteDoubles::Error`]: enum.NonFiniteDoubles.html#variant.Error
+    /// [`NonFiniteDoubles::Nil`]: enum.NonFiniteDoubles.html#variant.Nil
+    pub fn non_finite_doubles(mut self, policy: NonFiniteDoubles) -> Self {
+        self.non_finite_doubles = policy;
+        self
+    }
+
+    /// Sets the format of `<dateTime.iso8601>` values.
+    ///
+    /// Defaults to [`DateTimeFormat::Basic`].
+    ///
+    /// [`DateTimeFormat::Basic`]: enum.DateTimeFormat.html#variant.Basic
+    pub fn datetime_format(mut self, format: DateTimeFormat) -> Self {
+        self.datetime_format = format;
+        self
+    }
+}
+
+/// The whitespace written between XML elements.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WriterLayout {
+    /// Puts most elements on a line of their own, without indentation.
+    ///
+    /// Scalar values are written on a single line, such as `<value><i4>1</i4></value>`, and the
+    /// tags opening a struct or array are combined, as in `<value><array><data>`.
+    #[default]
+    Lines,
+    /// Doesn't write any whitespace between elements.
+    Compact,
+    /// Like `Lines`, but puts every container tag on a line of its own and indents nested elements
+    /// by two spaces.
+    Pretty,
+}
+
+/// How NaN and infinite doubles are written.
+///
+/// The XML-RPC specification has no representation for these values, so servers handle them
+/// differently, if at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFiniteDoubles {
+    /// Writes them as formatted by Rust, that is, as `NaN`, `inf` or `-inf`.
+    ///
+    /// This is the default for compatibility with previous versions, but the resulting `<double>`
+    /// is invalid XML-RPC that most servers reject. Use [`Error`] to catch these values before
+    /// sending them, or [`Nil`] to replace them.
+    ///
+    /// [`Error`]: #variant.Error
+    /// [`Nil`]: #variant.Nil
+    #[default]
+    Verbatim,
+    /// Writes them as `NaN`, `Infinity` or `-Infinity`, which is understood by Java-based servers
+    /// such as Apache XML-RPC.
+    Apache,
+    /// Writes them as `<nil/>` (or `<ex:nil/>`, see [`WriterOptions::apache_nil`]).
+    ///
+    /// [`WriterOptions::apache_nil`]: struct.WriterOptions.html#method.apache_nil
+    Nil,
+    /// Fails to write the value with an error of kind `InvalidData`.
+    Error,
+}
+
+/// The format of written `<dateTime.iso8601>` values.
+///
+/// Servers disagree about the format of date/time values, so this picks one of the common
+/// variants. In all formats, milliseconds are only written when they're not zero.
+///
+/// Since a zero offset can't be distinguished from an unspecified time zone, date/times without an
+/// offset are assumed to be in UTC already when converting them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DateTimeFormat {
+    /// The format of the example in the specification, such as `19980717T14:08:55`, followed by
+    /// the offset from UTC (`+01:00`) unless it is zero.
+    #[default]
+    Basic,
+    /// Like `Basic`, but with dashes between the parts of the date, as in `1998-07-17T14:08:55`.
+    Extended,
+    /// Converts the value to UTC and writes it with dashes and a `Z` suffix, as in
+    /// `1998-07-17T14:08:55Z`. This is the format used by many Java-based servers.
+    Utc,
+    /// Converts the value to UTC and writes it like `Basic`, without any time zone. This is
+    /// intended for servers that reject time zones, such as WordPress.
+    NaiveUtc,
+}
+
+/// A `ValueVisitor` that writes the visited value as a `<value>` element.
+///
+/// Errors reported by the writer are returned from the visitor methods, which stops the value
+/// from being written any further.
+pub struct XmlWriter<'w, W> {
+    writer: &'w mut W,
+    options: WriterOptions,
+    /// Nesting depth of the element being written (only tracked for indentation).
+    depth: usize,
+    /// For each array or struct being written, whether a `<member>` element is open.
+    open_members: Vec<bool>,
+    /// The data of the `<base64>` value being written.
+    base64: Vec<u8>,
+}
+
+impl<'w, W: Write> XmlWriter<'w, W> {
+    pub fn new(writer: &'w mut W) -> Self {
+        Self::with_options(writer, &WriterOptions::default())
+    }
+
+    /// Creates a writer formatting values according to `options`. The encoding is ignored, the
+    /// output is always UTF-8.
+    pub fn with_options(writer: &'w mut W, options: &WriterOptions) -> Self {
+        XmlWriter {
+            writer,
+            options: options.clone(),
+            depth: 0,
+            open_members: Vec::new(),
+            base64: Vec::new(),
+        }
+    }
+
+    fn write(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        self.writer.write_fmt(args)
+    }
+
+    /// Writes `args` on a line of its own, as far as the layout allows.
+    pub fn line(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        if self.options.layout == WriterLayout::Pretty {
+            for _ in 0..self.depth {
+                self.write(format_args!("  "))?;
+            }
+        }
+        self.write(args)?;
+        self.end_line()
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        if self.options.layout != WriterLayout::Compact {
+            self.write(format_args!("\n"))?;
+        }
+        Ok(())
+    }
+
+    /// Opens the nested elements named by `tags`.
+    ///
+    /// The `Pretty` layout puts each tag on a line of its own, the others put them on a single
+    /// line.
+    pub fn open(&mut self, tags: &[&str]) -> io::Result<()> {
+        if self.options.layout == WriterLayout::Pretty {
+            for tag in tags {
+                self.line(format_args!("<{}>", tag))?;
+                self.depth += 1;
+            }
+            Ok(())
+        } else {
+            for tag in tags {
+                self.write(format_args!("<{}>", tag))?;
+            }
+            self.end_line()
+        }
+    }
+
+    /// Closes the elements named by `tags`, innermost first.
+    pub fn close(&mut self, tags: &[&str]) -> io::Result<()> {
+        if self.options.layout == WriterLayout::Pretty {
+            for tag in tags {
+                self.depth -= 1;
+                self.line(format_args!("</{}>", tag))?;
+            }
+            Ok(())
+        } else {
+            for tag in tags {
+                self.write(format_args!("</{}>", tag))?;
+            }
+            self.end_line()
+        }
+    }
+
+    /// Closes the root element of a document, without a line break.
+    pub fn close_root(&mut self, tag: &str) -> io::Result<()> {
+        if self.options.layout == WriterLayout::Pretty {
+            self.depth -= 1;
+        }
+        self.write(format_args!("</{}>", tag))
+    }
+
+    /// Writes a scalar `<value>`, with `content` enclosed in the tag `tag`.
+    fn scalar(&mut self, tag: &str, content: fmt::Arguments<'_>) -> io::Result<()> {
+        self.line(format_args!(
+            "<value><{tag}>{}</{tag}></value>",
+            content,
+            tag = tag
+        ))
+    }
+
+    /// Writes an Apache extension value. The extension namespace is declared on each extension
+    /// tag, so that values can be written independently of the surrounding document.
+    fn write_extension(&mut self, tag: &str, content: &str) -> io::Result<()> {
+        self.line(format_args!(
+            r#"<value><ex:{tag} xmlns:ex="{ns}">{content}</ex:{tag}></value>"#,
+            tag = tag,
+            ns = APACHE_EXTENSIONS_NS,
+            content = content
+        ))
+    }
+}
+
+/// Checks that `xml` consists of a single well-formed element (and possibly whitespace, comments
+/// and processing instructions), so that it can be written as the content of an `<ex:dom>` without
+/// changing the structure of the surrounding document.
+fn is_single_element(xml: &str) -> bool {
+    // Parse it in the context it's written in, where the `ex` prefix is bound
+    let document = format!(
+        r#"<ex:dom xmlns:ex="{}">{}</ex:dom>"#,
+        APACHE_EXTENSIONS_NS, xml
+    );
+    let mut reader = EventReader::from_str(&document);
+    let mut depth = 0;
+    let mut elements = 0;
+    loop {
+        match reader.next() {
+            Ok(XmlEvent::StartElement { .. }) => {
+                if depth == 1 {
+                    elements += 1;
+                }
+                depth += 1;
+            }
+            Ok(XmlEvent::EndElement { .. }) => {
+                depth -= 1;
+                if depth == 0 {
+                    // Only the closing tag appended above may close the `<ex:dom>`
+                    return elements == 1 && matches!(reader.next(), Ok(XmlEvent::EndDocument));
+                }
+            }
+            Ok(XmlEvent::Characters(_)) | Ok(XmlEvent::CData(_)) if depth == 1 => return false,
+            Ok(XmlEvent::EndDocument) | Err(_) => return false,
+            Ok(_) => {}
+        }
+    }
+}
+
+/// A document that can be written with an `XmlWriter`.
+pub(crate) trait XmlDocument {
+    /// Writes the root element of the document.
+    fn write_root<W: Write>(&self, writer: &mut XmlWriter<'_, W>) -> io::Result<()>;
+}
+
+/// Writes `document` in the encoding selected in `options`, preceded by the XML declaration if
+/// enabled.
+pub(crate) fn write_document<W: Write, D: XmlDocument>(
+    fmt: &mut W,
+    document: &D,
+    options: &WriterOptions,
+) -> io::Result<()> {
+    let encoding = options.get_encoding();
+    if encoding == UTF_8 {
+        return write_utf8(fmt, document, options, "utf-8");
+    }
+
+    // Write the document as UTF-8 first, then transcode it
+    let mut utf8 = Vec::new();
+    write_utf8(&mut utf8, document, options, encoding.name())?;
+    let utf8 =
+        String::from_utf8(utf8).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let (bytes, _, _) = encoding.encode(&utf8);
+    fmt.write_all(&bytes)
+}
+
+/// Writes `document` as UTF-8, declaring the encoding as `encoding`.
+fn write_utf8<W: Write, D: XmlDocument>(
+    fmt: &mut W,
+    document: &D,
+    options: &WriterOptions,
+    encoding: &str,
+) -> io::Result<()> {
+    let mut writer = XmlWriter::with_options(fmt, options);
+    if options.xml_declaration {
+        writer.line(format_args!(
+            r#"<?xml version="1.0" encoding="{}"?>"#,
+            encoding
+        ))?;
+    }
+    document.write_root(&mut writer)
+}
+
+impl<'w, W: Write> ValueVisitor for XmlWriter<'w, W> {
+    type Error = io::Error;
+
+    fn int(&mut self, value: i32) -> io::Result<()> {
+        let tag = if self.options.int_tag { "int" } else { "i4" };
+        self.scalar(tag, format_args!("{}", value))
+    }
+
+    fn int64(&mut self, value: i64) -> io::Result<()> {
+        if self.options.apache_extensions {
+            self.write_extension("i8", &value.to_string())
+        } else {
+            self.scalar("i8", format_args!("{}", value))
+        }
+    }
+
+    fn bool(&mut self, value: bool) -> io::Result<()> {
+        let value = if value { "1" } else { "0" };
+        self.scalar("boolean", format_args!("{}", value))
+    }
+
+    fn string(&mut self, value: &str) -> io::Result<()> {
+        if self.options.bare_strings {
+            self.line(format_args!("<value>{}</value>", escape_xml(value)))
+        } else {
+            self.scalar("string", format_args!("{}", escape_xml(value)))
+        }
+    }
+
+    fn double(&mut self, value: f64) -> io::Result<()> {
+        if !value.is_finite() {
+            match self.options.non_finite_doubles {
+                NonFiniteDoubles::Verbatim => {}
+                NonFiniteDoubles::Apache => {
+                    let value = if value.is_nan() {
+                        "NaN"
+                    } else if value > 0.0 {
+                        "Infinity"
+                    } else {
+                        "-Infinity"
+                    };
+                    return self.scalar("double", format_args!("{}", value));
+                }
+                NonFiniteDoubles::Nil => return self.nil(),
+                NonFiniteDoubles::Error => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} can not be written as an XML-RPC double", value),
+                    ));
+                }
+            }
+        }
+
+        match self.options.double_precision {
+            Some(precision) => self.scalar("double", format_args!("{:.*}", precision, value)),
+            None => self.scalar("double", format_args!("{}", value)),
+        }
+    }
+
+    fn datetime(&mut self, value: DateTime) -> io::Result<()> {
+        // Apache parses `<ex:dateTime>` as `xs:dateTime`, which requires dashes
+        let format = match self.options.datetime_format {
+            format if !self.options.apache_extensions => format,
+            DateTimeFormat::Utc | DateTimeFormat::NaiveUtc => DateTimeFormat::Utc,
+            DateTimeFormat::Basic | DateTimeFormat::Extended => DateTimeFormat::Extended,
+        };
+        match format_datetime(&value, format) {
+            Some(formatted) if self.options.apache_extensions => {
+                self.write_extension("dateTime", &formatted)
+            }
+            Some(formatted) => self.scalar("dateTime.iso8601", format_args!("{}", formatted)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} can not be written as an XML-RPC date/time", value),
+            )),
+        }
+    }
+
+    fn start_base64(&mut self) -> io::Result<()> {
+        self.base64.clear();
+        Ok(())
+    }
+
+    fn base64_chunk(&mut self, data: &[u8]) -> io::Result<()> {
+        self.base64.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn end_base64(&mut self) -> io::Result<()> {
+        let encoded = encode(&self.base64);
+        self.scalar("base64", format_args!("{}", encoded))
+    }
+
+    fn start_struct(&mut self) -> io::Result<()> {
+        self.open(&["value", "struct"])?;
+        self.open_members.push(false);
+        Ok(())
+    }
+
+    fn member(&mut self, name: &str) -> io::Result<()> {
+        match self.open_members.last() {
+            Some(&true) => self.close(&["member"])?,
+            Some(&false) => {}
+            None => return Ok(()), // not inside of a struct
+        }
+        self.open(&["member"])?;
+        self.line(format_args!("<name>{}</name>", escape_xml(name)))?;
+        if let Some(open) = self.open_members.last_mut() {
+            *open = true;
+        }
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> io::Result<()> {
+        if let Some(true) = self.open_members.pop() {
+            self.close(&["member"])?;
+        }
+        self.close(&["struct", "value"])
+    }
+
+    fn start_array(&mut self) -> io::Result<()> {
+        self.open(&["value", "array", "data"])?;
+        self.open_members.push(false);
+        Ok(())
+    }
+
+    fn end_array(&mut self) -> io::Result<()> {
+        self.open_members.pop();
+        self.close(&["data", "array", "value"])
+    }
+
+    fn nil(&mut self) -> io::Result<()> {
+        if self.options.apache_nil || self.options.apache_extensions {
+            self.line(format_args!(
+                r#"<value><ex:nil xmlns:ex="{}"/></value>"#,
+                APACHE_EXTENSIONS_NS
+            ))
+        } else {
+            self.line(format_args!("<value><nil/></value>"))
+        }
+    }
+
+    fn big_integer(&mut self, value: &str) -> io::Result<()> {
+        self.write_extension("biginteger", &escape_xml(value))
+    }
+
+    fn big_decimal(&mut self, value: &str) -> io::Result<()> {
+        self.write_extension("bigdecimal", &escape_xml(value))
+    }
+
+    fn serializable(&mut self, data: &[u8]) -> io::Result<()> {
+        self.write_extension("serializable", &encode(data))
+    }
+
+    fn dom(&mut self, xml: &str) -> io::Result<()> {
+        if !is_single_element(xml) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the content of a DOM value must be a single well-formed XML element",
+            ));
+        }
+        self.write_extension("dom", xml)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iso8601::{self, Date, Time};
+    use {parse_response, Map, ParserOptions, Request, Value};
+
+    fn request_xml(options: WriterOptions) -> String {
+        let mut members = Map::new();
+        members.insert("id".to_string(), Value::Int(7));
+        members.insert("tags".to_string(), Value::Array(vec![Value::from("a&b")]));
+        let request = Request::new("update")
+            .arg(Value::Struct(members))
+            .arg(Value::Nil)
+            .writer_options(options);
+
+        let mut output = Vec::new();
+        request.write_as_xml(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn writes_compact_documents() {
+        assert_eq!(
+            request_xml(WriterOptions::new().layout(WriterLayout::Compact)),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?><methodCall><methodName>update</methodName>\
+             <params><param><value><struct><member><name>id</name><value><i4>7</i4></value>\
+             </member><member><name>tags</name><value><array><data>\
+             <value><string>a&amp;b</string></value></data></array></value></member>\
+             </struct></value></param><param><value><nil/></value></param></params></methodCall>"
+        );
+    }
+
+    #[test]
+    fn writes_pretty_documents() {
+        assert_eq!(
+            request_xml(WriterOptions::new().layout(WriterLayout::Pretty)),
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<methodCall>
+  <methodName>update</methodName>
+  <params>
+    <param>
+      <value>
+        <struct>
+          <member>
+            <name>id</name>
+            <value><i4>7</i4></value>
+          </member>
+          <member>
+            <name>tags</name>
+            <value>
+              <array>
+                <data>
+                  <value><string>a&amp;b</string></value>
+                </data>
+              </array>
+            </value>
+          </member>
+        </struct>
+      </value>
+    </param>
+    <param>
+      <value><nil/></value>
+    </param>
+  </params>
+</methodCall>"#
+        );
+    }
+
+    #[test]
+    fn writes_dialect_options() {
+        let options = WriterOptions::new()
+            .layout(WriterLayout::Compact)
+            .int_tag(true)
+            .bare_strings(true)
+            .apache_nil(true)
+            .xml_declaration(false);
+        assert_eq!(
+            request_xml(options),
+            "<methodCall><methodName>update</methodName>\
+             <params><param><value><struct><member><name>id</name><value><int>7</int></value>\
+             </member><member><name>tags</name><value><array><data>\
+             <value>a&amp;b</value></data></array></value></member>\
+             </struct></value></param><param>\
+             <value><ex:nil xmlns:ex=\"http://ws.apache.org/xmlrpc/namespaces/extensions\"/></value>\
+             </param></params></methodCall>"
+        );
+    }
+
+    fn double_xml(value: f64, options: &WriterOptions) -> io::Result<String> {
+        let mut output = Vec::new();
+        Value::Double(value).write_as_xml_with(&mut output, options)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn writes_doubles_in_decimal_notation() {
+        let options = WriterOptions::new();
+        assert_eq!(
+            double_xml(-12.214, &options).unwrap(),
+            "<value><double>-12.214</double></value>\n"
+        );
+        assert_eq!(
+            double_xml(1e21, &options).unwrap(),
+            "<value><double>1000000000000000000000</double></value>\n"
+        );
+
+        let options = WriterOptions::new().double_precision(Some(3));
+        assert_eq!(
+            double_xml(2.0 / 3.0, &options).unwrap(),
+            "<value><double>0.667</double></value>\n"
+        );
+        assert_eq!(
+            double_xml(1e-10, &options).unwrap(),
+            "<value><double>0.000</double></value>\n"
+        );
+    }
+
+    #[test]
+    fn writes_non_finite_doubles() {
+        let xml =
+            |value, policy| double_xml(value, &WriterOptions::new().non_finite_doubles(policy));
+
+        assert_eq!(
+            xml(f64::NEG_INFINITY, NonFiniteDoubles::Verbatim).unwrap(),
+            "<value><double>-inf</double></value>\n"
+        );
+        assert_eq!(
+            xml(f64::INFINITY, NonFiniteDoubles::Apache).unwrap(),
+            "<value><double>Infinity</double></value>\n"
+        );
+        assert_eq!(
+            xml(f64::NAN, NonFiniteDoubles::Apache).unwrap(),
+            "<value><double>NaN</double></value>\n"
+        );
+        assert_eq!(
+            xml(f64::NAN, NonFiniteDoubles::Nil).unwrap(),
+            "<value><nil/></value>\n"
+        );
+        assert_eq!(
+            xml(1.5, NonFiniteDoubles::Error).unwrap(),
+            "<value><double>1.5</double></value>\n"
+        );
+        let err = xml(f64::INFINITY, NonFiniteDoubles::Error).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = double_xml(f64::NAN, &WriterOptions::strict()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reports_unwritable_datetimes() {
+        let date_time = DateTime {
+            date: Date::Ordinal {
+                year: 2021,
+                ddd: 400,
+            },
+            time: Time::default(),
+        };
+        let value = Value::Array(vec![Value::DateTime(date_time)]);
+        let err = value.write_as_xml(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn writes_datetime_formats() {
+        let value = Value::DateTime(iso8601::datetime("2024-01-02T03:04:05-05:00").unwrap());
+        for (format, expected) in &[
+            (DateTimeFormat::Basic, "20240102T03:04:05-05:00"),
+            (DateTimeFormat::Extended, "2024-01-02T03:04:05-05:00"),
+            (DateTimeFormat::Utc, "2024-01-02T08:04:05Z"),
+            (DateTimeFormat::NaiveUtc, "20240102T08:04:05"),
+        ] {
+            let mut output = Vec::new();
+            let options = WriterOptions::new().datetime_format(*format);
+            value.write_as_xml_with(&mut output, &options).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format!(
+                    "<value><dateTime.iso8601>{}</dateTime.iso8601></value>\n",
+                    expected
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn writes_apache_extensions() {
+        let date_time = iso8601::datetime("2024-01-02T03:04:05.250-05:00").unwrap();
+        let value = Value::Array(vec![
+            Value::Int(1),
+            Value::Int64(-100100100100),
+            Value::Double(0.1),
+            Value::DateTime(date_time),
+            Value::Nil,
+            Value::BigInteger("12345678901234567890".to_string()),
+        ]);
+        let options = WriterOptions::new()
+            .layout(WriterLayout::Compact)
+            .apache_extensions(true);
+        let mut xml = b"<?xml version=\"1.0\"?><methodResponse><params><param>".to_vec();
+        value.write_as_xml_with(&mut xml, &options).unwrap();
+        xml.extend_from_slice(b"</param></params></methodResponse>");
+
+        let ex = r#"xmlns:ex="http://ws.apache.org/xmlrpc/namespaces/extensions""#;
+        let written = String::from_utf8(xml.clone()).unwrap();
+        assert!(written.contains("<value><i4>1</i4></value>"));
+        assert!(written.contains(&format!("<ex:i8 {}>-100100100100</ex:i8>", ex)));
+        assert!(written.contains("<value><double>0.1</double></value>"));
+        assert!(written.contains(&format!(
+            "<ex:dateTime {}>2024-01-02T03:04:05.250-05:00</ex:dateTime>",
+            ex
+        )));
+        assert!(written.contains(&format!("<ex:nil {}/>", ex)));
+
+        let response = parse_response(&mut &xml[..], &ParserOptions::new().apache_extensions(true));
+        assert_eq!(response, Ok(Ok(value)));
+        assert!(parse_response(&mut &xml[..], &ParserOptions::new()).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_dom_values() {
+        for xml in &[
+            "<doc/>",
+            " <doc a=\"1\"><!-- c --><ex:x>1 &amp; 2</ex:x></doc>\n",
+            "<p:doc xmlns:p=\"urn:p\"><![CDATA[<]]></p:doc>",
+        ] {
+            let value = Value::Dom(xml.to_string());
+            value.write_as_xml(&mut Vec::new()).unwrap();
+        }
+
+        for xml in &[
+            "",
+            "text",
+            "<a/><b/>",
+            "<a/>text",
+            "<a>",
+            "</a>",
+            "<a>1 &nbsp; 2</a>",
+            "<p:a/>",
+            "<?xml version=\"1.0\"?><a/>",
+            "<a/></ex:dom></value></param><param><value><i4>1</i4></value></param>",
+            "<a/></ex:dom><ex:dom xmlns:ex=\"urn:x\"><b/>",
+        ] {
+            let value = Value::Dom(xml.to_string());
+            let err = value.write_as_xml(&mut Vec::new()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", xml);
+        }
+    }
+
+    #[test]
+    fn written_values_can_be_parsed() {
+        let value = Value::Array(vec![
+            Value::from(""),
+            Value::from(" padded "),
+            Value::Int(-1),
+            Value::Struct(Map::new()),
+        ]);
+        for layout in &[
+            WriterLayout::Lines,
+            WriterLayout::Compact,
+            WriterLayout::Pretty,
+        ] {
+            let options = WriterOptions::new()
+                .layout(*layout)
+                .int_tag(true)
+                .bare_strings(true);
+            let mut xml = b"<?xml version=\"1.0\"?><methodResponse><params><param>".to_vec();
+            value.write_as_xml_with(&mut xml, &options).unwrap();
+            xml.extend_from_slice(b"</param></params></methodResponse>");
+
+            let response = parse_response(&mut &xml[..], &ParserOptions::new());
+            assert_eq!(response, Ok(Ok(value.clone())), "{:?}", layout);
+        }
+    }
+}
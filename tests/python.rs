@@ -10,7 +10,7 @@ use std::thread::sleep;
 use std::time::{Duration, Instant};
 
 const PORT: u16 = 8000;
-const URL: &'static str = "http://127.0.0.1:8000";
+const URL: &str = "http://127.0.0.1:8000";
 
 /// Kills a child process when dropped.
 struct Reap(Child);
@@ -48,18 +48,16 @@ fn setup() -> Result<Reap, ()> {
         }
 
         // try to connect to the server
-        match TcpStream::connect(("127.0.0.1", PORT)) {
-            Ok(_) => {
-                // server should work now
-                println!(
-                    "connected to server after {:?} (iteration {})",
-                    Instant::now() - start,
-                    iteration
-                );
-                return Ok(Reap(child));
-            }
-            Err(_) => {} // not yet ready
+        if TcpStream::connect(("127.0.0.1", PORT)).is_ok() {
+            // server should work now
+            println!(
+                "connected to server after {:?} (iteration {})",
+                Instant::now() - start,
+                iteration
+            );
+            return Ok(Reap(child));
         }
+        // not yet ready
 
         sleep(Duration::from_millis(50));
 